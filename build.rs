@@ -43,12 +43,207 @@ fn has_atomic(name: &str) -> io::Result<bool> {
 
 macro_rules! test_atomic {
     ($name:literal) => {
-        if cfg!(feature = $name) && has_atomic($name)? {
+        println!(concat!("cargo:rustc-check-cfg=cfg(has_", $name, "_atomic)"));
+        if cfg!(feature = $name) && !force_fallback() && has_atomic($name)? {
             println!(concat!("cargo:rustc-cfg=has_", $name, "_atomic"));
         }
     };
 }
 
+fn cfg_list(name: &str) -> Vec<String> {
+    env::var(name).unwrap_or_default().split(',').map(str::to_owned).collect()
+}
+
+/// Returns whether the crate is built with `--cfg loom`, which replaces the
+/// fallback types' synchronization primitives with those of [loom].
+///
+/// [loom]: https://docs.rs/loom/0.7
+fn loom() -> bool {
+    env::var_os("CARGO_CFG_LOOM").is_some()
+}
+
+/// Returns whether every type alias should point to a lock-based fallback
+/// type, even where a built-in atomic is available. This is requested with
+/// the feature `force-fallback` or by setting `ATOMIC_INT_FORCE_FALLBACK` to
+/// any value other than `0`, so that a downstream crate's CI can test the
+/// fallback types without changing its manifest. This has no effect with
+/// loom, under which the fallback types can't be created in `const` contexts.
+fn force_fallback() -> bool {
+    if loom() {
+        return false;
+    }
+    cfg!(feature = "force-fallback")
+        || matches!(
+            env::var("ATOMIC_INT_FORCE_FALLBACK").as_deref(),
+            Ok(v) if !v.is_empty() && v != "0",
+        )
+}
+
+#[path = "build/asm_atomic64.rs"]
+mod asm_atomic64;
+
+/// Enables the `ldrexd`/`strexd` implementation of the 64-bit atomics on ARM
+/// targets that don't have built-in 64-bit atomics (see
+/// [`asm_atomic64::applies`]).
+fn test_asm_atomic64() {
+    println!("cargo:rustc-check-cfg=cfg(asm_atomic64)");
+    if !cfg!(feature = "asm-atomics") {
+        return;
+    }
+    // `portable-atomic` replaces every fallback type.
+    if cfg!(feature = "portable-atomic") {
+        return;
+    }
+    // The fallback types are requested instead.
+    if force_fallback() {
+        return;
+    }
+    // loom can't model inline assembly, so the lock-based fallback is used.
+    if loom() {
+        return;
+    }
+    if asm_atomic64::applies(
+        &env::var("TARGET").unwrap_or_default(),
+        &env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default(),
+        &cfg_list("CARGO_CFG_TARGET_HAS_ATOMIC"),
+        &cfg_list("CARGO_CFG_TARGET_FEATURE"),
+    ) {
+        println!("cargo:rustc-cfg=asm_atomic64");
+    }
+}
+
+/// Enables the `cmpxchg16b`/`casp` implementation of the 128-bit atomics on
+/// x86-64 and little-endian AArch64 targets.
+fn test_asm_atomic128() -> bool {
+    println!("cargo:rustc-check-cfg=cfg(asm_atomic128)");
+    if !cfg!(feature = "asm-atomics") {
+        return false;
+    }
+    // `portable-atomic` replaces every fallback type.
+    if cfg!(feature = "portable-atomic") {
+        return false;
+    }
+    // The fallback types are requested instead.
+    if force_fallback() {
+        return false;
+    }
+    // loom can't model inline assembly, so the lock-based fallback is used.
+    if loom() {
+        return false;
+    }
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let endian = env::var("CARGO_CFG_TARGET_ENDIAN").unwrap_or_default();
+    let env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+    let features = cfg_list("CARGO_CFG_TARGET_FEATURE");
+    let static_cx16 = features.iter().any(|s| s == "cmpxchg16b");
+    let supported = match &*arch {
+        // Run-time detection uses `cpuid`, which SGX doesn't allow.
+        "x86_64" => static_cx16 || env != "sgx",
+        "aarch64" => endian == "little",
+        _ => false,
+    };
+    if supported {
+        println!("cargo:rustc-cfg=asm_atomic128");
+    }
+    supported
+}
+
+/// Emits `fallback_128` when `AtomicI128` and `AtomicU128` are lock-based
+/// fallback types, which doctests of fallback-only methods rely on. This is
+/// the case unless `portable-atomic` (without [`force_fallback`]) or
+/// `asm_atomic128` applies.
+fn fallback_128(asm_atomic128: bool) {
+    println!("cargo:rustc-check-cfg=cfg(fallback_128)");
+    if !asm_atomic128
+        && (force_fallback() || !cfg!(feature = "portable-atomic"))
+    {
+        println!("cargo:rustc-cfg=fallback_128");
+    }
+}
+
+/// Emits `spinlock` when the fallback types are protected by spinlocks, which
+/// is the case unless `critical-section` (a critical section instead) is
+/// enabled.
+fn spinlock() {
+    println!("cargo:rustc-check-cfg=cfg(spinlock)");
+    if !cfg!(feature = "critical-section") {
+        println!("cargo:rustc-cfg=spinlock");
+    }
+}
+
+/// Emits `seqlock` when the feature `seqlock` applies: it requires the
+/// spinlock (see [`spinlock`]), and loom can't model its optimistic reads of
+/// the fallback types (only the protocol itself, in the models in
+/// `seqlock.rs`).
+fn seqlock() {
+    println!("cargo:rustc-check-cfg=cfg(seqlock)");
+    if cfg!(feature = "seqlock")
+        && !cfg!(feature = "critical-section")
+        && !loom()
+    {
+        println!("cargo:rustc-cfg=seqlock");
+    }
+}
+
+/// Emits `compact_fallback` when the fallback types consist only of their
+/// values (and a zero-sized field that aligns them like the built-in
+/// atomics). The feature `striped` guarantees this, whatever other features
+/// are enabled, by keeping the lock (and the counter added by `seqlock`) in
+/// the stripes; `poisoning`, which adds a flag to each atomic, is rejected
+/// with `striped` in lib.rs. `critical-section` removes the lock too, but not
+/// the flag added by `poisoning`. loom's cell is larger than its value, so
+/// this never applies with loom.
+fn compact_fallback() {
+    println!("cargo:rustc-check-cfg=cfg(compact_fallback)");
+    if (cfg!(feature = "striped") || cfg!(feature = "critical-section"))
+        && !cfg!(feature = "poisoning")
+        && !loom()
+    {
+        println!("cargo:rustc-cfg=compact_fallback");
+    }
+}
+
+/// Emits `no_atomic_bool` when the target lacks atomic read-modify-write
+/// operations on `AtomicBool` (e.g., `thumbv6m` and `riscv32i`), which the
+/// spinlocks, `AtomicFlag`, `AtomicOnce`, and `SpinLock` need. The crate
+/// then builds only with the feature `critical-section` (which replaces the
+/// spinlocks) or `portable-atomic` (whose `AtomicBool` the spinlocks then
+/// use), and without those types. Passing `--cfg no_atomic_bool` simulates
+/// such a target, which the tests use to check this requirement.
+fn no_atomic_bool() {
+    println!("cargo:rustc-check-cfg=cfg(no_atomic_bool)");
+    if !cfg_list("CARGO_CFG_TARGET_HAS_ATOMIC").iter().any(|s| s == "8") {
+        println!("cargo:rustc-cfg=no_atomic_bool");
+    }
+}
+
+/// Emits `sig_atomic_t_long` when C's `sig_atomic_t` is `long` rather than
+/// `int`, which is the case only on 64-bit FreeBSD. Neither `core::ffi` nor
+/// `libc` provides this type, so the crate defines it from this cfg.
+fn sig_atomic_t() {
+    println!("cargo:rustc-check-cfg=cfg(sig_atomic_t_long)");
+    let os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let width = env::var("CARGO_CFG_TARGET_POINTER_WIDTH").unwrap_or_default();
+    if os == "freebsd" && width == "64" {
+        println!("cargo:rustc-cfg=sig_atomic_t_long");
+    }
+}
+
+/// Passes the number of lock stripes used by the feature `striped` to the
+/// crate as `ATOMIC_INT_LOCK_STRIPES`.
+fn lock_stripes() {
+    const VAR: &str = "ATOMIC_INT_LOCK_STRIPES";
+    println!("cargo:rerun-if-env-changed={VAR}");
+    let stripes = match env::var(VAR) {
+        Ok(s) => match s.trim().parse::<usize>() {
+            Ok(n) if n > 0 && n <= 1 << 16 => n,
+            _ => panic!("{} must be an integer from 1 to 65536", VAR),
+        },
+        Err(_) => 64,
+    };
+    println!("cargo:rustc-env={VAR}={stripes}");
+}
+
 fn main() -> io::Result<()> {
     env::set_current_dir("feature-test")?;
     test_atomic!("c_char");
@@ -62,6 +257,38 @@ fn main() -> io::Result<()> {
     test_atomic!("c_ulong");
     test_atomic!("c_longlong");
     test_atomic!("c_ulonglong");
+    test_atomic!("size_t");
+    test_atomic!("ssize_t");
+    test_atomic!("intptr_t");
+    test_atomic!("uintptr_t");
+    test_atomic!("ptrdiff_t");
+    test_atomic!("char16_t");
+    test_atomic!("char32_t");
+    test_atomic!("sig_atomic_t");
+    test_atomic!("wchar_t");
+    test_atomic!("time_t");
+    test_atomic!("off_t");
+    test_atomic!("clock_t");
+    test_atomic!("pid_t");
+    test_atomic!("uid_t");
+    test_atomic!("gid_t");
+    test_atomic!("socklen_t");
+    test_atomic!("in_addr_t");
+    test_atomic!("in_port_t");
+    println!("cargo:rustc-check-cfg=cfg(loom)");
+    println!("cargo:rustc-check-cfg=cfg(force_fallback)");
+    println!("cargo:rerun-if-env-changed=ATOMIC_INT_FORCE_FALLBACK");
+    if force_fallback() {
+        println!("cargo:rustc-cfg=force_fallback");
+    }
+    test_asm_atomic64();
+    fallback_128(test_asm_atomic128());
+    spinlock();
+    seqlock();
+    compact_fallback();
+    no_atomic_bool();
+    sig_atomic_t();
+    lock_stripes();
     println!("cargo:rerun-if-changed=feature-test");
     Ok(())
 }
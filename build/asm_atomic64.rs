@@ -0,0 +1,68 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Target detection for the `ldrexd`/`strexd` implementation of the 64-bit
+//! atomics. This is a separate file so that `tests/asm_atomic64.rs` can
+//! check it against the configuration of every ARM target known to `rustc`,
+//! as those targets can't be built without their standard libraries.
+
+/// Returns whether the `ldrexd`/`strexd` implementation of the 64-bit
+/// atomics applies to `target`, given its `target_arch`, `target_has_atomic`,
+/// and `target_feature` values.
+///
+/// This is the case on ARM targets without built-in 64-bit atomics that have
+/// the exclusive doubleword instructions: ARMv6K (in ARM mode) and later,
+/// except for the M profile. Every built-in ARMv7-A/R target has 64-bit
+/// atomics, so in practice, this applies to ARMv6K targets (like
+/// `armv6k-nintendo-3ds`) and custom targets. The ARM `target_feature`s that
+/// indicate the architecture (like `v7` and `mclass`) are unstable, and so
+/// aren't reported on stable Rust; when they are, they take precedence over
+/// the target name, from which the architecture is otherwise taken.
+pub fn applies(
+    target: &str,
+    arch: &str,
+    has_atomic: &[String],
+    features: &[String],
+) -> bool {
+    let has = |list: &[String], name: &str| list.iter().any(|s| s == name);
+    if arch != "arm" || has(has_atomic, "64") || has(features, "mclass") {
+        return false;
+    }
+    if has(features, "v7") || has(features, "v6k") {
+        return true;
+    }
+    // Custom targets are given as paths to JSON files.
+    let name = target.rsplit(['/', '\\']).next().unwrap_or(target);
+    let name = name.split('-').next().unwrap_or(name);
+    let (thumb, version) = if let Some(v) = name.strip_prefix("thumb") {
+        (true, v)
+    } else if let Some(v) = name.strip_prefix("armeb") {
+        (false, v)
+    } else if let Some(v) = name.strip_prefix("arm") {
+        (false, v)
+    } else {
+        return false;
+    };
+    let m_profile = ["v6m", "v7m", "v7em", "v8m"];
+    if m_profile.iter().any(|p| version.starts_with(p)) {
+        return false;
+    }
+    version.starts_with("v7")
+        || version.starts_with("v8")
+        || (version.starts_with("v6k") && !thumb)
+}
@@ -18,7 +18,28 @@
 
 #![no_std]
 #[allow(unused_imports)]
-use core::ffi;
+mod ffi {
+    pub use core::ffi::*;
+    pub use core::primitive::{isize as ssize_t, usize as size_t};
+    pub use core::primitive::{isize as intptr_t, usize as uintptr_t};
+    pub use core::primitive::isize as ptrdiff_t;
+    pub use core::primitive::{u16 as char16_t, u32 as char32_t};
+    // `int` or `long`, depending on the target (see `sig_atomic_t` in
+    // build.rs), so like the types below, this is the wider of the two.
+    pub use core::ffi::c_long as sig_atomic_t;
+    // `libc` isn't available here, so these are the widest types that
+    // `libc` uses for them on any target. If a target's real type is
+    // narrower, the crate falls back unnecessarily, but never the other way
+    // around.
+    pub use core::primitive::{i64 as time_t, i64 as off_t, i64 as clock_t};
+    // `libc` defines this as an integer of 16 or 32 bits on every target.
+    pub use core::primitive::i32 as wchar_t;
+    // `libc` defines these as integers of at most 32 bits on every target.
+    pub use core::primitive::{i32 as pid_t, u32 as uid_t, u32 as gid_t};
+    pub use core::primitive::{u32 as socklen_t, u32 as in_addr_t};
+    // `libc` defines this as a 16-bit integer on every target.
+    pub use core::primitive::u16 as in_port_t;
+}
 
 pub trait HasAtomic {}
 
@@ -59,3 +80,21 @@ impl_c_test!(c_long, test_has_c_long_atomic);
 impl_c_test!(c_ulong, test_has_c_ulong_atomic);
 impl_c_test!(c_longlong, test_has_c_longlong_atomic);
 impl_c_test!(c_ulonglong, test_has_c_ulonglong_atomic);
+impl_c_test!(size_t, test_has_size_t_atomic);
+impl_c_test!(ssize_t, test_has_ssize_t_atomic);
+impl_c_test!(intptr_t, test_has_intptr_t_atomic);
+impl_c_test!(uintptr_t, test_has_uintptr_t_atomic);
+impl_c_test!(ptrdiff_t, test_has_ptrdiff_t_atomic);
+impl_c_test!(char16_t, test_has_char16_t_atomic);
+impl_c_test!(char32_t, test_has_char32_t_atomic);
+impl_c_test!(sig_atomic_t, test_has_sig_atomic_t_atomic);
+impl_c_test!(wchar_t, test_has_wchar_t_atomic);
+impl_c_test!(time_t, test_has_time_t_atomic);
+impl_c_test!(off_t, test_has_off_t_atomic);
+impl_c_test!(clock_t, test_has_clock_t_atomic);
+impl_c_test!(pid_t, test_has_pid_t_atomic);
+impl_c_test!(uid_t, test_has_uid_t_atomic);
+impl_c_test!(gid_t, test_has_gid_t_atomic);
+impl_c_test!(socklen_t, test_has_socklen_t_atomic);
+impl_c_test!(in_addr_t, test_has_in_addr_t_atomic);
+impl_c_test!(in_port_t, test_has_in_port_t_atomic);
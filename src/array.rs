@@ -0,0 +1,151 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::{AtomicIntExt, HasAtomic};
+use core::fmt;
+use core::sync::atomic::Ordering;
+
+/// A fixed-size array of `N` atomic integers of type `T`.
+///
+/// Each element is the atomic alias for `T` in this crate (e.g.,
+/// [`AtomicU32`](crate::AtomicU32) for `u32`), so it is a built-in atomic
+/// where one is available and a fallback type otherwise, exactly as if it
+/// were declared on its own. Every operation acts on individual elements;
+/// methods that take an index panic if the index is not less than `N`.
+///
+/// ```
+/// use atomic_int::AtomicArray;
+/// use std::sync::atomic::Ordering;
+///
+/// // A histogram with 4 buckets.
+/// let hist = AtomicArray::<u64, 4>::new([0; 4]);
+/// for sample in [0, 3, 3, 1, 3] {
+///     hist.fetch_add(sample, 1, Ordering::Relaxed);
+/// }
+/// assert_eq!(hist.load(3, Ordering::Relaxed), 3);
+/// assert_eq!(hist.load_all(Ordering::Relaxed), [1, 1, 0, 3]);
+///
+/// hist.store_all([0; 4], Ordering::Relaxed);
+/// let total: u64 = hist
+///     .as_slice_of_atomics()
+///     .iter()
+///     .map(|bucket| bucket.load(Ordering::Relaxed))
+///     .sum();
+/// assert_eq!(total, 0);
+/// ```
+#[repr(transparent)]
+pub struct AtomicArray<T: HasAtomic, const N: usize> {
+    elements: [T::Atomic; N],
+}
+
+impl<T: HasAtomic, const N: usize> AtomicArray<T, N> {
+    /// Creates a new array with the given values.
+    pub fn new(values: [T; N]) -> Self {
+        Self {
+            elements: values.map(T::Atomic::new),
+        }
+    }
+
+    /// Returns the elements as a slice of atomics.
+    pub fn as_slice_of_atomics(&self) -> &[T::Atomic] {
+        &self.elements
+    }
+
+    /// Loads the element at `index`.
+    ///
+    /// See [`AtomicIntExt::load`].
+    pub fn load(&self, index: usize, order: Ordering) -> T {
+        self.elements[index].load(order)
+    }
+
+    /// Stores `val` in the element at `index`.
+    ///
+    /// See [`AtomicIntExt::store`].
+    pub fn store(&self, index: usize, val: T, order: Ordering) {
+        self.elements[index].store(val, order);
+    }
+
+    /// Adds `val` to the element at `index`, returning its previous value.
+    ///
+    /// See [`AtomicIntExt::fetch_add`].
+    pub fn fetch_add(&self, index: usize, val: T, order: Ordering) -> T {
+        self.elements[index].fetch_add(val, order)
+    }
+
+    /// Loads every element, in order of increasing index.
+    ///
+    /// The elements are loaded one at a time, so if the array is modified
+    /// concurrently, the result may not correspond to the contents of the
+    /// array at any single point in time.
+    pub fn load_all(&self, order: Ordering) -> [T; N] {
+        let mut values = [T::ZERO; N];
+        for (value, atomic) in values.iter_mut().zip(&self.elements) {
+            *value = atomic.load(order);
+        }
+        values
+    }
+
+    /// Stores each of `values` in the element with the same index, in order
+    /// of increasing index.
+    ///
+    /// As with [`load_all`](Self::load_all), the stores are performed one at
+    /// a time, so other threads may observe some of them but not others.
+    pub fn store_all(&self, values: [T; N], order: Ordering) {
+        for (value, atomic) in
+            IntoIterator::into_iter(values).zip(&self.elements)
+        {
+            atomic.store(value, order);
+        }
+    }
+
+    /// Returns a mutable reference to the value of the element at `index`.
+    ///
+    /// This is safe because the mutable reference guarantees that no other
+    /// threads are concurrently accessing the array.
+    pub fn get_mut(&mut self, index: usize) -> &mut T {
+        self.elements[index].get_mut()
+    }
+
+    /// Consumes the array and returns the contained values.
+    pub fn into_inner(self) -> [T; N] {
+        self.elements.map(T::Atomic::into_inner)
+    }
+}
+
+impl<T: HasAtomic, const N: usize> Default for AtomicArray<T, N> {
+    fn default() -> Self {
+        Self::new([T::ZERO; N])
+    }
+}
+
+impl<T: HasAtomic, const N: usize> From<[T; N]> for AtomicArray<T, N> {
+    fn from(values: [T; N]) -> Self {
+        Self::new(values)
+    }
+}
+
+impl<T: HasAtomic + fmt::Debug, const N: usize> fmt::Debug
+    for AtomicArray<T, N>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.load_all(Ordering::SeqCst)).finish()
+    }
+}
+
+// SAFETY: Every atomic alias is valid when zeroed, and has a value of 0.
+impl_zeroable!([T: HasAtomic, const N: usize] AtomicArray<T, N>);
@@ -0,0 +1,248 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/// Defines a fieldless enum and an atomic type that stores it.
+///
+/// The atomic type wraps the given atomic integer type, which stores the
+/// enum’s discriminant. Its `load`, `store`, `swap`, `compare_exchange`,
+/// `compare_exchange_weak`, and `fetch_update` methods take and return the
+/// enum itself, so a value that isn’t a valid discriminant can never be
+/// observed. Every discriminant must be representable by the atomic’s integer
+/// type (e.g., `u8` for [`AtomicU8`]); this is checked at compile time. The
+/// enum should usually have a matching `#[repr]`, but this is not required.
+///
+/// No traits need to be implemented by the enum; the atomic type implements
+/// [`Debug`], which prints the name of the current variant, and
+/// <code>[From]\<Enum></code>.
+///
+/// ```
+/// use atomic_int::{AtomicU8, atomic_enum};
+/// use std::sync::atomic::Ordering;
+///
+/// atomic_enum! {
+///     #[repr(u8)]
+///     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///     pub enum State {
+///         Idle,
+///         Running,
+///         Done = 10,
+///     }
+///
+///     /// An atomic [`State`].
+///     pub struct AtomicState(AtomicU8);
+/// }
+///
+/// static STATE: AtomicState = AtomicState::new(State::Idle);
+/// assert_eq!(
+///     STATE.compare_exchange(
+///         State::Idle,
+///         State::Running,
+///         Ordering::AcqRel,
+///         Ordering::Acquire,
+///     ),
+///     Ok(State::Idle),
+/// );
+/// assert_eq!(STATE.swap(State::Done, Ordering::AcqRel), State::Running);
+/// assert_eq!(format!("{:?}", STATE), "Done");
+/// ```
+///
+/// A discriminant that doesn’t fit causes a compile error:
+///
+/// ```compile_fail
+/// use atomic_int::{AtomicU8, atomic_enum};
+///
+/// atomic_enum! {
+///     pub enum Big {
+///         A = 256,
+///     }
+///
+///     pub struct AtomicBig(AtomicU8);
+/// }
+/// ```
+///
+/// [`AtomicU8`]: crate::AtomicU8
+/// [`Debug`]: core::fmt::Debug
+#[macro_export]
+macro_rules! atomic_enum {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$variant_attr:meta])*
+                $variant:ident $(= $disc:expr)?
+            ),* $(,)?
+        }
+
+        $(#[$atomic_attr:meta])*
+        $atomic_vis:vis struct $atomic_name:ident($atomic:ty);
+    ) => {
+        $(#[$attr])*
+        $vis enum $name {
+            $(
+                $(#[$variant_attr])*
+                $variant $(= $disc)?,
+            )*
+        }
+
+        const _: () = {
+            type Int = <$atomic as $crate::AtomicIntExt>::Int;
+            $(::core::assert!(
+                $name::$variant as Int as i128 == $name::$variant as i128,
+                ::core::concat!(
+                    "the discriminant of `",
+                    ::core::stringify!($name),
+                    "::",
+                    ::core::stringify!($variant),
+                    "` does not fit in `",
+                    ::core::stringify!($atomic),
+                    "`",
+                ),
+            );)*
+        };
+
+        $(#[$atomic_attr])*
+        #[repr(transparent)]
+        $atomic_vis struct $atomic_name {
+            inner: $atomic,
+        }
+
+        #[allow(dead_code)]
+        impl $atomic_name {
+            /// Creates a new atomic.
+            pub const fn new(v: $name) -> Self {
+                Self {
+                    inner: <$atomic>::new(v as _),
+                }
+            }
+
+            fn from_bits(
+                bits: <$atomic as $crate::AtomicIntExt>::Int,
+            ) -> $name {
+                $(if bits == $name::$variant as _ {
+                    return $name::$variant;
+                })*
+                // Only valid discriminants are ever stored.
+                ::core::unreachable!("invalid discriminant")
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            pub fn into_inner(self) -> $name {
+                Self::from_bits(self.inner.into_inner())
+            }
+
+            /// Loads the value.
+            pub fn load(
+                &self,
+                order: ::core::sync::atomic::Ordering,
+            ) -> $name {
+                Self::from_bits(self.inner.load(order))
+            }
+
+            /// Stores a value.
+            pub fn store(
+                &self,
+                v: $name,
+                order: ::core::sync::atomic::Ordering,
+            ) {
+                self.inner.store(v as _, order);
+            }
+
+            /// Stores a value, returning the previous value.
+            pub fn swap(
+                &self,
+                v: $name,
+                order: ::core::sync::atomic::Ordering,
+            ) -> $name {
+                Self::from_bits(self.inner.swap(v as _, order))
+            }
+
+            /// Stores a value if the current value is the same as
+            /// `current`.
+            pub fn compare_exchange(
+                &self,
+                current: $name,
+                new: $name,
+                success: ::core::sync::atomic::Ordering,
+                failure: ::core::sync::atomic::Ordering,
+            ) -> ::core::result::Result<$name, $name> {
+                self.inner
+                    .compare_exchange(current as _, new as _, success, failure)
+                    .map(Self::from_bits)
+                    .map_err(Self::from_bits)
+            }
+
+            /// Stores a value if the current value is the same as
+            /// `current`. This method may fail spuriously.
+            pub fn compare_exchange_weak(
+                &self,
+                current: $name,
+                new: $name,
+                success: ::core::sync::atomic::Ordering,
+                failure: ::core::sync::atomic::Ordering,
+            ) -> ::core::result::Result<$name, $name> {
+                self.inner
+                    .compare_exchange_weak(
+                        current as _,
+                        new as _,
+                        success,
+                        failure,
+                    )
+                    .map(Self::from_bits)
+                    .map_err(Self::from_bits)
+            }
+
+            /// Fetches the value, and applies a function to it that
+            /// returns an optional new value.
+            pub fn fetch_update<F>(
+                &self,
+                set_order: ::core::sync::atomic::Ordering,
+                fetch_order: ::core::sync::atomic::Ordering,
+                mut f: F,
+            ) -> ::core::result::Result<$name, $name>
+            where
+                F: ::core::ops::FnMut($name) -> ::core::option::Option<$name>,
+            {
+                self.inner
+                    .fetch_update(set_order, fetch_order, |bits| {
+                        f(Self::from_bits(bits)).map(|v| v as _)
+                    })
+                    .map(Self::from_bits)
+                    .map_err(Self::from_bits)
+            }
+        }
+
+        impl ::core::convert::From<$name> for $atomic_name {
+            fn from(v: $name) -> Self {
+                Self::new(v)
+            }
+        }
+
+        impl ::core::fmt::Debug for $atomic_name {
+            fn fmt(
+                &self,
+                f: &mut ::core::fmt::Formatter<'_>,
+            ) -> ::core::fmt::Result {
+                f.write_str(
+                    match self.load(::core::sync::atomic::Ordering::SeqCst) {
+                        $($name::$variant => ::core::stringify!($variant),)*
+                    },
+                )
+            }
+        }
+    };
+}
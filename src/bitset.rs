@@ -0,0 +1,216 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::AtomicUsize;
+use super::ext::load_order;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::sync::atomic::Ordering;
+
+const WORD_BITS: usize = usize::BITS as usize;
+
+/// Returns the word index and mask of bit `index`.
+fn locate<const N: usize>(index: usize) -> (usize, usize) {
+    assert!(index < N * WORD_BITS, "bit index out of range");
+    (index / WORD_BITS, 1 << (index % WORD_BITS))
+}
+
+/// A fixed-size set of bits stored in `N` [`AtomicUsize`] words.
+///
+/// The set holds [`Self::BITS`] bits, numbered from 0; bit `i` is bit
+/// `i % usize::BITS` of word `i / usize::BITS`. Every operation acts on
+/// individual words, so it is lock-free wherever [`AtomicUsize`] is. Methods
+/// that take a bit index panic if the index is not less than
+/// [`Self::BITS`].
+///
+/// When [`AtomicUsize`] is a built-in atomic, this type has the same layout
+/// as `[usize; N]`, so it can be shared with C code that uses an array of
+/// `size_t` or `uintptr_t` as a bitmap.
+///
+/// ```
+/// use atomic_int::AtomicBitSet;
+/// use std::sync::atomic::Ordering;
+///
+/// // A table of at least 128 slots.
+/// let slots = AtomicBitSet::<4>::new();
+/// let a = slots.set_first_zero(Ordering::AcqRel).unwrap();
+/// let b = slots.set_first_zero(Ordering::AcqRel).unwrap();
+/// assert_eq!((a, b), (0, 1));
+/// assert!(slots.clear(a, Ordering::Release));
+/// assert_eq!(slots.find_first_zero(Ordering::Acquire), Some(0));
+///
+/// slots.set(70, Ordering::Relaxed);
+/// let set: Vec<_> = slots.iter(Ordering::Relaxed).collect();
+/// assert_eq!(set, [1, 70]);
+/// ```
+#[repr(transparent)]
+pub struct AtomicBitSet<const N: usize> {
+    words: [AtomicUsize; N],
+}
+
+impl<const N: usize> AtomicBitSet<N> {
+    /// The number of bits in the set.
+    pub const BITS: usize = N * WORD_BITS;
+
+    /// Creates a new set with every bit cleared.
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicUsize = AtomicUsize::new(0);
+        Self {
+            words: [ZERO; N],
+        }
+    }
+
+    /// Returns the underlying words.
+    pub fn words(&self) -> &[AtomicUsize; N] {
+        &self.words
+    }
+
+    /// Sets the bit at `index`, returning its previous value.
+    pub fn set(&self, index: usize, order: Ordering) -> bool {
+        let (word, mask) = locate::<N>(index);
+        self.words[word].fetch_or(mask, order) & mask != 0
+    }
+
+    /// Clears the bit at `index`, returning its previous value.
+    pub fn clear(&self, index: usize, order: Ordering) -> bool {
+        let (word, mask) = locate::<N>(index);
+        self.words[word].fetch_and(!mask, order) & mask != 0
+    }
+
+    /// Returns the bit at `index`.
+    pub fn test(&self, index: usize, order: Ordering) -> bool {
+        let (word, mask) = locate::<N>(index);
+        self.words[word].load(order) & mask != 0
+    }
+
+    /// Returns the index of the first cleared bit, or [`None`] if every bit
+    /// is set.
+    ///
+    /// The words are loaded one at a time, so if the set is modified
+    /// concurrently, the bit may already be set by the time this method
+    /// returns. To claim a cleared bit, use
+    /// [`set_first_zero`](Self::set_first_zero) instead.
+    pub fn find_first_zero(&self, order: Ordering) -> Option<usize> {
+        self.words.iter().enumerate().find_map(|(i, word)| {
+            let zeros = !word.load(order);
+            (zeros != 0)
+                .then(|| i * WORD_BITS + zeros.trailing_zeros() as usize)
+        })
+    }
+
+    /// Atomically sets the first cleared bit, returning its index, or
+    /// returns [`None`] if every bit is set.
+    ///
+    /// If multiple threads call this method concurrently, each receives a
+    /// different index, which makes it suitable for allocating slots.
+    /// `order` is used for the update of the word that contains the bit.
+    pub fn set_first_zero(&self, order: Ordering) -> Option<usize> {
+        self.words.iter().enumerate().find_map(|(i, word)| {
+            word.fetch_update(order, load_order(order), |w| {
+                (w != !0).then(|| w | (1 << (!w).trailing_zeros()))
+            })
+            .ok()
+            .map(|prev| i * WORD_BITS + (!prev).trailing_zeros() as usize)
+        })
+    }
+
+    /// Returns the number of set bits.
+    ///
+    /// Like [`find_first_zero`](Self::find_first_zero), this loads the
+    /// words one at a time.
+    pub fn count_ones(&self, order: Ordering) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.load(order).count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns an iterator over the indices of the set bits, in ascending
+    /// order.
+    ///
+    /// Each word is loaded with `order` when the iterator reaches it, so if
+    /// the set is modified concurrently, the bits yielded may not correspond
+    /// to the contents of the set at any single point in time.
+    pub fn iter(&self, order: Ordering) -> BitSetIter<'_, N> {
+        BitSetIter {
+            set: self,
+            order,
+            next_word: 0,
+            bits: 0,
+        }
+    }
+
+    /// Consumes the set and returns the underlying words.
+    pub fn into_inner(self) -> [usize; N] {
+        let mut words = [0; N];
+        for (word, atomic) in words.iter_mut().zip(self.words) {
+            *word = atomic.into_inner();
+        }
+        words
+    }
+}
+
+impl<const N: usize> Default for AtomicBitSet<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Debug for AtomicBitSet<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter(Ordering::SeqCst)).finish()
+    }
+}
+
+// SAFETY: All-zero words are a valid (empty) set.
+impl_zeroable!([const N: usize] AtomicBitSet<N>);
+
+/// An iterator over the set bits of an [`AtomicBitSet`].
+///
+/// This type is returned by [`AtomicBitSet::iter`].
+pub struct BitSetIter<'a, const N: usize> {
+    set: &'a AtomicBitSet<N>,
+    order: Ordering,
+    /// The index of the next word to load.
+    next_word: usize,
+    /// The bits of the current word that have not been yielded yet.
+    bits: usize,
+}
+
+impl<const N: usize> Iterator for BitSetIter<'_, N> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.bits == 0 {
+            self.bits = self.set.words.get(self.next_word)?.load(self.order);
+            self.next_word += 1;
+        }
+        let bit = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Some((self.next_word - 1) * WORD_BITS + bit)
+    }
+}
+
+impl<const N: usize> FusedIterator for BitSetIter<'_, N> {}
+
+impl<const N: usize> fmt::Debug for BitSetIter<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BitSetIter").finish_non_exhaustive()
+    }
+}
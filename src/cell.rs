@@ -0,0 +1,419 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Single-threaded counterparts to the atomic types in this crate.
+//!
+//! Each type in this module has the same name and API as the corresponding
+//! type at the crate root, including [`AtomicIntExt`] and [`AtomicPtrExt`],
+//! but stores its value in a [`Cell`] instead of synchronizing access to it.
+//! The requested orderings are ignored (though, like the built-in atomics,
+//! `compare_exchange` and `fetch_update` still panic on an invalid failure
+//! ordering), so no operation takes a lock.
+//!
+//! Code written against either set of types can therefore switch to the
+//! other by changing only the path of the type:
+//!
+//! ```
+//! use std::sync::atomic::Ordering;
+//!
+//! macro_rules! exercise {
+//!     ($atomic:ty) => {{
+//!         let a = <$atomic>::new(5);
+//!         let add = a.fetch_add(3, Ordering::Relaxed);
+//!         let max = a.fetch_max(20, Ordering::AcqRel);
+//!         let cas = a.compare_exchange(
+//!             20,
+//!             -1,
+//!             Ordering::SeqCst,
+//!             Ordering::Acquire,
+//!         );
+//!         let update = a.fetch_update(
+//!             Ordering::Release,
+//!             Ordering::Relaxed,
+//!             |n| n.checked_mul(4),
+//!         );
+//!         let swap = a.swap(7, Ordering::SeqCst);
+//!         (add, max, cas, update, swap, a.into_inner())
+//!     }};
+//! }
+//!
+//! assert_eq!(
+//!     exercise!(atomic_int::AtomicI64),
+//!     exercise!(atomic_int::cell::AtomicI64),
+//! );
+//!
+//! // The same holds for code that is generic over `AtomicIntExt`.
+//! fn count_even<A: atomic_int::AtomicIntExt<Int = u32>>(v: &[u32]) -> u32 {
+//!     let count = A::new(0);
+//!     v.iter().filter(|&&n| n % 2 == 0).for_each(|_| {
+//!         count.fetch_add(1, Ordering::Relaxed);
+//!     });
+//!     count.into_inner()
+//! }
+//!
+//! let values = [1, 2, 3, 4, 6];
+//! assert_eq!(count_even::<atomic_int::AtomicU32>(&values), 3);
+//! assert_eq!(count_even::<atomic_int::cell::AtomicU32>(&values), 3);
+//! ```
+//!
+//! The types in this module are not [`Sync`], so they can't be shared between
+//! threads:
+//!
+//! ```compile_fail
+//! use atomic_int::cell::AtomicU32;
+//! use std::sync::atomic::Ordering;
+//! use std::thread;
+//!
+//! let a = AtomicU32::new(0);
+//! thread::scope(|s| {
+//!     s.spawn(|| a.fetch_add(1, Ordering::Relaxed));
+//! });
+//! ```
+//!
+//! Enabling the feature `cell` only adds this module; the types at the crate
+//! root are unaffected.
+//!
+//! [`AtomicIntExt`]: crate::AtomicIntExt
+//! [`AtomicPtrExt`]: crate::AtomicPtrExt
+
+use crate::PrimInt;
+use crate::fallback::cas_order;
+use core::cell::Cell;
+use core::fmt;
+use core::sync::atomic::Ordering;
+
+macro_rules! define_cell {
+    ($atomic:ident$(<$generic:ident>)?, $type:ty) => {
+        /// A single-threaded counterpart to
+        #[doc = concat!(
+            "[`", stringify!($atomic), "`](crate::", stringify!($atomic), ")."
+        )]
+        #[repr(transparent)]
+        pub struct $atomic$(<$generic>)? {
+            value: Cell<$type>,
+        }
+
+        impl$(<$generic>)? $atomic$(<$generic>)? {
+            /// Creates a new atomic.
+            pub const fn new(v: $type) -> Self {
+                Self {
+                    value: Cell::new(v),
+                }
+            }
+
+            /// Returns a mutable reference to the underlying value.
+            pub fn get_mut(&mut self) -> &mut $type {
+                self.value.get_mut()
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            pub fn into_inner(self) -> $type {
+                self.value.into_inner()
+            }
+
+            /// Loads a value from the atomic.
+            pub fn load(&self, _order: Ordering) -> $type {
+                self.value.get()
+            }
+
+            /// Stores a value into the atomic.
+            pub fn store(&self, val: $type, _order: Ordering) {
+                self.value.set(val);
+            }
+
+            /// Stores a value into the atomic, returning the previous
+            /// value.
+            pub fn swap(&self, val: $type, _order: Ordering) -> $type {
+                self.value.replace(val)
+            }
+
+            /// Stores a value into the atomic if the current value is the same
+            /// as the `current` value.
+            pub fn compare_exchange(
+                &self,
+                current: $type,
+                new: $type,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$type, $type> {
+                cas_order(success, failure);
+                let prev = self.value.get();
+                if prev == current {
+                    self.value.set(new);
+                    Ok(prev)
+                } else {
+                    Err(prev)
+                }
+            }
+
+            /// Stores a value into the atomic if the current value is the same
+            /// as the `current` value. Unlike the built-in atomics, this never
+            /// fails spuriously.
+            pub fn compare_exchange_weak(
+                &self,
+                current: $type,
+                new: $type,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$type, $type> {
+                self.compare_exchange(current, new, success, failure)
+            }
+
+            /// Fetches the value, and applies a function to it that returns
+            /// an optional new value. Returns `Ok(previous_value)` if the
+            /// function returned `Some`, else `Err(previous_value)`.
+            pub fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                mut f: F,
+            ) -> Result<$type, $type>
+            where
+                F: FnMut($type) -> Option<$type>,
+            {
+                cas_order(set_order, fetch_order);
+                let prev = self.value.get();
+                let next = f(prev).ok_or(prev)?;
+                self.value.set(next);
+                Ok(prev)
+            }
+
+            /// Replaces the value with `f` applied to it, returning the
+            /// previous value.
+            fn fetch_map(&self, f: impl FnOnce($type) -> $type) -> $type {
+                let prev = self.value.get();
+                self.value.set(f(prev));
+                prev
+            }
+
+            /// Returns a mutable pointer to the underlying value.
+            pub const fn as_ptr(&self) -> *mut $type {
+                self.value.as_ptr()
+            }
+        }
+    };
+}
+
+macro_rules! define_cell_int {
+    ($atomic:ident, $int:ty) => {
+        define_cell!($atomic, $int);
+        impl_atomic_int_ext!($atomic, $int, false);
+        impl_serde!([] $atomic, $int);
+        impl_atomic_traits!(int $atomic, $int);
+        // SAFETY: The type consists only of a `Cell` of an integer.
+        impl_zeroable!([] $atomic);
+
+        impl $atomic {
+            /// Adds to the current value, returning the previous value.
+            pub fn fetch_add(&self, val: $int, _order: Ordering) -> $int {
+                self.fetch_map(|v| v.wrapping_add(val))
+            }
+
+            /// Subtracts from the current value, returning the previous value.
+            pub fn fetch_sub(&self, val: $int, _order: Ordering) -> $int {
+                self.fetch_map(|v| v.wrapping_sub(val))
+            }
+
+            /// Bitwise “and” with the current value.
+            pub fn fetch_and(&self, val: $int, _order: Ordering) -> $int {
+                self.fetch_map(|v| v & val)
+            }
+
+            /// Bitwise “nand” with the current value.
+            pub fn fetch_nand(&self, val: $int, _order: Ordering) -> $int {
+                self.fetch_map(|v| !(v & val))
+            }
+
+            /// Bitwise “or” with the current value.
+            pub fn fetch_or(&self, val: $int, _order: Ordering) -> $int {
+                self.fetch_map(|v| v | val)
+            }
+
+            /// Bitwise “xor” with the current value.
+            pub fn fetch_xor(&self, val: $int, _order: Ordering) -> $int {
+                self.fetch_map(|v| v ^ val)
+            }
+
+            /// Maximum with the current value.
+            pub fn fetch_max(&self, val: $int, _order: Ordering) -> $int {
+                self.fetch_map(|v| v.max(val))
+            }
+
+            /// Minimum with the current value.
+            pub fn fetch_min(&self, val: $int, _order: Ordering) -> $int {
+                self.fetch_map(|v| v.min(val))
+            }
+        }
+
+        impl Default for $atomic {
+            fn default() -> Self {
+                Self::new(<$int as PrimInt>::ZERO)
+            }
+        }
+
+        impl From<$int> for $atomic {
+            fn from(v: $int) -> Self {
+                Self::new(v)
+            }
+        }
+
+        impl fmt::Debug for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.value.get(), f)
+            }
+        }
+
+        // Like the built-in atomics, the value is always valid, even if an
+        // operation panics (e.g., in the closure passed to `fetch_update`).
+        impl core::panic::RefUnwindSafe for $atomic {}
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! define_primitive_cell {
+    ($atomic:ident, $int:ident, $($cfg:tt)*) => {
+        define_cell_int!($atomic, $int);
+    };
+}
+
+#[cfg(feature = "primitives")]
+with_primitive_atomics!(define_primitive_cell);
+
+#[allow(unused_macros)]
+macro_rules! define_c_cell {
+    ($atomic:ident, $int:ident, $feature:literal, $cfg:ident) => {
+        define_cell_int!($atomic, crate::ffi::$int);
+    };
+}
+
+with_c_atomics!(define_c_cell);
+
+#[cfg(feature = "wide")]
+define_cell_int!(AtomicU256, crate::U256);
+#[cfg(feature = "wide")]
+define_cell_int!(AtomicI256, crate::I256);
+
+#[cfg(feature = "primitives")]
+define_cell!(AtomicPtr<T>, *mut T);
+
+#[cfg(feature = "primitives")]
+mod ptr {
+    use super::{AtomicPtr, fmt};
+    use core::sync::atomic::Ordering;
+
+    // SAFETY: Like the built-in `AtomicPtr`, this type only stores the
+    // pointer and never dereferences it.
+    unsafe impl<T> Send for AtomicPtr<T> {}
+    impl_atomic_ptr_ext!(AtomicPtr, false);
+    impl_atomic_traits!([T] AtomicPtr<T>, *mut T);
+
+    impl<T> Default for AtomicPtr<T> {
+        fn default() -> Self {
+            Self::new(core::ptr::null_mut())
+        }
+    }
+
+    impl<T> From<*mut T> for AtomicPtr<T> {
+        fn from(p: *mut T) -> Self {
+            Self::new(p)
+        }
+    }
+
+    impl<T> fmt::Debug for AtomicPtr<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Debug::fmt(&self.value.get(), f)
+        }
+    }
+
+    // See `define_cell_int`. The pointer is never dereferenced, so, like the
+    // built-in `AtomicPtr`, this doesn't depend on `T`.
+    impl<T> core::panic::RefUnwindSafe for AtomicPtr<T> {}
+    impl<T> core::panic::UnwindSafe for AtomicPtr<T> {}
+
+    impl<T> AtomicPtr<T> {
+        fn fetch_map_addr(&self, f: impl FnOnce(usize) -> usize) -> *mut T {
+            self.fetch_map(|p| crate::ext::map_addr(p, f))
+        }
+
+        /// Offsets the pointer by `val` elements of `T`, returning the
+        /// previous pointer.
+        pub fn fetch_ptr_add(&self, val: usize, order: Ordering) -> *mut T {
+            let val = val.wrapping_mul(core::mem::size_of::<T>());
+            self.fetch_byte_add(val, order)
+        }
+
+        /// Offsets the pointer backwards by `val` elements of `T`, returning
+        /// the previous pointer.
+        pub fn fetch_ptr_sub(&self, val: usize, order: Ordering) -> *mut T {
+            let val = val.wrapping_mul(core::mem::size_of::<T>());
+            self.fetch_byte_sub(val, order)
+        }
+
+        /// Offsets the pointer by `val` bytes, returning the previous
+        /// pointer.
+        pub fn fetch_byte_add(&self, val: usize, _order: Ordering) -> *mut T {
+            self.fetch_map_addr(|a| a.wrapping_add(val))
+        }
+
+        /// Offsets the pointer backwards by `val` bytes, returning the
+        /// previous pointer.
+        pub fn fetch_byte_sub(&self, val: usize, _order: Ordering) -> *mut T {
+            self.fetch_map_addr(|a| a.wrapping_sub(val))
+        }
+
+        /// Performs a bitwise “or” on the address of the pointer, returning
+        /// the previous pointer.
+        pub fn fetch_or(&self, val: usize, _order: Ordering) -> *mut T {
+            self.fetch_map_addr(|a| a | val)
+        }
+
+        /// Performs a bitwise “and” on the address of the pointer, returning
+        /// the previous pointer.
+        pub fn fetch_and(&self, val: usize, _order: Ordering) -> *mut T {
+            self.fetch_map_addr(|a| a & val)
+        }
+
+        /// Performs a bitwise “xor” on the address of the pointer, returning
+        /// the previous pointer.
+        pub fn fetch_xor(&self, val: usize, _order: Ordering) -> *mut T {
+            self.fetch_map_addr(|a| a ^ val)
+        }
+    }
+}
+
+// Ensures that, like the types at the crate root, these types are `Send`.
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+
+    #[allow(unused_macros)]
+    macro_rules! check {
+        ($atomic:ty, $($rest:tt)*) => {
+            assert_send::<$atomic>();
+        };
+    }
+
+    #[cfg(feature = "primitives")]
+    with_primitive_atomics!(check);
+    #[cfg(feature = "primitives")]
+    check!(AtomicPtr<core::cell::Cell<u8>>,);
+    with_c_atomics!(check);
+    #[cfg(feature = "wide")]
+    check!(AtomicU256,);
+    #[cfg(feature = "wide")]
+    check!(AtomicI256,);
+};
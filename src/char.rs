@@ -0,0 +1,175 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::AtomicU32;
+use core::fmt;
+use core::sync::atomic::Ordering;
+
+fn from_bits(bits: u32) -> char {
+    debug_assert!(char::from_u32(bits).is_some());
+    // SAFETY: `AtomicChar` only ever stores values obtained from a `char`,
+    // so `bits` is always a valid Unicode scalar value.
+    unsafe { char::from_u32_unchecked(bits) }
+}
+
+/// An atomic [`char`], stored as its scalar value in an [`AtomicU32`].
+///
+/// Every value stored comes from a [`char`], so the underlying integer is
+/// always a valid Unicode scalar value.
+///
+/// ```
+/// use atomic_int::AtomicChar;
+/// use std::sync::atomic::Ordering::SeqCst;
+///
+/// let c = AtomicChar::new('a');
+/// assert_eq!(c.swap('é', SeqCst), 'a');
+/// assert_eq!(c.compare_exchange('é', '💖', SeqCst, SeqCst), Ok('é'));
+/// assert_eq!(c.compare_exchange('a', 'b', SeqCst, SeqCst), Err('💖'));
+/// assert_eq!(c.load(SeqCst), '💖');
+/// ```
+#[repr(transparent)]
+pub struct AtomicChar {
+    bits: AtomicU32,
+}
+
+impl AtomicChar {
+    /// Creates a new atomic `char`.
+    pub const fn new(v: char) -> Self {
+        Self {
+            bits: AtomicU32::new(v as u32),
+        }
+    }
+
+    /// Returns a mutable reference to the underlying value.
+    pub fn get_mut(&mut self) -> &mut char {
+        let bits: *mut u32 = self.bits.get_mut();
+        // SAFETY: `char` has the same size and alignment as `u32`, and the
+        // integer is always a valid `char`. Any value written through the
+        // returned reference is a `char`, so this invariant is preserved.
+        unsafe { &mut *bits.cast() }
+    }
+
+    /// Consumes the atomic and returns the contained value.
+    pub fn into_inner(self) -> char {
+        from_bits(self.bits.into_inner())
+    }
+
+    /// Loads the value.
+    pub fn load(&self, order: Ordering) -> char {
+        from_bits(self.bits.load(order))
+    }
+
+    /// Stores a value.
+    pub fn store(&self, val: char, order: Ordering) {
+        self.bits.store(val as u32, order);
+    }
+
+    /// Stores a value, returning the previous value.
+    pub fn swap(&self, val: char, order: Ordering) -> char {
+        from_bits(self.bits.swap(val as u32, order))
+    }
+
+    /// Stores `new` if the current value is `current`.
+    ///
+    /// The return value is the previous value: `Ok` if it was `current`,
+    /// `Err` otherwise.
+    pub fn compare_exchange(
+        &self,
+        current: char,
+        new: char,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<char, char> {
+        self.bits
+            .compare_exchange(current as u32, new as u32, success, failure)
+            .map(from_bits)
+            .map_err(from_bits)
+    }
+
+    /// Like [`Self::compare_exchange`], but may fail spuriously.
+    pub fn compare_exchange_weak(
+        &self,
+        current: char,
+        new: char,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<char, char> {
+        self.bits
+            .compare_exchange_weak(
+                current as u32,
+                new as u32,
+                success,
+                failure,
+            )
+            .map(from_bits)
+            .map_err(from_bits)
+    }
+
+    /// Fetches the value, and applies a function to it that returns an
+    /// optional new value.
+    ///
+    /// See [`AtomicU32::fetch_update`][fetch_update].
+    ///
+    /// [fetch_update]: core::sync::atomic::AtomicU32::fetch_update
+    pub fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: F,
+    ) -> Result<char, char>
+    where
+        F: FnMut(char) -> Option<char>,
+    {
+        self.bits
+            .fetch_update(set_order, fetch_order, |bits| {
+                f(from_bits(bits)).map(u32::from)
+            })
+            .map(from_bits)
+            .map_err(from_bits)
+    }
+}
+
+impl Default for AtomicChar {
+    fn default() -> Self {
+        Self::new('\0')
+    }
+}
+
+impl From<char> for AtomicChar {
+    fn from(v: char) -> Self {
+        Self::new(v)
+    }
+}
+
+impl crate::ext::sealed::Sealed for AtomicChar {}
+
+impl_any_atomic!(
+    [] AtomicChar,
+    char,
+    <AtomicU32 as crate::AnyAtomic>::IS_ALWAYS_LOCK_FREE
+);
+
+impl_serde!([] AtomicChar, char);
+// SAFETY: Zero is `'\0'`.
+impl_zeroable!([] AtomicChar);
+
+impl fmt::Debug for AtomicChar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.load(Ordering::SeqCst).fmt(f)
+    }
+}
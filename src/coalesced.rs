@@ -0,0 +1,104 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::AtomicIntExt;
+use core::sync::atomic::Ordering;
+
+/// A handle that coalesces repeated stores to an atomic.
+///
+/// Each writer creates its own handle, which buffers stored values locally
+/// and writes only the most recent one to the underlying atomic every
+/// `interval` stores, when [`flush`](Self::flush) is called, or when the
+/// handle is dropped. This avoids repeatedly taking exclusive ownership of
+/// the atomic’s cache line for values that nobody reads, such as a progress
+/// counter updated in a tight loop.
+///
+/// The trade-off is staleness: readers of the atomic see only the last
+/// flushed value, which may lag behind the writer by up to `interval - 1`
+/// stores, and indefinitely so if the writer stops storing without flushing
+/// (while the handle remains alive). All writes to the atomic use
+/// [`Relaxed`](Ordering::Relaxed) ordering, so a flush does not synchronize
+/// with readers; use a fence or a separate atomic if readers must observe
+/// other memory written before a flush.
+///
+/// ```
+/// use atomic_int::{AtomicU32, AtomicIntExt, Coalesced};
+/// use std::sync::atomic::Ordering;
+///
+/// let progress = AtomicU32::new(0);
+/// let mut writer = Coalesced::new(&progress, 4);
+/// for i in 1..=6 {
+///     writer.store(i);
+/// }
+/// // Only the fourth store has been written so far.
+/// assert_eq!(progress.load(Ordering::Relaxed), 4);
+/// writer.flush();
+/// assert_eq!(progress.load(Ordering::Relaxed), 6);
+/// ```
+pub struct Coalesced<'a, A: AtomicIntExt> {
+    atomic: &'a A,
+    pending: Option<A::Int>,
+    count: usize,
+    interval: usize,
+}
+
+impl<'a, A: AtomicIntExt> Coalesced<'a, A> {
+    /// Creates a handle that writes to `atomic` every `interval` stores.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is 0.
+    pub fn new(atomic: &'a A, interval: usize) -> Self {
+        assert!(interval > 0, "`Coalesced` interval must be nonzero");
+        Self {
+            atomic,
+            pending: None,
+            count: 0,
+            interval,
+        }
+    }
+
+    /// Returns the underlying atomic.
+    pub fn get_ref(&self) -> &'a A {
+        self.atomic
+    }
+
+    /// Buffers a value to be stored into the atomic, flushing if this is the
+    /// `interval`-th store since the last flush.
+    pub fn store(&mut self, val: A::Int) {
+        self.pending = Some(val);
+        self.count += 1;
+        if self.count >= self.interval {
+            self.flush();
+        }
+    }
+
+    /// Writes the most recently buffered value, if any, to the atomic.
+    pub fn flush(&mut self) {
+        self.count = 0;
+        if let Some(val) = self.pending.take() {
+            self.atomic.store(val, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<A: AtomicIntExt> Drop for Coalesced<'_, A> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
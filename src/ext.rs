@@ -0,0 +1,2340 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use core::fmt::{self, Debug};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{BitAnd, BitOr, BitXor, Not, Shl};
+#[allow(unused_imports)]
+use core::sync::atomic;
+use core::sync::atomic::{Ordering, fence};
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+/// A primitive integer type that can be stored in an [`AtomicIntExt`].
+pub trait PrimInt:
+    sealed::Sealed
+    + Copy
+    + Ord
+    + Debug
+    + Default
+    + Send
+    + Sync
+    + 'static
+    + Not<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Shl<u32, Output = Self>
+{
+    /// The size of this type in bits.
+    const BITS: u32;
+
+    /// The value 0.
+    const ZERO: Self;
+
+    /// The value 1.
+    const ONE: Self;
+
+    /// The smallest value of this type.
+    const MIN: Self;
+
+    /// The largest value of this type.
+    const MAX: Self;
+
+    /// Wrapping (modular) addition.
+    fn wrapping_add(self, rhs: Self) -> Self;
+
+    /// Wrapping (modular) subtraction.
+    fn wrapping_sub(self, rhs: Self) -> Self;
+
+    /// Wrapping (modular) multiplication.
+    fn wrapping_mul(self, rhs: Self) -> Self;
+
+    /// Saturating addition.
+    fn saturating_add(self, rhs: Self) -> Self;
+
+    /// Saturating subtraction.
+    fn saturating_sub(self, rhs: Self) -> Self;
+
+    /// Checked addition, returning [`None`] on overflow.
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+
+    /// Checked subtraction, returning [`None`] on overflow.
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+
+    /// Checked multiplication, returning [`None`] on overflow.
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+
+    /// Wrapping division, which wraps only for the minimum value of a
+    /// signed type divided by −1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rhs` is 0.
+    fn wrapping_div(self, rhs: Self) -> Self;
+
+    /// Wrapping remainder, which is 0 for the minimum value of a signed type
+    /// divided by −1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rhs` is 0.
+    fn wrapping_rem(self, rhs: Self) -> Self;
+
+    /// Returns the number of ones in the binary representation.
+    fn count_ones(self) -> u32;
+
+    /// The corresponding nonzero integer type (e.g., [`NonZeroU32`] for
+    /// [`u32`]).
+    ///
+    /// [`NonZeroU32`]: core::num::NonZeroU32
+    type NonZero: Copy + Debug + Send + Sync + 'static;
+
+    /// Converts to the nonzero type, returning [`None`] if the value is 0.
+    fn to_nonzero(self) -> Option<Self::NonZero>;
+
+    /// Converts from the nonzero type.
+    fn from_nonzero(n: Self::NonZero) -> Self;
+
+    /// The signed integer type with the same width (e.g., [`i32`] for
+    /// [`u32`]). For signed types, this is `Self`.
+    type Signed: PrimInt;
+
+    /// Converts a signed integer of the same width to this type by
+    /// reinterpreting its bits, so that adding the result with wrapping
+    /// arithmetic is equivalent to adding `v`.
+    fn from_signed_bits(v: Self::Signed) -> Self;
+}
+
+macro_rules! impl_prim_int {
+    ($($int:ident: $nonzero:ident, $signed:ident),*) => {$(
+        impl sealed::Sealed for $int {}
+
+        impl PrimInt for $int {
+            const BITS: u32 = <$int>::BITS;
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const MIN: Self = <$int>::MIN;
+            const MAX: Self = <$int>::MAX;
+
+            fn wrapping_add(self, rhs: Self) -> Self {
+                <$int>::wrapping_add(self, rhs)
+            }
+
+            fn wrapping_sub(self, rhs: Self) -> Self {
+                <$int>::wrapping_sub(self, rhs)
+            }
+
+            fn wrapping_mul(self, rhs: Self) -> Self {
+                <$int>::wrapping_mul(self, rhs)
+            }
+
+            fn saturating_add(self, rhs: Self) -> Self {
+                <$int>::saturating_add(self, rhs)
+            }
+
+            fn saturating_sub(self, rhs: Self) -> Self {
+                <$int>::saturating_sub(self, rhs)
+            }
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$int>::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$int>::checked_sub(self, rhs)
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$int>::checked_mul(self, rhs)
+            }
+
+            fn wrapping_div(self, rhs: Self) -> Self {
+                <$int>::wrapping_div(self, rhs)
+            }
+
+            fn wrapping_rem(self, rhs: Self) -> Self {
+                <$int>::wrapping_rem(self, rhs)
+            }
+
+            fn count_ones(self) -> u32 {
+                <$int>::count_ones(self)
+            }
+
+            type NonZero = core::num::$nonzero;
+
+            fn to_nonzero(self) -> Option<Self::NonZero> {
+                Self::NonZero::new(self)
+            }
+
+            fn from_nonzero(n: Self::NonZero) -> Self {
+                n.get()
+            }
+
+            type Signed = $signed;
+
+            fn from_signed_bits(v: $signed) -> Self {
+                v as Self
+            }
+        }
+    )*};
+}
+
+impl_prim_int!(
+    i8: NonZeroI8, i8,
+    u8: NonZeroU8, i8,
+    i16: NonZeroI16, i16,
+    u16: NonZeroU16, i16,
+    i32: NonZeroI32, i32,
+    u32: NonZeroU32, i32,
+    i64: NonZeroI64, i64,
+    u64: NonZeroU64, i64,
+    i128: NonZeroI128, i128,
+    u128: NonZeroU128, i128,
+    isize: NonZeroIsize, isize,
+    usize: NonZeroUsize, isize
+);
+
+/// The error returned by [`AtomicIntExt::try_fetch_add`] and similar methods
+/// when the operation would overflow.
+///
+/// The atomic is left unchanged, and `current` holds the value it contained
+/// when the overflow was detected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Overflow<T> {
+    /// The current (unchanged) value of the atomic.
+    pub current: T,
+}
+
+impl<T: Debug> fmt::Display for Overflow<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "atomic operation on {:?} overflowed", self.current)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Debug> std::error::Error for Overflow<T> {}
+
+/// A read-modify-write operation performed by [`AtomicIntExt::fetch_apply`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ApplyOp {
+    /// Wrapping addition; see [`AtomicIntExt::fetch_add`].
+    Add,
+    /// Wrapping subtraction; see [`AtomicIntExt::fetch_sub`].
+    Sub,
+    /// Bitwise “and”; see [`AtomicIntExt::fetch_and`].
+    And,
+    /// Bitwise “or”; see [`AtomicIntExt::fetch_or`].
+    Or,
+    /// Bitwise “xor”; see [`AtomicIntExt::fetch_xor`].
+    Xor,
+    /// Minimum; see [`AtomicIntExt::fetch_min`].
+    Min,
+    /// Maximum; see [`AtomicIntExt::fetch_max`].
+    Max,
+    /// Wrapping multiplication.
+    Mul,
+}
+
+/// The result of [`AtomicIntExt::fetch_apply`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RmwResult<T> {
+    /// The value before the operation.
+    pub previous: T,
+    /// The value stored by the operation.
+    pub new: T,
+    /// Whether the operation wrapped around. This is only ever true for
+    /// [`ApplyOp::Add`], [`ApplyOp::Sub`], and [`ApplyOp::Mul`].
+    pub overflowed: bool,
+}
+
+/// Operations common to every atomic integer type.
+///
+/// This trait is implemented both for the built-in atomics in
+/// [`core::sync::atomic`] and for this crate’s fallback types, so it can be
+/// used to write code that is generic over any atomic integer provided by
+/// this crate, regardless of which implementation a given type alias
+/// resolves to. Each method behaves like the method of the same name on
+/// [`AtomicI32`](atomic::AtomicI32).
+pub trait AtomicIntExt: sealed::Sealed + Sized {
+    /// The integer type stored in the atomic.
+    type Int: PrimInt;
+
+    /// Creates a new atomic.
+    fn new(v: Self::Int) -> Self;
+
+    /// Creates an array of atomics from an array of integers.
+    ///
+    /// This function can't be used to initialize a `static`; use
+    /// [`atomic_array!`](crate::atomic_array) for that.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU16};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let table = AtomicU16::from_array([1, 2, 3]);
+    /// let values = table.map(|a| a.load(Ordering::Relaxed));
+    /// assert_eq!(values, [1, 2, 3]);
+    /// ```
+    fn from_array<const N: usize>(vals: [Self::Int; N]) -> [Self; N] {
+        vals.map(Self::new)
+    }
+
+    /// Creates a reference to an atomic from a pointer to an integer, such as
+    /// a C global or memory at a fixed, linker-provided address.
+    ///
+    /// This is like `from_ptr` on the built-in atomics, but also works with
+    /// the fallback types when they have the same layout as their integer,
+    /// which the feature `striped` guarantees whatever other features are
+    /// enabled. Otherwise, a fallback type contains its own lock, and using
+    /// this function with it is a compile-time error (when the function is
+    /// instantiated).
+    ///
+    /// # Safety
+    ///
+    /// * `ptr` must be aligned to `align_of::<Self>()`, which may be greater
+    ///   than the alignment of the integer type.
+    /// * `ptr` must be valid for reads and writes for the lifetime `'a`.
+    /// * For the duration of `'a`, the value must not be accessed except
+    ///   through atomics of type `Self` created by this function (or otherwise
+    ///   located at the same address). In particular, when `Self` is a
+    ///   fallback type, concurrent atomic accesses from C code are not
+    ///   synchronized with it, as C does not use this crate’s locks.
+    #[cfg_attr(not(force_fallback), doc = "```")]
+    #[cfg_attr(force_fallback, doc = "```ignore")]
+    /// use atomic_int::{AtomicCInt, AtomicIntExt};
+    /// use std::ffi::c_int;
+    /// use std::ptr;
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// static mut COUNTER: c_int = 0;
+    ///
+    /// // SAFETY: `COUNTER` is properly aligned, as `AtomicCInt` is a
+    /// // built-in atomic here, and is accessed only through `counter`.
+    /// let counter: &AtomicCInt =
+    ///     unsafe { AtomicIntExt::from_ptr(ptr::addr_of_mut!(COUNTER)) };
+    /// counter.fetch_add(2, Ordering::Relaxed);
+    /// assert_eq!(counter.load(Ordering::Relaxed), 2);
+    /// ```
+    unsafe fn from_ptr<'a>(ptr: *mut Self::Int) -> &'a Self {
+        #[allow(clippy::let_unit_value)]
+        let () = SameLayout::<Self, Self::Int>::CHECK;
+        // SAFETY: Checked by caller. `Self` has the same size as its
+        // integer, and atomic types consist solely of an `UnsafeCell` of
+        // their integer, apart from zero-sized fields.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a mutable reference to the underlying value.
+    fn get_mut(&mut self) -> &mut Self::Int;
+
+    /// Consumes the atomic and returns the contained value.
+    fn into_inner(self) -> Self::Int;
+
+    /// Loads a value from the atomic.
+    fn load(&self, order: Ordering) -> Self::Int;
+
+    /// Stores a value into the atomic.
+    fn store(&self, val: Self::Int, order: Ordering);
+
+    /// Stores a value into the atomic, returning the previous value.
+    fn swap(&self, val: Self::Int, order: Ordering) -> Self::Int;
+
+    /// Stores a value into the atomic if the current value is the same as
+    /// the `current` value.
+    fn compare_exchange(
+        &self,
+        current: Self::Int,
+        new: Self::Int,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Self::Int, Self::Int>;
+
+    /// Stores a value into the atomic if the current value is the same as
+    /// the `current` value.
+    fn compare_exchange_weak(
+        &self,
+        current: Self::Int,
+        new: Self::Int,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Self::Int, Self::Int>;
+
+    /// Fetches the value, and applies a function to it that returns an
+    /// optional new value.
+    fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        f: F,
+    ) -> Result<Self::Int, Self::Int>
+    where
+        F: FnMut(Self::Int) -> Option<Self::Int>;
+
+    /// Adds to the current value, returning the previous value.
+    fn fetch_add(&self, val: Self::Int, order: Ordering) -> Self::Int;
+
+    /// Subtracts from the current value, returning the previous value.
+    fn fetch_sub(&self, val: Self::Int, order: Ordering) -> Self::Int;
+
+    /// Bitwise “and” with the current value.
+    fn fetch_and(&self, val: Self::Int, order: Ordering) -> Self::Int;
+
+    /// Bitwise “nand” with the current value.
+    fn fetch_nand(&self, val: Self::Int, order: Ordering) -> Self::Int;
+
+    /// Bitwise “or” with the current value.
+    fn fetch_or(&self, val: Self::Int, order: Ordering) -> Self::Int;
+
+    /// Bitwise “xor” with the current value.
+    fn fetch_xor(&self, val: Self::Int, order: Ordering) -> Self::Int;
+
+    /// Maximum with the current value.
+    fn fetch_max(&self, val: Self::Int, order: Ordering) -> Self::Int;
+
+    /// Minimum with the current value.
+    fn fetch_min(&self, val: Self::Int, order: Ordering) -> Self::Int;
+
+    /// Advances the value to the next state in `states`, returning the
+    /// previous value.
+    ///
+    /// If the current value is the last element of `states`, the first
+    /// element is stored. If the current value does not appear in `states`
+    /// at all, it is also reset to the first element. If the current value
+    /// appears more than once, its first occurrence is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `states` is empty.
+    fn fetch_advance(
+        &self,
+        states: &[Self::Int],
+        order: Ordering,
+    ) -> Self::Int {
+        assert!(!states.is_empty(), "`states` must not be empty");
+        let next = |prev| {
+            let pos = states.iter().position(|&s| s == prev);
+            let next = pos.map_or(0, |i| (i + 1) % states.len());
+            Some(states[next])
+        };
+        match self.fetch_update(order, load_order(order), next) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        }
+    }
+
+    /// Adds to the current value, saturating at the numeric bounds instead of
+    /// overflowing. Returns the previous value and the amount that was
+    /// actually added.
+    ///
+    /// The amount added is less than `val` in magnitude when the addition
+    /// saturates; e.g., adding 10 to `u8::MAX - 3` stores `u8::MAX` and
+    /// returns `(u8::MAX - 3, 3)`.
+    fn fetch_saturating_add_delta(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+    ) -> (Self::Int, Self::Int) {
+        let prev = match self.fetch_update(order, load_order(order), |prev| {
+            Some(prev.saturating_add(val))
+        }) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        };
+        (prev, prev.saturating_add(val).wrapping_sub(prev))
+    }
+
+    /// Subtracts from the current value, saturating at the numeric bounds
+    /// instead of overflowing. Returns the previous value and the amount that
+    /// was actually subtracted.
+    ///
+    /// See [`Self::fetch_saturating_add_delta`].
+    fn fetch_saturating_sub_delta(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+    ) -> (Self::Int, Self::Int) {
+        let prev = match self.fetch_update(order, load_order(order), |prev| {
+            Some(prev.saturating_sub(val))
+        }) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        };
+        (prev, prev.wrapping_sub(prev.saturating_sub(val)))
+    }
+
+    /// Adds to the current value, saturating at the numeric bounds instead of
+    /// overflowing. Returns the previous value.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU8};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let a = AtomicU8::new(250);
+    /// assert_eq!(a.fetch_saturating_add(10, Ordering::Relaxed), 250);
+    /// assert_eq!(a.load(Ordering::Relaxed), 255);
+    /// assert_eq!(a.fetch_saturating_sub(200, Ordering::Relaxed), 255);
+    /// assert_eq!(a.fetch_saturating_sub(200, Ordering::Relaxed), 55);
+    /// assert_eq!(a.load(Ordering::Relaxed), 0);
+    /// ```
+    fn fetch_saturating_add(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+    ) -> Self::Int {
+        match self.fetch_update(order, load_order(order), |prev| {
+            Some(prev.saturating_add(val))
+        }) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        }
+    }
+
+    /// Subtracts from the current value, saturating at the numeric bounds
+    /// instead of overflowing. Returns the previous value.
+    ///
+    /// See [`Self::fetch_saturating_add`].
+    fn fetch_saturating_sub(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+    ) -> Self::Int {
+        match self.fetch_update(order, load_order(order), |prev| {
+            Some(prev.saturating_sub(val))
+        }) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        }
+    }
+
+    /// Adds to the current value, returning the previous value, or [`None`]
+    /// (leaving the value unchanged) if the addition would overflow.
+    ///
+    /// This is [`Self::try_fetch_add`] without the value observed on
+    /// failure.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicI8, AtomicIntExt};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let a = AtomicI8::new(-120);
+    /// assert_eq!(a.fetch_checked_sub(8, Ordering::Relaxed), Some(-120));
+    /// assert_eq!(a.fetch_checked_sub(1, Ordering::Relaxed), None);
+    /// assert_eq!(a.fetch_checked_add(1, Ordering::Relaxed), Some(-128));
+    /// assert_eq!(a.load(Ordering::Relaxed), -127);
+    /// ```
+    fn fetch_checked_add(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+    ) -> Option<Self::Int> {
+        self.fetch_update(order, load_order(order), |v| v.checked_add(val))
+            .ok()
+    }
+
+    /// Subtracts from the current value, returning the previous value, or
+    /// [`None`] (leaving the value unchanged) if the subtraction would
+    /// overflow.
+    ///
+    /// See [`Self::fetch_checked_add`].
+    fn fetch_checked_sub(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+    ) -> Option<Self::Int> {
+        self.fetch_update(order, load_order(order), |v| v.checked_sub(val))
+            .ok()
+    }
+
+    /// Multiplies the current value by `val`, wrapping around on overflow,
+    /// and returns the previous value.
+    ///
+    /// Like the other methods implemented with [`Self::fetch_update`], this
+    /// is a compare-and-swap loop for built-in atomics, and modifies the
+    /// value directly while holding the lock for fallback types.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU32};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// // A rate limit that doubles while there is spare capacity, and is
+    /// // cut in thirds under pressure.
+    /// let limit = AtomicU32::new(100);
+    /// assert_eq!(limit.fetch_mul(2, Ordering::Relaxed), 100);
+    /// assert_eq!(limit.fetch_div(3, Ordering::Relaxed), 200);
+    /// assert_eq!(limit.fetch_rem(7, Ordering::Relaxed), 66);
+    /// assert_eq!(limit.load(Ordering::Relaxed), 3);
+    /// ```
+    fn fetch_mul(&self, val: Self::Int, order: Ordering) -> Self::Int {
+        match self.fetch_update(order, load_order(order), |prev| {
+            Some(prev.wrapping_mul(val))
+        }) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        }
+    }
+
+    /// Divides the current value by `val`, rounding toward zero, and returns
+    /// the previous value.
+    ///
+    /// Like [`i32::wrapping_div`], dividing the minimum value of a signed
+    /// type by −1 leaves it unchanged rather than overflowing.
+    ///
+    /// See [`Self::fetch_mul`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `val` is 0. The value is then left unchanged, and the panic
+    /// occurs before the atomic is accessed.
+    fn fetch_div(&self, val: Self::Int, order: Ordering) -> Self::Int {
+        assert!(val != PrimInt::ZERO, "attempt to divide by zero");
+        match self.fetch_update(order, load_order(order), |prev| {
+            Some(prev.wrapping_div(val))
+        }) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        }
+    }
+
+    /// Replaces the current value with the remainder of dividing it by
+    /// `val`, and returns the previous value.
+    ///
+    /// The remainder has the sign of the previous value, and, like
+    /// [`i32::wrapping_rem`], it is 0 for the minimum value of a signed type
+    /// divided by −1.
+    ///
+    /// See [`Self::fetch_mul`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `val` is 0. The value is then left unchanged, and the panic
+    /// occurs before the atomic is accessed.
+    fn fetch_rem(&self, val: Self::Int, order: Ordering) -> Self::Int {
+        assert!(
+            val != PrimInt::ZERO,
+            "attempt to calculate the remainder with a divisor of zero",
+        );
+        match self.fetch_update(order, load_order(order), |prev| {
+            Some(prev.wrapping_rem(val))
+        }) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        }
+    }
+
+    /// Negates the current value, wrapping around on overflow, and returns
+    /// the previous value.
+    ///
+    /// As with [`i32::wrapping_neg`], the minimum value of a signed type is
+    /// left unchanged, and an unsigned value `v` becomes `0 - v` (with
+    /// wrapping arithmetic).
+    ///
+    /// See [`Self::fetch_mul`].
+    ///
+    /// ```
+    /// use atomic_int::{AtomicI32, AtomicIntExt};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// // A counter whose sign records whether it has been closed.
+    /// let count = AtomicI32::new(3);
+    /// assert_eq!(count.fetch_neg(Ordering::AcqRel), 3);
+    /// assert_eq!(count.fetch_not(Ordering::AcqRel), -3);
+    /// assert_eq!(count.load(Ordering::Acquire), 2);
+    /// ```
+    fn fetch_neg(&self, order: Ordering) -> Self::Int {
+        match self.fetch_update(order, load_order(order), |prev| {
+            Some(Self::Int::ZERO.wrapping_sub(prev))
+        }) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        }
+    }
+
+    /// Inverts every bit of the current value, returning the previous value.
+    ///
+    /// Unlike [`Self::fetch_neg`], this is a single
+    /// [`fetch_xor`](Self::fetch_xor) with all bits set, so it is as
+    /// efficient as the other bitwise operations.
+    fn fetch_not(&self, order: Ordering) -> Self::Int {
+        self.fetch_xor(!Self::Int::ZERO, order)
+    }
+
+    /// Subtracts one from the current value, returning whether the new value
+    /// is zero.
+    ///
+    /// This is the decrement operation of a reference count: the thread for
+    /// which this returns `true` dropped the last reference. Before freeing
+    /// the shared object, that thread must synchronize with the decrements
+    /// of all other threads, so the recommended pattern, as in
+    /// [`Arc`](https://doc.rust-lang.org/std/sync/struct.Arc.html), is to
+    /// decrement with [`Release`](Ordering::Release) and issue an
+    /// [`Acquire`](Ordering::Acquire) fence when this returns `true`.
+    /// [`Self::fetch_dec_acquire_if_zero`] does exactly that.
+    fn fetch_dec_test_zero(&self, order: Ordering) -> bool {
+        self.fetch_sub(Self::Int::ONE, order) == Self::Int::ONE
+    }
+
+    /// Decrements a reference count, returning whether the new value is zero,
+    /// with the orderings needed to then free the shared object.
+    ///
+    /// The decrement uses [`Release`](Ordering::Release) ordering, and if the
+    /// new value is zero, an [`Acquire`](Ordering::Acquire) fence is issued
+    /// before returning `true`. See [`Self::fetch_dec_test_zero`].
+    ///
+    /// ```
+    /// use atomic_int::{AtomicCInt, AtomicIntExt};
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// let refs = AtomicCInt::new(8);
+    /// let freed = AtomicUsize::new(0);
+    /// std::thread::scope(|s| {
+    ///     for _ in 0..8 {
+    ///         s.spawn(|| {
+    ///             if refs.fetch_dec_acquire_if_zero() {
+    ///                 freed.fetch_add(1, Ordering::Relaxed);
+    ///             }
+    ///         });
+    ///     }
+    /// });
+    /// assert_eq!(freed.into_inner(), 1);
+    /// ```
+    fn fetch_dec_acquire_if_zero(&self) -> bool {
+        if self.fetch_dec_test_zero(Ordering::Release) {
+            fence(Ordering::Acquire);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Mixes `val` into the current value with a linear congruential step,
+    /// storing `prev * mul + val + add` (with wrapping arithmetic) and
+    /// returning the previous value.
+    ///
+    /// This is a cheap rolling checksum of a sequence of values: as long as
+    /// the values are folded in the same order, the result is the same.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU64};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// const MUL: u64 = 6364136223846793005;
+    /// const ADD: u64 = 1442695040888963407;
+    ///
+    /// let checksum = |values: &[u64]| {
+    ///     let hash = AtomicU64::new(0);
+    ///     for &v in values {
+    ///         hash.fetch_fold(v, Ordering::Relaxed, MUL, ADD);
+    ///     }
+    ///     hash.into_inner()
+    /// };
+    /// assert_eq!(checksum(&[1, 2, 3]), checksum(&[1, 2, 3]));
+    /// assert_ne!(checksum(&[1, 2, 3]), checksum(&[3, 2, 1]));
+    ///
+    /// let hash = AtomicU64::new(7);
+    /// assert_eq!(hash.fetch_fold(5, Ordering::Relaxed, MUL, ADD), 7);
+    /// let expected = 7_u64.wrapping_mul(MUL).wrapping_add(5 + ADD);
+    /// assert_eq!(hash.into_inner(), expected);
+    /// ```
+    fn fetch_fold(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+        mul: Self::Int,
+        add: Self::Int,
+    ) -> Self::Int {
+        let fold = |prev: Self::Int| {
+            Some(prev.wrapping_mul(mul).wrapping_add(val).wrapping_add(add))
+        };
+        match self.fetch_update(order, load_order(order), fold) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        }
+    }
+
+    /// Stores 0, returning the number of bits that were set in the previous
+    /// value.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU16};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let flags = AtomicU16::new(0b1010_0110);
+    /// assert_eq!(flags.fetch_reset_popcount(Ordering::AcqRel), 4);
+    /// assert_eq!(flags.fetch_reset_popcount(Ordering::AcqRel), 0);
+    /// ```
+    fn fetch_reset_popcount(&self, order: Ordering) -> u32 {
+        self.swap(Self::Int::ZERO, order).count_ones()
+    }
+
+    /// Clears the bits set in `mask`, returning the previous value.
+    ///
+    /// This is equivalent to `fetch_and(!mask, order)`, but makes the intent
+    /// of read-to-clear operations, such as acknowledging handled bits in a
+    /// status word, explicit:
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU8};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let status = AtomicU8::new(0b1011);
+    /// let prev = status.fetch_and_clear_mask(0b0011, Ordering::AcqRel);
+    /// assert_eq!(prev, 0b1011);
+    /// assert_eq!(status.load(Ordering::Relaxed), 0b1000);
+    /// ```
+    fn fetch_and_clear_mask(
+        &self,
+        mask: Self::Int,
+        order: Ordering,
+    ) -> Self::Int {
+        self.fetch_and(!mask, order)
+    }
+
+    /// Sets the bit at `index` (where 0 is the least significant bit),
+    /// returning its previous value.
+    ///
+    /// This is performed with [`Self::fetch_or`], like `lock bts` on x86.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than the number of bits in
+    /// [`Self::Int`].
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU32};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let flags = AtomicU32::new(0b0100);
+    /// assert!(!flags.fetch_bit_set(0, Ordering::AcqRel));
+    /// assert!(flags.fetch_bit_clear(2, Ordering::AcqRel));
+    /// assert!(!flags.fetch_bit_toggle(31, Ordering::AcqRel));
+    /// assert!(flags.bit_test(31, Ordering::Acquire));
+    /// assert_eq!(flags.load(Ordering::Relaxed), 0x8000_0001);
+    /// ```
+    fn fetch_bit_set(&self, index: u32, order: Ordering) -> bool {
+        let mask = bit_mask::<Self::Int>(index);
+        self.fetch_or(mask, order) & mask != Self::Int::ZERO
+    }
+
+    /// Clears the bit at `index`, returning its previous value.
+    ///
+    /// This is performed with [`Self::fetch_and`], like `lock btr` on x86.
+    /// See [`Self::fetch_bit_set`].
+    fn fetch_bit_clear(&self, index: u32, order: Ordering) -> bool {
+        let mask = bit_mask::<Self::Int>(index);
+        self.fetch_and(!mask, order) & mask != Self::Int::ZERO
+    }
+
+    /// Toggles the bit at `index`, returning its previous value.
+    ///
+    /// This is performed with [`Self::fetch_xor`], like `lock btc` on x86.
+    /// See [`Self::fetch_bit_set`].
+    fn fetch_bit_toggle(&self, index: u32, order: Ordering) -> bool {
+        let mask = bit_mask::<Self::Int>(index);
+        self.fetch_xor(mask, order) & mask != Self::Int::ZERO
+    }
+
+    /// Loads the value and returns the bit at `index`.
+    ///
+    /// See [`Self::fetch_bit_set`].
+    fn bit_test(&self, index: u32, order: Ordering) -> bool {
+        let mask = bit_mask::<Self::Int>(index);
+        self.load(order) & mask != Self::Int::ZERO
+    }
+
+    /// Adds `delta` to the current value if it is positive, or subtracts its
+    /// magnitude if it is negative, returning the previous value.
+    ///
+    /// This is intended for unsigned counters that are both incremented and
+    /// decremented. Like [`Self::fetch_add`], it wraps around on overflow:
+    /// for unsigned types, subtracting below zero wraps to the maximum value.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU32};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let balance = AtomicU32::new(10);
+    /// assert_eq!(balance.fetch_add_or_sub(5, Ordering::Relaxed), 10);
+    /// assert_eq!(balance.fetch_add_or_sub(-12, Ordering::Relaxed), 15);
+    /// assert_eq!(balance.fetch_add_or_sub(-4, Ordering::Relaxed), 3);
+    /// assert_eq!(balance.load(Ordering::Relaxed), u32::MAX);
+    /// ```
+    fn fetch_add_or_sub(
+        &self,
+        delta: <Self::Int as PrimInt>::Signed,
+        order: Ordering,
+    ) -> Self::Int {
+        self.fetch_add(Self::Int::from_signed_bits(delta), order)
+    }
+
+    /// Adds to the current value, returning the previous value, or
+    /// [`Overflow`] (leaving the value unchanged) if the addition would
+    /// overflow.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU8, Overflow};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let a = AtomicU8::new(250);
+    /// assert_eq!(a.try_fetch_add(5, Ordering::Relaxed), Ok(250));
+    /// assert_eq!(
+    ///     a.try_fetch_add(1, Ordering::Relaxed),
+    ///     Err(Overflow { current: 255 }),
+    /// );
+    /// assert_eq!(a.load(Ordering::Relaxed), 255);
+    /// ```
+    fn try_fetch_add(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+    ) -> Result<Self::Int, Overflow<Self::Int>> {
+        self.fetch_update(order, load_order(order), |v| v.checked_add(val))
+            .map_err(|current| Overflow {
+                current,
+            })
+    }
+
+    /// Subtracts from the current value, returning the previous value, or
+    /// [`Overflow`] (leaving the value unchanged) if the subtraction would
+    /// overflow.
+    ///
+    /// See [`Self::try_fetch_add`].
+    fn try_fetch_sub(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+    ) -> Result<Self::Int, Overflow<Self::Int>> {
+        self.fetch_update(order, load_order(order), |v| v.checked_sub(val))
+            .map_err(|current| Overflow {
+                current,
+            })
+    }
+
+    /// Multiplies the current value, returning the previous value, or
+    /// [`Overflow`] (leaving the value unchanged) if the multiplication would
+    /// overflow.
+    ///
+    /// See [`Self::try_fetch_add`].
+    fn try_fetch_mul(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+    ) -> Result<Self::Int, Overflow<Self::Int>> {
+        self.fetch_update(order, load_order(order), |v| v.checked_mul(val))
+            .map_err(|current| Overflow {
+                current,
+            })
+    }
+
+    /// Loads the value as a nonzero integer, returning [`None`] if it is 0.
+    ///
+    /// Together with [`Self::store_nonzero`], this allows an atomic to store
+    /// an [`Option`] of a nonzero integer, with 0 representing [`None`]:
+    ///
+    /// ```
+    /// use atomic_int::{AtomicIntExt, AtomicU32};
+    /// use std::num::NonZeroU32;
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let slot = AtomicU32::new(0);
+    /// assert_eq!(slot.load_nonzero(Ordering::Relaxed), None);
+    ///
+    /// let index = NonZeroU32::new(5);
+    /// slot.store_nonzero(index, Ordering::Relaxed);
+    /// assert_eq!(slot.load(Ordering::Relaxed), 5);
+    /// assert_eq!(slot.load_nonzero(Ordering::Relaxed), index);
+    ///
+    /// slot.store_nonzero(None, Ordering::Relaxed);
+    /// assert_eq!(slot.load(Ordering::Relaxed), 0);
+    /// ```
+    fn load_nonzero(
+        &self,
+        order: Ordering,
+    ) -> Option<<Self::Int as PrimInt>::NonZero> {
+        self.load(order).to_nonzero()
+    }
+
+    /// Stores a nonzero integer, or 0 if `val` is [`None`].
+    ///
+    /// See [`Self::load_nonzero`].
+    fn store_nonzero(
+        &self,
+        val: Option<<Self::Int as PrimInt>::NonZero>,
+        order: Ordering,
+    ) {
+        self.store(val.map_or(Self::Int::ZERO, PrimInt::from_nonzero), order);
+    }
+
+    /// Stores `val` if it is strictly greater than the current value,
+    /// returning whether the value was updated.
+    ///
+    /// This is like [`Self::fetch_max`], but makes the intent of publishing a
+    /// monotonically increasing value explicit. `order` applies to the
+    /// update; if the value is not updated, the load uses the corresponding
+    /// load ordering (e.g., [`Acquire`](Ordering::Acquire) for
+    /// [`AcqRel`](Ordering::AcqRel)).
+    fn store_if_greater(&self, val: Self::Int, order: Ordering) -> bool {
+        self.fetch_update(order, load_order(order), |prev| {
+            (val > prev).then_some(val)
+        })
+        .is_ok()
+    }
+
+    /// Stores `val` if it is strictly less than the current value, returning
+    /// whether the value was updated.
+    ///
+    /// See [`Self::store_if_greater`].
+    fn store_if_less(&self, val: Self::Int, order: Ordering) -> bool {
+        self.fetch_update(order, load_order(order), |prev| {
+            (val < prev).then_some(val)
+        })
+        .is_ok()
+    }
+
+    /// Applies `op` to the current value and `val`, returning the previous
+    /// value, the new value, and whether the operation overflowed.
+    ///
+    /// Every operation except [`ApplyOp::Mul`] is performed with the
+    /// corresponding `fetch_*` method, so it is lock-free whenever that
+    /// method is. Arithmetic wraps around on overflow, as with
+    /// [`Self::fetch_add`].
+    ///
+    /// ```
+    /// use atomic_int::{ApplyOp, AtomicIntExt, AtomicU8, RmwResult};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let a = AtomicU8::new(200);
+    /// assert_eq!(
+    ///     a.fetch_apply(100, Ordering::Relaxed, ApplyOp::Add),
+    ///     RmwResult {
+    ///         previous: 200,
+    ///         new: 44,
+    ///         overflowed: true,
+    ///     },
+    /// );
+    /// let r = a.fetch_apply(44, Ordering::Relaxed, ApplyOp::Sub);
+    /// assert_eq!((r.previous, r.new, r.overflowed), (44, 0, false));
+    /// let r = a.fetch_apply(1, Ordering::Relaxed, ApplyOp::Sub);
+    /// assert_eq!((r.new, r.overflowed), (255, true));
+    /// let r = a.fetch_apply(2, Ordering::Relaxed, ApplyOp::Mul);
+    /// assert_eq!((r.new, r.overflowed), (254, true));
+    /// let r = a.fetch_apply(3, Ordering::Relaxed, ApplyOp::Mul);
+    /// assert_eq!((r.new, r.overflowed), (250, true));
+    /// a.store(5, Ordering::Relaxed);
+    /// let r = a.fetch_apply(3, Ordering::Relaxed, ApplyOp::Mul);
+    /// assert_eq!((r.previous, r.new, r.overflowed), (5, 15, false));
+    ///
+    /// for (op, new) in [
+    ///     (ApplyOp::And, 0b0100),
+    ///     (ApplyOp::Or, 0b1111),
+    ///     (ApplyOp::Xor, 0b1011),
+    ///     (ApplyOp::Min, 0b0111),
+    ///     (ApplyOp::Max, 0b1100),
+    /// ] {
+    ///     a.store(0b0111, Ordering::Relaxed);
+    ///     let r = a.fetch_apply(0b1100, Ordering::Relaxed, op);
+    ///     assert_eq!(r.previous, 0b0111);
+    ///     assert_eq!((r.new, r.overflowed), (new, false));
+    ///     assert_eq!(a.load(Ordering::Relaxed), new);
+    /// }
+    /// ```
+    ///
+    /// The same holds for fallback types:
+    ///
+    /// ```
+    /// use atomic_int::{ApplyOp, AtomicI128, AtomicIntExt};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let a = AtomicI128::new(i128::MIN);
+    /// let r = a.fetch_apply(-1, Ordering::SeqCst, ApplyOp::Add);
+    /// assert_eq!((r.new, r.overflowed), (i128::MAX, true));
+    /// let r = a.fetch_apply(-1, Ordering::SeqCst, ApplyOp::Sub);
+    /// assert_eq!((r.new, r.overflowed), (i128::MIN, true));
+    /// let r = a.fetch_apply(-1, Ordering::SeqCst, ApplyOp::Mul);
+    /// assert_eq!((r.new, r.overflowed), (i128::MIN, true));
+    /// let r = a.fetch_apply(3, Ordering::SeqCst, ApplyOp::Max);
+    /// assert_eq!((r.previous, r.new, r.overflowed), (i128::MIN, 3, false));
+    /// ```
+    fn fetch_apply(
+        &self,
+        val: Self::Int,
+        order: Ordering,
+        op: ApplyOp,
+    ) -> RmwResult<Self::Int> {
+        let (previous, new, overflowed) = match op {
+            ApplyOp::Add => {
+                let prev = self.fetch_add(val, order);
+                let overflowed = prev.checked_add(val).is_none();
+                (prev, prev.wrapping_add(val), overflowed)
+            }
+            ApplyOp::Sub => {
+                let prev = self.fetch_sub(val, order);
+                let overflowed = prev.checked_sub(val).is_none();
+                (prev, prev.wrapping_sub(val), overflowed)
+            }
+            ApplyOp::And => {
+                let prev = self.fetch_and(val, order);
+                (prev, prev & val, false)
+            }
+            ApplyOp::Or => {
+                let prev = self.fetch_or(val, order);
+                (prev, prev | val, false)
+            }
+            ApplyOp::Xor => {
+                let prev = self.fetch_xor(val, order);
+                (prev, prev ^ val, false)
+            }
+            ApplyOp::Min => {
+                let prev = self.fetch_min(val, order);
+                (prev, prev.min(val), false)
+            }
+            ApplyOp::Max => {
+                let prev = self.fetch_max(val, order);
+                (prev, prev.max(val), false)
+            }
+            ApplyOp::Mul => {
+                let prev = self
+                    .fetch_update(order, load_order(order), |v| {
+                        Some(v.wrapping_mul(val))
+                    })
+                    .unwrap_or_else(|v| v);
+                let overflowed = prev.checked_mul(val).is_none();
+                (prev, prev.wrapping_mul(val), overflowed)
+            }
+        };
+        RmwResult {
+            previous,
+            new,
+            overflowed,
+        }
+    }
+
+    /// Blocks until the value is no longer `expected`, like
+    /// `std::atomic<T>::wait` in C++.
+    ///
+    /// The value is loaded with `order`; if it differs from `expected`, this
+    /// returns immediately. Otherwise, this blocks until another thread calls
+    /// [`Self::notify_one`] or [`Self::notify_all`] on this atomic and the
+    /// value has changed. Merely changing the value doesn’t wake waiting
+    /// threads.
+    ///
+    /// This uses `futex` on Linux and Android, `__ulock_wait` on macOS and
+    /// iOS, and `WaitOnAddress` on Windows. On other platforms, it spins.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicCInt, AtomicIntExt, AtomicU128};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let ready = AtomicCInt::new(0);
+    /// let total = AtomicU128::new(0);
+    /// std::thread::scope(|s| {
+    ///     s.spawn(|| {
+    ///         total.store(5, Ordering::Relaxed);
+    ///         ready.store(1, Ordering::Release);
+    ///         ready.notify_all();
+    ///         total.wait(5, Ordering::Relaxed);
+    ///     });
+    ///     ready.wait(0, Ordering::Acquire);
+    ///     assert_eq!(total.swap(6, Ordering::Relaxed), 5);
+    ///     total.notify_one();
+    /// });
+    /// ```
+    #[cfg(feature = "wait")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "wait")))]
+    fn wait(&self, expected: Self::Int, order: Ordering) {
+        crate::wait::wait(self as *const Self as usize, || {
+            self.load(order) == expected
+        });
+    }
+
+    /// Wakes a thread blocked in [`Self::wait`] on this atomic, if any.
+    ///
+    /// This may wake other threads too (including threads waiting on other
+    /// atomics), which then wait again if their values haven’t changed.
+    #[cfg(feature = "wait")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "wait")))]
+    fn notify_one(&self) {
+        crate::wait::notify(self as *const Self as usize);
+    }
+
+    /// Wakes all threads blocked in [`Self::wait`] on this atomic.
+    #[cfg(feature = "wait")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "wait")))]
+    fn notify_all(&self) {
+        crate::wait::notify(self as *const Self as usize);
+    }
+}
+
+/// Operations common to every atomic pointer type.
+///
+/// Like [`AtomicIntExt`], this trait is implemented both for the built-in
+/// [`AtomicPtr`](atomic::AtomicPtr) and for this crate’s fallback type, and
+/// each required method behaves like the method of the same name on
+/// [`AtomicPtr`](atomic::AtomicPtr).
+///
+/// ```
+/// use atomic_int::{AtomicPtr, AtomicPtrExt};
+/// use std::sync::atomic::Ordering;
+///
+/// // Works with both the built-in and the fallback `AtomicPtr`.
+/// fn take<T, A: AtomicPtrExt<T>>(a: &A) -> *mut T {
+///     a.swap(std::ptr::null_mut(), Ordering::AcqRel)
+/// }
+///
+/// let mut value = 5;
+/// let a = AtomicPtr::new(&mut value as *mut i32);
+/// assert_eq!(take(&a), &mut value as *mut i32);
+/// assert!(take(&a).is_null());
+/// ```
+pub trait AtomicPtrExt<T>: sealed::Sealed + Sized {
+    /// Creates a new atomic pointer.
+    fn new(p: *mut T) -> Self;
+
+    /// Returns a mutable reference to the underlying pointer.
+    fn get_mut(&mut self) -> &mut *mut T;
+
+    /// Consumes the atomic and returns the contained value.
+    fn into_inner(self) -> *mut T;
+
+    /// Loads a value from the pointer.
+    fn load(&self, order: Ordering) -> *mut T;
+
+    /// Stores a value into the pointer.
+    fn store(&self, ptr: *mut T, order: Ordering);
+
+    /// Stores a value into the pointer, returning the previous value.
+    fn swap(&self, ptr: *mut T, order: Ordering) -> *mut T;
+
+    /// Stores a value into the pointer if the current value is the same as
+    /// the `current` value.
+    fn compare_exchange(
+        &self,
+        current: *mut T,
+        new: *mut T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<*mut T, *mut T>;
+
+    /// Stores a value into the pointer if the current value is the same as
+    /// the `current` value.
+    fn compare_exchange_weak(
+        &self,
+        current: *mut T,
+        new: *mut T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<*mut T, *mut T>;
+
+    /// Fetches the value, and applies a function to it that returns an
+    /// optional new value.
+    fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        f: F,
+    ) -> Result<*mut T, *mut T>
+    where
+        F: FnMut(*mut T) -> Option<*mut T>;
+
+    /// Offsets the pointer by `val` elements of `T`, returning the previous
+    /// pointer.
+    ///
+    /// Like the other pointer arithmetic methods below, this polyfills the
+    /// method of the same name on newer versions of the built-in
+    /// [`AtomicPtr`](atomic::AtomicPtr), which takes precedence where
+    /// available and behaves identically. The new pointer is computed with
+    /// `wrapping_add`, so it retains the provenance of the current pointer,
+    /// and the operation is performed with [`Self::fetch_update`] (which, on
+    /// the fallback type, acquires the lock once).
+    ///
+    /// ```
+    /// use atomic_int::{AtomicPtr, AtomicPtrExt};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let mut array = [0_u32; 4];
+    /// let base = array.as_mut_ptr();
+    /// let atomic = AtomicPtr::new(base);
+    /// assert_eq!(atomic.fetch_ptr_add(3, Ordering::Relaxed), base);
+    /// let prev = atomic.fetch_byte_sub(4, Ordering::Relaxed);
+    /// assert_eq!(prev, base.wrapping_add(3));
+    /// assert_eq!(atomic.load(Ordering::Relaxed), base.wrapping_add(2));
+    ///
+    /// // `u32` is 4-byte aligned, so the low 2 bits can hold a tag.
+    /// atomic.fetch_or(0b01, Ordering::Relaxed);
+    /// let tagged = atomic.fetch_xor(0b11, Ordering::Relaxed);
+    /// assert_eq!(tagged as usize & 0b11, 0b01);
+    /// let tagged = atomic.fetch_and(!0b11, Ordering::Relaxed);
+    /// assert_eq!(tagged as usize & 0b11, 0b10);
+    /// assert_eq!(atomic.load(Ordering::Relaxed), base.wrapping_add(2));
+    /// ```
+    fn fetch_ptr_add(&self, val: usize, order: Ordering) -> *mut T {
+        self.fetch_byte_add(val.wrapping_mul(mem::size_of::<T>()), order)
+    }
+
+    /// Offsets the pointer backwards by `val` elements of `T`, returning the
+    /// previous pointer.
+    ///
+    /// See [`Self::fetch_ptr_add`].
+    fn fetch_ptr_sub(&self, val: usize, order: Ordering) -> *mut T {
+        self.fetch_byte_sub(val.wrapping_mul(mem::size_of::<T>()), order)
+    }
+
+    /// Offsets the pointer by `val` bytes, returning the previous pointer.
+    ///
+    /// See [`Self::fetch_ptr_add`].
+    fn fetch_byte_add(&self, val: usize, order: Ordering) -> *mut T {
+        fetch_map_ptr(self, order, |p| p.cast::<u8>().wrapping_add(val).cast())
+    }
+
+    /// Offsets the pointer backwards by `val` bytes, returning the previous
+    /// pointer.
+    ///
+    /// See [`Self::fetch_ptr_add`].
+    fn fetch_byte_sub(&self, val: usize, order: Ordering) -> *mut T {
+        fetch_map_ptr(self, order, |p| p.cast::<u8>().wrapping_sub(val).cast())
+    }
+
+    /// Performs a bitwise “or” on the address of the pointer, returning the
+    /// previous pointer.
+    ///
+    /// This is typically used to set tag bits in the unused low bits of an
+    /// aligned pointer. The new pointer retains the provenance of the current
+    /// pointer. See [`Self::fetch_ptr_add`].
+    fn fetch_or(&self, val: usize, order: Ordering) -> *mut T {
+        fetch_map_ptr(self, order, |p| map_addr(p, |a| a | val))
+    }
+
+    /// Performs a bitwise “and” on the address of the pointer, returning the
+    /// previous pointer.
+    ///
+    /// See [`Self::fetch_or`].
+    fn fetch_and(&self, val: usize, order: Ordering) -> *mut T {
+        fetch_map_ptr(self, order, |p| map_addr(p, |a| a & val))
+    }
+
+    /// Performs a bitwise “xor” on the address of the pointer, returning the
+    /// previous pointer.
+    ///
+    /// See [`Self::fetch_or`].
+    fn fetch_xor(&self, val: usize, order: Ordering) -> *mut T {
+        fetch_map_ptr(self, order, |p| map_addr(p, |a| a ^ val))
+    }
+
+    /// Offsets the pointer by `val` elements of `T`, but only if the result
+    /// does not exceed `end`, returning the previous pointer.
+    ///
+    /// If the current pointer is greater than `end`, or advancing it by `val`
+    /// elements would move it past `end`, the pointer is left unchanged and
+    /// [`None`] is returned. This makes it possible to hand out elements of an
+    /// array to multiple threads without ever forming a pointer beyond its
+    /// end.
+    ///
+    /// The new pointer is computed with `wrapping_add` and retains the
+    /// provenance of the current pointer.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicPtr, AtomicPtrExt};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let mut array = [10, 20, 30, 40, 50];
+    /// let end = array.as_mut_ptr_range().end;
+    /// let next = AtomicPtr::new(array.as_mut_ptr());
+    /// let advance = |n| {
+    ///     next.fetch_ptr_add_bounded(n, end, Ordering::Relaxed)
+    /// };
+    /// let mut taken = Vec::new();
+    /// // Hand out two elements at a time until fewer than two remain.
+    /// while let Some(p) = advance(2) {
+    ///     // SAFETY: `p` points to one of the first four elements.
+    ///     taken.push(unsafe { *p });
+    /// }
+    /// assert_eq!(taken, [10, 30]);
+    /// // The last element remains, and can still be taken on its own.
+    /// // SAFETY: The pointer points to the last element.
+    /// assert_eq!(advance(1).map(|p| unsafe { *p }), Some(50));
+    /// assert_eq!(next.load(Ordering::Relaxed), end);
+    /// assert_eq!(advance(1), None);
+    /// ```
+    fn fetch_ptr_add_bounded(
+        &self,
+        val: usize,
+        end: *mut T,
+        order: Ordering,
+    ) -> Option<*mut T> {
+        self.fetch_update(order, load_order(order), |prev| {
+            let remaining = (end as usize).checked_sub(prev as usize)?;
+            let bytes = val.checked_mul(core::mem::size_of::<T>())?;
+            (bytes <= remaining).then(|| prev.wrapping_add(val))
+        })
+        .ok()
+    }
+
+    /// Splits the pointer into address and tag bits, lets `f` compute new
+    /// ones, and stores the recombined pointer, returning the previous
+    /// pointer.
+    ///
+    /// `f` receives the bits of the current pointer selected by `addr_mask`
+    /// and by `tag_mask` (without shifting them), and returns new address and
+    /// tag bits, which are masked in the same way. Bits outside both masks
+    /// are left unchanged. The new pointer is derived from the current one
+    /// with `wrapping_add`, so it retains the current pointer’s provenance.
+    ///
+    /// Like [`Self::fetch_update`], `f` may be called multiple times on the
+    /// built-in atomic; on the fallback type, the whole update is performed
+    /// while holding the lock.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicPtr, AtomicPtrExt};
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let mut value = 0_u64;
+    /// let base: *mut u64 = &mut value;
+    /// let atomic = AtomicPtr::new(base);
+    ///
+    /// // `u64` is 8-byte aligned, so the low 3 bits can hold a version.
+    /// let bump = |addr, tag| (addr, (tag + 1) & 0b111);
+    /// atomic.fetch_update_addr_masked(!0b111, 0b111, Ordering::AcqRel, bump);
+    /// atomic.fetch_update_addr_masked(!0b111, 0b111, Ordering::AcqRel, bump);
+    ///
+    /// let tagged = atomic.load(Ordering::Acquire);
+    /// assert_eq!(tagged as usize & 0b111, 2);
+    /// assert_eq!(tagged as usize & !0b111, base as usize);
+    /// ```
+    fn fetch_update_addr_masked<F>(
+        &self,
+        addr_mask: usize,
+        tag_mask: usize,
+        order: Ordering,
+        mut f: F,
+    ) -> *mut T
+    where
+        F: FnMut(usize, usize) -> (usize, usize),
+    {
+        let update = |prev: *mut T| {
+            let bits = prev as usize;
+            let (addr, tag) = f(bits & addr_mask, bits & tag_mask);
+            let new = (bits & !(addr_mask | tag_mask))
+                | (addr & addr_mask)
+                | (tag & tag_mask);
+            let prev = prev.cast::<u8>();
+            Some(prev.wrapping_add(new.wrapping_sub(bits)).cast())
+        };
+        match self.fetch_update(order, load_order(order), update) {
+            Ok(prev) => prev,
+            Err(prev) => prev,
+        }
+    }
+
+    /// Replaces the pointer with `new`, after linking `new` to the current
+    /// pointer, as when pushing a node onto a lock-free stack.
+    ///
+    /// This repeatedly loads the current pointer `head`, calls
+    /// `set_next(new, head)` so that `new` can store `head` as its successor,
+    /// and then attempts to replace `head` with `new`, retrying if the
+    /// pointer changed in the meantime. `set_next` may therefore be called
+    /// several times, and must not assume that its second argument will
+    /// remain the head. `order` applies to the successful replacement;
+    /// [`Release`](Ordering::Release) is sufficient to publish the writes
+    /// made by `set_next`.
+    ///
+    /// On the fallback type, each attempt acquires the lock once.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicPtr, AtomicPtrExt};
+    /// use std::ptr;
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// struct Node {
+    ///     value: u32,
+    ///     next: *mut Node,
+    /// }
+    ///
+    /// // A Treiber stack.
+    /// let head = AtomicPtr::new(ptr::null_mut());
+    /// std::thread::scope(|s| {
+    ///     for value in 0..4 {
+    ///         let head = &head;
+    ///         s.spawn(move || {
+    ///             let node = Box::into_raw(Box::new(Node {
+    ///                 value,
+    ///                 next: ptr::null_mut(),
+    ///             }));
+    ///             head.push_like(
+    ///                 node,
+    ///                 // SAFETY: `node` is not yet shared with other threads.
+    ///                 |node, next| unsafe { (*node).next = next },
+    ///                 Ordering::Release,
+    ///             );
+    ///         });
+    ///     }
+    /// });
+    ///
+    /// let mut values = Vec::new();
+    /// let mut node = head.load(Ordering::Acquire);
+    /// while !node.is_null() {
+    ///     // SAFETY: Every node was created by `Box::into_raw`.
+    ///     let boxed = unsafe { Box::from_raw(node) };
+    ///     values.push(boxed.value);
+    ///     node = boxed.next;
+    /// }
+    /// values.sort();
+    /// assert_eq!(values, [0, 1, 2, 3]);
+    /// ```
+    fn push_like<F>(&self, new: *mut T, mut set_next: F, order: Ordering)
+    where
+        F: FnMut(*mut T, *mut T),
+    {
+        let mut head = self.load(Ordering::Relaxed);
+        loop {
+            set_next(new, head);
+            match self.compare_exchange_weak(
+                head,
+                new,
+                order,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Blocks until the pointer is no longer `expected`.
+    ///
+    /// See [`AtomicIntExt::wait`].
+    #[cfg(feature = "wait")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "wait")))]
+    fn wait(&self, expected: *mut T, order: Ordering) {
+        crate::wait::wait(self as *const Self as usize, || {
+            self.load(order) == expected
+        });
+    }
+
+    /// Wakes a thread blocked in [`Self::wait`] on this atomic, if any.
+    ///
+    /// See [`AtomicIntExt::notify_one`].
+    #[cfg(feature = "wait")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "wait")))]
+    fn notify_one(&self) {
+        crate::wait::notify(self as *const Self as usize);
+    }
+
+    /// Wakes all threads blocked in [`Self::wait`] on this atomic.
+    #[cfg(feature = "wait")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "wait")))]
+    fn notify_all(&self) {
+        crate::wait::notify(self as *const Self as usize);
+    }
+}
+
+/// Returns a pointer with the address `f(p as usize)` and the provenance of
+/// `p`, like `pointer::map_addr`, which is newer than this crate’s minimum
+/// supported Rust version.
+pub(crate) fn map_addr<T>(
+    p: *mut T,
+    f: impl FnOnce(usize) -> usize,
+) -> *mut T {
+    let addr = p as usize;
+    p.cast::<u8>().wrapping_add(f(addr).wrapping_sub(addr)).cast()
+}
+
+/// Replaces the pointer in `atomic` with `f` applied to it, returning the
+/// previous pointer.
+fn fetch_map_ptr<T, A: AtomicPtrExt<T>>(
+    atomic: &A,
+    order: Ordering,
+    mut f: impl FnMut(*mut T) -> *mut T,
+) -> *mut T {
+    match atomic.fetch_update(order, load_order(order), |p| Some(f(p))) {
+        Ok(prev) => prev,
+        Err(prev) => prev,
+    }
+}
+
+/// Operations common to every atomic type exported by this crate.
+///
+/// Unlike [`AtomicIntExt`] and [`AtomicPtrExt`], this trait is also
+/// implemented for [`AtomicBool`](atomic::AtomicBool), the atomic floats,
+/// and [`Atomic<T>`](crate::Atomic), and it provides only the operations that
+/// all of those types support. Each method behaves like the inherent method
+/// of the same name on the implementing type; in particular, the floats and
+/// [`Atomic<T>`](crate::Atomic) compare values bitwise in
+/// [`compare_exchange`](Self::compare_exchange).
+///
+/// ```
+/// use atomic_int::{AnyAtomic, AtomicCInt, AtomicU64};
+/// use std::sync::atomic::Ordering;
+///
+/// fn bump<A, F>(a: &A, f: F) -> A::Prim
+/// where
+///     A: AnyAtomic,
+///     F: Fn(A::Prim) -> A::Prim,
+/// {
+///     let order = Ordering::Relaxed;
+///     match a.fetch_update(order, order, |v| Some(f(v))) {
+///         Ok(v) | Err(v) => v,
+///     }
+/// }
+///
+/// let a = AtomicCInt::new(1);
+/// let b = AtomicU64::new(u64::MAX);
+/// assert_eq!(bump(&a, |v| v + 1), 1);
+/// assert_eq!(bump(&b, |v| v / 2), u64::MAX);
+/// assert_eq!(a.load(Ordering::Relaxed), 2);
+/// assert_eq!(b.load(Ordering::Relaxed), u64::MAX / 2);
+/// ```
+pub trait AnyAtomic: sealed::Sealed + Sized {
+    /// The type of the value stored in the atomic.
+    type Prim: Copy;
+
+    /// Whether this type is always lock-free, i.e., whether it is a built-in
+    /// atomic rather than a lock-based fallback type.
+    ///
+    /// Like `std::atomic<T>::is_always_lock_free` in C++, this depends only on
+    /// the type and target, so it can be used in constant expressions.
+    #[cfg_attr(not(force_fallback), doc = "```")]
+    #[cfg_attr(force_fallback, doc = "```ignore")]
+    /// use atomic_int::{AnyAtomic, Atomic, AtomicU8};
+    ///
+    /// const NATIVE: bool = <AtomicU8 as AnyAtomic>::IS_ALWAYS_LOCK_FREE;
+    /// assert!(NATIVE);
+    /// assert!(!<Atomic<[u64; 3]> as AnyAtomic>::IS_ALWAYS_LOCK_FREE);
+    /// ```
+    const IS_ALWAYS_LOCK_FREE: bool;
+
+    /// Returns whether operations on this type are lock-free.
+    ///
+    /// This is true whenever [`Self::IS_ALWAYS_LOCK_FREE`] is, and it can also
+    /// be true for types whose support for lock-free operations is detected
+    /// at run time, like 128-bit atomics on x86-64 with `asm-atomics`.
+    fn is_lock_free() -> bool {
+        Self::IS_ALWAYS_LOCK_FREE
+    }
+
+    /// Creates a new atomic.
+    fn new(v: Self::Prim) -> Self;
+
+    /// Loads a value from the atomic.
+    fn load(&self, order: Ordering) -> Self::Prim;
+
+    /// Stores a value into the atomic.
+    fn store(&self, val: Self::Prim, order: Ordering);
+
+    /// Stores a value into the atomic, returning the previous value.
+    fn swap(&self, val: Self::Prim, order: Ordering) -> Self::Prim;
+
+    /// Stores a value into the atomic if the current value is the same as
+    /// the `current` value.
+    fn compare_exchange(
+        &self,
+        current: Self::Prim,
+        new: Self::Prim,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Self::Prim, Self::Prim>;
+
+    /// Fetches the value, and applies a function to it that returns an
+    /// optional new value.
+    fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        f: F,
+    ) -> Result<Self::Prim, Self::Prim>
+    where
+        F: FnMut(Self::Prim) -> Option<Self::Prim>;
+}
+
+/// Used by [`AtomicIntExt::from_ptr`] and the fallback types’ `from_ptr` to
+/// check at compile time that an atomic `A` has the same size as its value
+/// `T`.
+pub(crate) struct SameLayout<A, T>(PhantomData<(A, T)>);
+
+impl<A, T> SameLayout<A, T> {
+    pub(crate) const CHECK: () = assert!(
+        mem::size_of::<A>() == mem::size_of::<T>(),
+        "`from_ptr` requires an atomic with the same layout as its integer \
+         (for fallback types, enable the feature `striped`)",
+    );
+}
+
+/// Returns an integer with only the bit at `index` set.
+pub(crate) fn bit_mask<I: PrimInt>(index: u32) -> I {
+    assert!(index < I::BITS, "bit index out of range");
+    I::ONE << index
+}
+
+/// Returns the strongest ordering that is valid for a load, and no stronger
+/// than `order`. This is used as the failure ordering of compare-and-swap
+/// loops.
+pub(crate) fn load_order(order: Ordering) -> Ordering {
+    match order {
+        Ordering::Release => Ordering::Relaxed,
+        Ordering::AcqRel => Ordering::Acquire,
+        order => order,
+    }
+}
+
+/// Returns the largest value in a slice of atomics, or [`None`] if the slice
+/// is empty.
+///
+/// Each atomic is loaded individually with ordering `order`, so if the
+/// atomics are modified concurrently, the result may not correspond to the
+/// maximum at any single point in time. Values are compared as
+/// [`A::Int`](AtomicIntExt::Int), so signed integers are ordered correctly:
+///
+/// ```
+/// use atomic_int::{AtomicI32, AtomicIntExt, max_of};
+/// use std::sync::atomic::Ordering;
+///
+/// let slice = AtomicI32::from_array([-5, 3, -10, 0]);
+/// assert_eq!(max_of(&slice, Ordering::Relaxed), Some(3));
+/// assert_eq!(max_of(&slice[..1], Ordering::Relaxed), Some(-5));
+/// assert_eq!(max_of::<AtomicI32>(&[], Ordering::Relaxed), None);
+/// ```
+///
+/// While the atomics are only ever increased, the result is at least the
+/// maximum before the call and at most the maximum after it:
+///
+/// ```
+/// use atomic_int::{AtomicU32, max_of};
+/// use std::sync::atomic::Ordering;
+/// use std::thread;
+///
+/// let counters: [AtomicU32; 4] = Default::default();
+/// thread::scope(|s| {
+///     for counter in &counters {
+///         s.spawn(move || {
+///             for _ in 0..1000 {
+///                 counter.fetch_add(1, Ordering::Relaxed);
+///             }
+///         });
+///     }
+///     let mut prev = 0;
+///     for _ in 0..1000 {
+///         let max = max_of(&counters, Ordering::Relaxed).unwrap();
+///         assert!((prev..=1000).contains(&max));
+///         prev = max;
+///     }
+/// });
+/// assert_eq!(max_of(&counters, Ordering::Relaxed), Some(1000));
+/// ```
+pub fn max_of<A: AtomicIntExt>(
+    slice: &[A],
+    order: Ordering,
+) -> Option<A::Int> {
+    slice.iter().map(|a| a.load(order)).max()
+}
+
+/// Returns the smallest value in a slice of atomics, or [`None`] if the slice
+/// is empty.
+///
+/// See [`max_of`] for details.
+///
+/// ```
+/// use atomic_int::{AtomicIntExt, AtomicU8, min_of};
+/// use std::sync::atomic::Ordering;
+///
+/// // Unsigned values are compared as such, so 200 is not negative.
+/// let slice = AtomicU8::from_array([7, 200, 3]);
+/// assert_eq!(min_of(&slice, Ordering::Relaxed), Some(3));
+/// assert_eq!(min_of(&slice[1..2], Ordering::Relaxed), Some(200));
+/// assert_eq!(min_of::<AtomicU8>(&[], Ordering::Relaxed), None);
+/// ```
+///
+/// While the atomics are only ever decreased, the result is at most the
+/// minimum before the call and at least the minimum after it:
+///
+/// ```
+/// use atomic_int::{AtomicU32, AtomicIntExt, min_of};
+/// use std::sync::atomic::Ordering;
+/// use std::thread;
+///
+/// let counters = AtomicU32::from_array([1000; 4]);
+/// thread::scope(|s| {
+///     for counter in &counters {
+///         s.spawn(move || {
+///             for _ in 0..1000 {
+///                 counter.fetch_sub(1, Ordering::Relaxed);
+///             }
+///         });
+///     }
+///     let mut prev = 1000;
+///     for _ in 0..1000 {
+///         let min = min_of(&counters, Ordering::Relaxed).unwrap();
+///         assert!((0..=prev).contains(&min));
+///         prev = min;
+///     }
+/// });
+/// assert_eq!(min_of(&counters, Ordering::Relaxed), Some(0));
+/// ```
+pub fn min_of<A: AtomicIntExt>(
+    slice: &[A],
+    order: Ordering,
+) -> Option<A::Int> {
+    slice.iter().map(|a| a.load(order)).min()
+}
+
+/// Writes the values in a slice of atomics to `f` as a list, like
+/// `[1, 2, 3]`.
+///
+/// This is intended for implementing [`Debug`] or [`Display`](fmt::Display)
+/// on types that contain atomics. Each atomic is loaded individually with
+/// ordering `order`; see [`max_of`].
+pub fn fmt_slice<A: AtomicIntExt>(
+    slice: &[A],
+    order: Ordering,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    f.debug_list().entries(slice.iter().map(|a| a.load(order))).finish()
+}
+
+/// Returns the values in a slice of atomics formatted as a list, like
+/// `[1, 2, 3]`.
+///
+/// Each atomic is loaded individually with ordering `order`; see [`max_of`].
+///
+/// ```
+/// use atomic_int::{AtomicI8, AtomicIntExt, dump_slice};
+/// use std::sync::atomic::Ordering;
+///
+/// let slice = AtomicI8::from_array([1, -2, 3]);
+/// assert_eq!(dump_slice(&slice, Ordering::Relaxed), "[1, -2, 3]");
+/// assert_eq!(dump_slice::<AtomicI8>(&[], Ordering::Relaxed), "[]");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn dump_slice<A: AtomicIntExt>(
+    slice: &[A],
+    order: Ordering,
+) -> alloc::string::String {
+    struct Dump<'a, A>(&'a [A], Ordering);
+
+    impl<A: AtomicIntExt> fmt::Display for Dump<'_, A> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt_slice(self.0, self.1, f)
+        }
+    }
+
+    alloc::string::ToString::to_string(&Dump(slice, order))
+}
+
+/// Implements [`AnyAtomic`] for an atomic type by forwarding to its inherent
+/// methods. The generic parameters of the impl, if any, go in brackets, and
+/// `$lock_free` is the value of [`AnyAtomic::IS_ALWAYS_LOCK_FREE`]. If it
+/// can be false for a lock-free type, `$runtime` is the run-time check used
+/// for [`AnyAtomic::is_lock_free`].
+macro_rules! impl_any_atomic {
+    (
+        [$($generics:tt)*] $atomic:ty,
+        $prim:ty,
+        $lock_free:expr $(, $runtime:expr)? $(,)?
+    ) => {
+        impl<$($generics)*> $crate::AnyAtomic for $atomic {
+            type Prim = $prim;
+
+            const IS_ALWAYS_LOCK_FREE: bool = $lock_free;
+
+            $(
+                fn is_lock_free() -> bool {
+                    $runtime
+                }
+            )?
+
+            fn new(v: $prim) -> Self {
+                <$atomic>::new(v)
+            }
+
+            fn load(&self, order: Ordering) -> $prim {
+                <$atomic>::load(self, order)
+            }
+
+            fn store(&self, val: $prim, order: Ordering) {
+                <$atomic>::store(self, val, order)
+            }
+
+            fn swap(&self, val: $prim, order: Ordering) -> $prim {
+                <$atomic>::swap(self, val, order)
+            }
+
+            fn compare_exchange(
+                &self,
+                current: $prim,
+                new: $prim,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$prim, $prim> {
+                <$atomic>::compare_exchange(
+                    self, current, new, success, failure,
+                )
+            }
+
+            fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                f: F,
+            ) -> Result<$prim, $prim>
+            where
+                F: FnMut($prim) -> Option<$prim>,
+            {
+                <$atomic>::fetch_update(self, set_order, fetch_order, f)
+            }
+        }
+    };
+}
+
+/// Implements `Serialize` and `Deserialize` for one of this crate's own
+/// atomic types, serializing the value loaded with `SeqCst` ordering. The
+/// generic parameters of the impls, if any, go in brackets.
+#[cfg(feature = "serde")]
+macro_rules! impl_serde {
+    ([$($generics:tt)*] $atomic:ty, $prim:ty) => {
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "serde")))]
+        impl<$($generics)*> serde::Serialize for $atomic
+        where
+            $prim: serde::Serialize,
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                let value = self.load(core::sync::atomic::Ordering::SeqCst);
+                serde::Serialize::serialize(&value, serializer)
+            }
+        }
+
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "serde")))]
+        impl<'de, $($generics)*> serde::Deserialize<'de> for $atomic
+        where
+            $prim: serde::Deserialize<'de>,
+        {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                <$prim as serde::Deserialize<'de>>::deserialize(deserializer)
+                    .map(Self::new)
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "serde"))]
+macro_rules! impl_serde {
+    ($($tt:tt)*) => {};
+}
+
+/// Implements [`bytemuck::Zeroable`]. Callers must ensure that the all-zero
+/// bit pattern is a valid value of the type.
+#[cfg(feature = "bytemuck")]
+macro_rules! impl_zeroable {
+    ([$($generics:tt)*] $type:ty) => {
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "bytemuck")))]
+        unsafe impl<$($generics)*> bytemuck::Zeroable for $type {}
+    };
+}
+
+#[cfg(not(feature = "bytemuck"))]
+macro_rules! impl_zeroable {
+    ($($tt:tt)*) => {};
+}
+
+/// Implements [`AtomicIntExt`] for an atomic type by forwarding to its
+/// inherent methods.
+macro_rules! impl_atomic_int_ext {
+    ($atomic:ty, $int:ty, $($lock_free:expr),+ $(,)?) => {
+        impl $crate::ext::sealed::Sealed for $atomic {}
+
+        impl_any_atomic!([] $atomic, $int, $($lock_free),+);
+
+        impl $crate::AtomicIntExt for $atomic {
+            type Int = $int;
+
+            fn new(v: $int) -> Self {
+                <$atomic>::new(v)
+            }
+
+            fn get_mut(&mut self) -> &mut $int {
+                <$atomic>::get_mut(self)
+            }
+
+            fn into_inner(self) -> $int {
+                <$atomic>::into_inner(self)
+            }
+
+            fn load(&self, order: Ordering) -> $int {
+                <$atomic>::load(self, order)
+            }
+
+            fn store(&self, val: $int, order: Ordering) {
+                <$atomic>::store(self, val, order)
+            }
+
+            fn swap(&self, val: $int, order: Ordering) -> $int {
+                <$atomic>::swap(self, val, order)
+            }
+
+            fn compare_exchange(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                <$atomic>::compare_exchange(
+                    self, current, new, success, failure,
+                )
+            }
+
+            fn compare_exchange_weak(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                <$atomic>::compare_exchange_weak(
+                    self, current, new, success, failure,
+                )
+            }
+
+            fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                f: F,
+            ) -> Result<$int, $int>
+            where
+                F: FnMut($int) -> Option<$int>,
+            {
+                <$atomic>::fetch_update(self, set_order, fetch_order, f)
+            }
+
+            fn fetch_add(&self, val: $int, order: Ordering) -> $int {
+                <$atomic>::fetch_add(self, val, order)
+            }
+
+            fn fetch_sub(&self, val: $int, order: Ordering) -> $int {
+                <$atomic>::fetch_sub(self, val, order)
+            }
+
+            fn fetch_and(&self, val: $int, order: Ordering) -> $int {
+                <$atomic>::fetch_and(self, val, order)
+            }
+
+            fn fetch_nand(&self, val: $int, order: Ordering) -> $int {
+                <$atomic>::fetch_nand(self, val, order)
+            }
+
+            fn fetch_or(&self, val: $int, order: Ordering) -> $int {
+                <$atomic>::fetch_or(self, val, order)
+            }
+
+            fn fetch_xor(&self, val: $int, order: Ordering) -> $int {
+                <$atomic>::fetch_xor(self, val, order)
+            }
+
+            fn fetch_max(&self, val: $int, order: Ordering) -> $int {
+                <$atomic>::fetch_max(self, val, order)
+            }
+
+            fn fetch_min(&self, val: $int, order: Ordering) -> $int {
+                <$atomic>::fetch_min(self, val, order)
+            }
+        }
+    };
+}
+
+/// Implements [`AtomicPtrExt`] for an atomic pointer type by forwarding to
+/// its inherent methods.
+macro_rules! impl_atomic_ptr_ext {
+    ($atomic:ident, $lock_free:expr) => {
+        impl<T> $crate::ext::sealed::Sealed for $atomic<T> {}
+
+        impl_any_atomic!([T] $atomic<T>, *mut T, $lock_free);
+
+        impl<T> $crate::AtomicPtrExt<T> for $atomic<T> {
+            fn new(p: *mut T) -> Self {
+                $atomic::new(p)
+            }
+
+            fn get_mut(&mut self) -> &mut *mut T {
+                $atomic::get_mut(self)
+            }
+
+            fn into_inner(self) -> *mut T {
+                $atomic::into_inner(self)
+            }
+
+            fn load(&self, order: Ordering) -> *mut T {
+                $atomic::load(self, order)
+            }
+
+            fn store(&self, ptr: *mut T, order: Ordering) {
+                $atomic::store(self, ptr, order)
+            }
+
+            fn swap(&self, ptr: *mut T, order: Ordering) -> *mut T {
+                $atomic::swap(self, ptr, order)
+            }
+
+            fn compare_exchange(
+                &self,
+                current: *mut T,
+                new: *mut T,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<*mut T, *mut T> {
+                $atomic::compare_exchange(self, current, new, success, failure)
+            }
+
+            fn compare_exchange_weak(
+                &self,
+                current: *mut T,
+                new: *mut T,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<*mut T, *mut T> {
+                $atomic::compare_exchange_weak(
+                    self, current, new, success, failure,
+                )
+            }
+
+            fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                f: F,
+            ) -> Result<*mut T, *mut T>
+            where
+                F: FnMut(*mut T) -> Option<*mut T>,
+            {
+                $atomic::fetch_update(self, set_order, fetch_order, f)
+            }
+        }
+    };
+}
+
+/// Implements the traits from [`atomic_traits`] for one of this crate's own
+/// atomic types by forwarding to its inherent methods: `Atomic` and
+/// `fetch::Update` for every type, plus `NumOps`, `Bitwise`, and the
+/// `fetch::*` traits they require for integers (`int`). The generic
+/// parameters of the impls, if any, go in brackets.
+#[cfg(feature = "atomic-traits")]
+macro_rules! impl_atomic_traits {
+    (int $atomic:ty, $int:ty) => {
+        impl_atomic_traits!([] $atomic, $int);
+        impl_atomic_traits!(
+            @fetch $atomic, $int,
+            Add::fetch_add,
+            Sub::fetch_sub,
+            And::fetch_and,
+            Nand::fetch_nand,
+            Or::fetch_or,
+            Xor::fetch_xor,
+            Max::fetch_max,
+            Min::fetch_min,
+        );
+
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "atomic-traits")))]
+        impl atomic_traits::NumOps for $atomic {}
+
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "atomic-traits")))]
+        impl atomic_traits::Bitwise for $atomic {}
+    };
+
+    (@fetch $atomic:ty, $int:ty, $($trait:ident::$method:ident),* $(,)?) => {
+        $(
+            #[cfg_attr(
+                feature = "doc_cfg",
+                doc(cfg(feature = "atomic-traits")),
+            )]
+            impl atomic_traits::fetch::$trait for $atomic {
+                type Type = $int;
+
+                fn $method(&self, val: $int, order: Ordering) -> $int {
+                    <$atomic>::$method(self, val, order)
+                }
+            }
+        )*
+    };
+
+    ([$($generics:tt)*] $atomic:ty, $prim:ty) => {
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "atomic-traits")))]
+        impl<$($generics)*> atomic_traits::Atomic for $atomic {
+            type Type = $prim;
+
+            fn new(v: $prim) -> Self {
+                <$atomic>::new(v)
+            }
+
+            fn get_mut(&mut self) -> &mut $prim {
+                <$atomic>::get_mut(self)
+            }
+
+            fn into_inner(self) -> $prim {
+                <$atomic>::into_inner(self)
+            }
+
+            fn load(&self, order: Ordering) -> $prim {
+                <$atomic>::load(self, order)
+            }
+
+            fn store(&self, val: $prim, order: Ordering) {
+                <$atomic>::store(self, val, order)
+            }
+
+            fn swap(&self, val: $prim, order: Ordering) -> $prim {
+                <$atomic>::swap(self, val, order)
+            }
+
+            fn compare_and_swap(
+                &self,
+                current: $prim,
+                new: $prim,
+                order: Ordering,
+            ) -> $prim {
+                let failure = $crate::ext::load_order(order);
+                match <$atomic>::compare_exchange(
+                    self, current, new, order, failure,
+                ) {
+                    Ok(v) | Err(v) => v,
+                }
+            }
+
+            fn compare_exchange(
+                &self,
+                current: $prim,
+                new: $prim,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$prim, $prim> {
+                <$atomic>::compare_exchange(
+                    self, current, new, success, failure,
+                )
+            }
+
+            fn compare_exchange_weak(
+                &self,
+                current: $prim,
+                new: $prim,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$prim, $prim> {
+                <$atomic>::compare_exchange_weak(
+                    self, current, new, success, failure,
+                )
+            }
+        }
+
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "atomic-traits")))]
+        impl<$($generics)*> atomic_traits::fetch::Update for $atomic {
+            type Type = $prim;
+
+            fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                f: F,
+            ) -> Result<$prim, $prim>
+            where
+                F: FnMut($prim) -> Option<$prim>,
+            {
+                <$atomic>::fetch_update(self, set_order, fetch_order, f)
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "atomic-traits"))]
+#[allow(unused_macros)]
+macro_rules! impl_atomic_traits {
+    ($($tt:tt)*) => {};
+}
+
+// With `no_atomic_bool`, `AtomicBool` has no inherent `swap` or
+// `compare_exchange`, so the calls to them in `impl_any_atomic` would resolve
+// to the trait methods themselves and recurse.
+#[cfg(not(no_atomic_bool))]
+impl sealed::Sealed for atomic::AtomicBool {}
+
+#[cfg(not(no_atomic_bool))]
+impl_any_atomic!([] atomic::AtomicBool, bool, true);
+
+#[cfg(target_has_atomic = "ptr")]
+use atomic::AtomicPtr;
+
+#[cfg(target_has_atomic = "ptr")]
+impl_atomic_ptr_ext!(AtomicPtr, true);
+
+macro_rules! impl_native_atomic_int_ext {
+    ($atomic:ident, $int:ident, $($cfg:tt)*) => {
+        #[cfg($($cfg)*)]
+        impl_atomic_int_ext!(atomic::$atomic, $int, true);
+    };
+}
+
+with_primitive_atomics!(impl_native_atomic_int_ext);
+
+#[cfg(feature = "portable-atomic")]
+mod portable {
+    use super::*;
+
+    macro_rules! impl_portable_atomic_int_ext {
+        ($atomic:ident, $int:ident, $($cfg:tt)*) => {
+            impl_atomic_int_ext!(
+                portable_atomic::$atomic,
+                $int,
+                portable_atomic::$atomic::is_always_lock_free(),
+                portable_atomic::$atomic::is_lock_free(),
+            );
+        };
+    }
+
+    with_primitive_atomics!(impl_portable_atomic_int_ext);
+
+    use portable_atomic::AtomicPtr;
+    impl_atomic_ptr_ext!(AtomicPtr, AtomicPtr::<()>::is_always_lock_free());
+}
+
+/// Creates an array of atomics in a constant expression.
+///
+/// `atomic_array!(A; [a, b, c])` expands to `[A::new(a), A::new(b),
+/// A::new(c)]`. Unlike [`AtomicIntExt::from_array`], this can be used to
+/// initialize a `static`, as every atomic type’s inherent `new` method is
+/// `const`:
+///
+/// ```
+/// use atomic_int::{AtomicI128, atomic_array};
+/// use std::sync::atomic::Ordering;
+///
+/// static TABLE: [AtomicI128; 3] = atomic_array!(AtomicI128; [1, -2, 3]);
+/// TABLE[1].store(5, Ordering::Relaxed);
+/// assert_eq!(TABLE[0].load(Ordering::Relaxed), 1);
+/// assert_eq!(TABLE[1].load(Ordering::Relaxed), 5);
+/// assert_eq!(TABLE[2].load(Ordering::Relaxed), 3);
+/// ```
+#[macro_export]
+macro_rules! atomic_array {
+    ($atomic:ty; [$($val:expr),* $(,)?]) => {
+        [$(<$atomic>::new($val)),*]
+    };
+}
@@ -0,0 +1,258 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Adaptive waiting for the fallback spinlocks.
+//!
+//! A thread that finds a lock held first spins, as most critical sections
+//! are only a few instructions long. If the lock is still held after
+//! [`SPIN_LIMIT`] iterations, the thread instead sleeps on a futex until the
+//! lock is released. Sleeping threads are tracked in a small global table of
+//! [`Slot`]s indexed by the lock’s address, so the fallback types don’t grow.
+//!
+//! Once a wait on a slot has escalated to sleeping, the next
+//! [`ESCALATED_WAITS`] contended waits on locks that map to that slot sleep
+//! immediately rather than spinning first, on the assumption that the lock is
+//! held for long periods (e.g., because the holder is frequently preempted).
+//! After that, waits spin first again, so a slot that escalated during a
+//! burst of contention doesn't keep paying for a futex call on every wait
+//! once the burst has passed. If the lock is still held for long periods, the
+//! next wait escalates again.
+//!
+//! Futexes are used on Linux and Android; on other platforms, escalated
+//! waits yield the thread to the OS scheduler instead.
+
+use super::sync;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// The number of iterations a thread spins before sleeping.
+const SPIN_LIMIT: usize = 1 << 10;
+
+/// The number of contended waits on a slot that sleep immediately, without
+/// spinning first, after a wait on that slot has escalated to sleeping.
+const ESCALATED_WAITS: u32 = 64;
+
+/// The number of slots in [`SLOTS`].
+const NUM_SLOTS: usize = 64;
+
+struct Slot {
+    /// Incremented every time a lock that maps to this slot is released
+    /// while threads are sleeping. Threads sleep on this futex word.
+    epoch: AtomicU32,
+    /// The number of threads sleeping (or about to sleep) on this slot.
+    sleepers: AtomicU32,
+    /// The number of remaining waits on this slot that sleep immediately,
+    /// set to [`ESCALATED_WAITS`] when a wait escalates to sleeping.
+    escalated: AtomicU32,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: Slot = Slot {
+    epoch: AtomicU32::new(0),
+    sleepers: AtomicU32::new(0),
+    escalated: AtomicU32::new(0),
+};
+
+static SLOTS: [Slot; NUM_SLOTS] = [EMPTY_SLOT; NUM_SLOTS];
+
+static CONTENDED: AtomicUsize = AtomicUsize::new(0);
+static SLEPT: AtomicUsize = AtomicUsize::new(0);
+
+fn slot(lock: &sync::AtomicBool) -> &'static Slot {
+    let addr = lock as *const sync::AtomicBool as usize;
+    &SLOTS[(addr ^ (addr >> 6) ^ (addr >> 12)) % NUM_SLOTS]
+}
+
+/// Statistics about contention on the fallback spinlocks.
+///
+/// These are returned by [`contention_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ContentionStats {
+    /// The number of lock acquisitions that found the lock already held.
+    pub contended: usize,
+    /// The number of times a waiting thread stopped spinning and slept.
+    pub slept: usize,
+}
+
+/// Returns statistics about contention on the fallback spinlocks since the
+/// start of the program.
+///
+/// The counters are updated only on contended paths, so maintaining them
+/// does not slow down uncontended operations.
+///
+/// ```
+/// use atomic_int::{AtomicU128, contention_stats};
+/// use std::sync::atomic::Ordering;
+/// use std::thread;
+///
+/// // `AtomicU128` is a fallback type on most targets. Threads that wait too
+/// // long for its lock sleep, but every increment still takes effect.
+/// let before = contention_stats();
+/// let a = AtomicU128::new(0);
+/// thread::scope(|s| {
+///     for _ in 0..8 {
+///         s.spawn(|| {
+///             for _ in 0..10_000 {
+///                 a.fetch_add(1, Ordering::AcqRel);
+///             }
+///         });
+///     }
+/// });
+/// assert_eq!(a.into_inner(), 80_000);
+/// let after = contention_stats();
+/// assert!(after.contended >= before.contended);
+/// assert!(after.slept >= before.slept);
+/// ```
+pub fn contention_stats() -> ContentionStats {
+    ContentionStats {
+        contended: CONTENDED.load(Ordering::Relaxed),
+        slept: SLEPT.load(Ordering::Relaxed),
+    }
+}
+
+/// Counts a contended lock acquisition in [`contention_stats`].
+pub fn record_contended() {
+    CONTENDED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Waits until `lock` appears to be unlocked.
+pub fn wait_while_locked(lock: &sync::AtomicBool) {
+    record_contended();
+    wait_on_slot(lock, slot(lock));
+}
+
+fn wait_on_slot(lock: &sync::AtomicBool, slot: &Slot) {
+    let escalated = slot
+        .escalated
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+            n.checked_sub(1)
+        })
+        .is_ok();
+    if !escalated {
+        for _ in 0..SPIN_LIMIT {
+            if !lock.load(Ordering::Relaxed) {
+                return;
+            }
+            core::hint::spin_loop();
+        }
+        slot.escalated.store(ESCALATED_WAITS, Ordering::Relaxed);
+    }
+    while lock.load(Ordering::Relaxed) {
+        SLEPT.fetch_add(1, Ordering::Relaxed);
+        slot.sleepers.fetch_add(1, Ordering::SeqCst);
+        let epoch = slot.epoch.load(Ordering::SeqCst);
+        // Pairs with the `SeqCst` release of the lock and the load in
+        // `wake`: either the releasing thread sees our increment of
+        // `sleepers`, or we see the lock released here.
+        if lock.load(Ordering::SeqCst) {
+            sleep(&slot.epoch, epoch);
+        }
+        slot.sleepers.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Wakes threads sleeping on `lock`, which must have just been released
+/// with a `SeqCst` store.
+///
+/// Only threads whose wait has escalated to sleeping increment `sleepers`,
+/// so after an uncontended operation, this is a single load of that counter.
+/// The `SeqCst` store orders the release before the load without a fence,
+/// which is a full barrier on every architecture, whereas a `SeqCst` store
+/// is a single `xchg` on x86-64 and needs no barrier at all on AArch64.
+pub fn wake(lock: &sync::AtomicBool) {
+    wake_slot(slot(lock));
+}
+
+fn wake_slot(slot: &Slot) {
+    if slot.sleepers.load(Ordering::SeqCst) != 0 {
+        slot.epoch.fetch_add(1, Ordering::SeqCst);
+        wake_all(&slot.epoch);
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn sleep(word: &AtomicU32, expected: u32) {
+    // SAFETY: `word` is a valid, aligned 32-bit integer. `FUTEX_WAIT`
+    // returns immediately if `word` no longer contains `expected`.
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word as *const AtomicU32,
+            libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+            expected,
+            core::ptr::null::<libc::timespec>(),
+        );
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn wake_all(word: &AtomicU32) {
+    // SAFETY: `word` is a valid, aligned 32-bit integer.
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word as *const AtomicU32,
+            libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+            libc::c_int::MAX,
+        );
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn sleep(word: &AtomicU32, expected: u32) {
+    if word.load(Ordering::Relaxed) == expected {
+        std::thread::yield_now();
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn wake_all(_word: &AtomicU32) {}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn escalation_decays() {
+        let lock = sync::AtomicBool::new(true);
+        let slot = EMPTY_SLOT;
+        thread::scope(|s| {
+            s.spawn(|| {
+                // Release the lock only once the waiter has escalated.
+                while slot.sleepers.load(Ordering::SeqCst) == 0 {
+                    thread::yield_now();
+                }
+                lock.store(false, Ordering::SeqCst);
+                wake_slot(&slot);
+            });
+            wait_on_slot(&lock, &slot);
+        });
+        assert_eq!(slot.escalated.load(Ordering::Relaxed), ESCALATED_WAITS);
+        for remaining in (0..ESCALATED_WAITS).rev() {
+            wait_on_slot(&lock, &slot);
+            assert_eq!(slot.escalated.load(Ordering::Relaxed), remaining);
+        }
+        // Waits spin first again, and don't escalate if the lock is
+        // released in time.
+        wait_on_slot(&lock, &slot);
+        assert_eq!(slot.escalated.load(Ordering::Relaxed), 0);
+    }
+}
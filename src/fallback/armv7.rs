@@ -0,0 +1,368 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Lock-free 64-bit atomics for ARMv6K and ARMv7-A/R targets that lack
+//! built-in 64-bit atomics, implemented with the exclusive doubleword
+//! instructions `ldrexd` and `strexd`.
+//!
+//! Every operation is bracketed by full `dmb ish` barriers, so all operations
+//! behave as [`SeqCst`](Ordering::SeqCst) regardless of the ordering
+//! requested. The doubleword exclusives require an even/odd register pair in
+//! ARM mode, so the loaded and stored values use the fixed pairs `r2`/`r3` and
+//! `r4`/`r5`.
+
+use core::arch::asm;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{Ordering, fence};
+
+fn split(v: u64) -> (u32, u32) {
+    (v as u32, (v >> 32) as u32)
+}
+
+fn join(lo: u32, hi: u32) -> u64 {
+    (u64::from(hi) << 32) | u64::from(lo)
+}
+
+/// Atomically loads the value at `ptr`.
+///
+/// # Safety
+///
+/// `ptr` must be valid and 8-byte aligned.
+unsafe fn load(ptr: *mut u64) -> u64 {
+    let (lo, hi): (u32, u32);
+    // SAFETY: Checked by caller. `ldrexd` is single-copy atomic for
+    // doublewords, and `clrex` releases the exclusive monitor.
+    unsafe {
+        asm!(
+            "ldrexd r2, r3, [{ptr}]",
+            "clrex",
+            ptr = in(reg) ptr,
+            out("r2") lo,
+            out("r3") hi,
+            options(nostack, preserves_flags),
+        );
+    }
+    join(lo, hi)
+}
+
+/// Atomically replaces the value at `ptr` with `new` if it is equal to
+/// `old`. Returns the previous value.
+///
+/// # Safety
+///
+/// `ptr` must be valid and 8-byte aligned.
+unsafe fn cas(ptr: *mut u64, old: u64, new: u64) -> u64 {
+    let (old_lo, old_hi) = split(old);
+    let (new_lo, new_hi) = split(new);
+    let (lo, hi): (u32, u32);
+    // SAFETY: Checked by caller. The store is performed only if the
+    // exclusive monitor is still held, so no other write can intervene
+    // between the load and the store.
+    unsafe {
+        asm!(
+            "2:",
+            "ldrexd r2, r3, [{ptr}]",
+            "eor {tmp}, r2, {old_lo}",
+            "eor {tmp2}, r3, {old_hi}",
+            "orrs {tmp}, {tmp}, {tmp2}",
+            "bne 3f",
+            "strexd {tmp}, r4, r5, [{ptr}]",
+            "cmp {tmp}, #0",
+            "bne 2b",
+            "b 4f",
+            "3:",
+            "clrex",
+            "4:",
+            ptr = in(reg) ptr,
+            old_lo = in(reg) old_lo,
+            old_hi = in(reg) old_hi,
+            tmp = out(reg) _,
+            tmp2 = out(reg) _,
+            in("r4") new_lo,
+            in("r5") new_hi,
+            out("r2") lo,
+            out("r3") hi,
+            options(nostack),
+        );
+    }
+    join(lo, hi)
+}
+
+macro_rules! define_asm_atomic {
+    ($atomic:ident, $int:ident, $doc:expr) => {
+        #[repr(C, align(8))]
+        pub struct $atomic {
+            value: UnsafeCell<$int>,
+        }
+
+        impl $atomic {
+            /// Creates a new atomic.
+            #[doc = concat!("\n\n", $doc, "::new`].")]
+            pub const fn new(v: $int) -> Self {
+                Self {
+                    value: UnsafeCell::new(v),
+                }
+            }
+
+            /// Whether this type is always lock-free. This is always the
+            /// case, as it uses `ldrexd`/`strexd` rather than a lock.
+            pub const IS_ALWAYS_LOCK_FREE: bool = true;
+
+            /// Returns whether operations on this type are lock-free. This is
+            /// always the case, as it uses `ldrexd`/`strexd` rather than a
+            /// lock.
+            pub const fn is_lock_free() -> bool {
+                Self::IS_ALWAYS_LOCK_FREE
+            }
+
+            fn ptr(&self) -> *mut u64 {
+                self.value.get().cast()
+            }
+
+            /// Applies `f` to the value in a compare-and-swap loop until it
+            /// returns [`None`] or the new value is stored successfully.
+            fn update<F>(&self, mut f: F) -> Result<$int, $int>
+            where
+                F: FnMut($int) -> Option<$int>,
+            {
+                fence(Ordering::SeqCst);
+                // SAFETY: `self.ptr()` is valid and 8-byte aligned.
+                let mut current = unsafe { load(self.ptr()) };
+                let result = loop {
+                    let new = match f(current as $int) {
+                        Some(new) => new as u64,
+                        None => break Err(current as $int),
+                    };
+                    // SAFETY: `self.ptr()` is valid and 8-byte aligned.
+                    let prev = unsafe { cas(self.ptr(), current, new) };
+                    if prev == current {
+                        break Ok(current as $int);
+                    }
+                    current = prev;
+                };
+                fence(Ordering::SeqCst);
+                result
+            }
+
+            /// Returns a mutable reference to the underlying value.
+            #[doc = concat!("\n\n", $doc, "::get_mut`].")]
+            pub fn get_mut(&mut self) -> &mut $int {
+                self.value.get_mut()
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            #[doc = concat!("\n\n", $doc, "::into_inner`].")]
+            pub fn into_inner(self) -> $int {
+                self.value.into_inner()
+            }
+
+            /// Loads a value from the atomic.
+            #[doc = concat!("\n\n", $doc, "::load`].")]
+            pub fn load(&self, order: Ordering) -> $int {
+                let _ = order;
+                fence(Ordering::SeqCst);
+                // SAFETY: `self.ptr()` is valid and 8-byte aligned.
+                let value = unsafe { load(self.ptr()) };
+                fence(Ordering::SeqCst);
+                value as $int
+            }
+
+            /// Stores a value into the atomic.
+            #[doc = concat!("\n\n", $doc, "::store`].")]
+            pub fn store(&self, val: $int, order: Ordering) {
+                self.swap(val, order);
+            }
+
+            /// Stores a value into the atomic, returning the previous
+            /// value.
+            #[doc = concat!("\n\n", $doc, "::swap`].")]
+            pub fn swap(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                match self.update(|_| Some(val)) {
+                    Ok(prev) => prev,
+                    Err(prev) => prev,
+                }
+            }
+
+            /// Stores a value into the atomic if the current value is the same
+            /// as the `current` value.
+            #[doc = concat!("\n\n", $doc, "::compare_and_swap`].")]
+            pub fn compare_and_swap(
+                &self,
+                current: $int,
+                new: $int,
+                order: Ordering,
+            ) -> $int {
+                let failure = crate::ext::load_order(order);
+                match self.compare_exchange(current, new, order, failure) {
+                    Ok(prev) => prev,
+                    Err(prev) => prev,
+                }
+            }
+
+            /// Stores a value into the atomic if the current value is the same
+            /// as the `current` value.
+            #[doc = concat!("\n\n", $doc, "::compare_exchange`].")]
+            pub fn compare_exchange(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                let _ = (success, failure);
+                fence(Ordering::SeqCst);
+                // SAFETY: `self.ptr()` is valid and 8-byte aligned.
+                let prev =
+                    unsafe { cas(self.ptr(), current as u64, new as u64) };
+                fence(Ordering::SeqCst);
+                if prev == current as u64 {
+                    Ok(prev as $int)
+                } else {
+                    Err(prev as $int)
+                }
+            }
+
+            /// Stores a value into the atomic if the current value is the same
+            /// as the `current` value.
+            #[doc = concat!("\n\n", $doc, "::compare_exchange_weak`].")]
+            pub fn compare_exchange_weak(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.compare_exchange(current, new, success, failure)
+            }
+
+            /// Fetches the value, and applies a function to it that returns an
+            /// optional new value.
+            #[doc = concat!("\n\n", $doc, "::fetch_update`].")]
+            pub fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                f: F,
+            ) -> Result<$int, $int>
+            where
+                F: FnMut($int) -> Option<$int>,
+            {
+                let _ = (set_order, fetch_order);
+                self.update(f)
+            }
+
+            /// Adds to the current value, returning the previous value.
+            #[doc = concat!("\n\n", $doc, "::fetch_add`].")]
+            pub fn fetch_add(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v.wrapping_add(val))
+            }
+
+            /// Subtracts from the current value, returning the previous value.
+            #[doc = concat!("\n\n", $doc, "::fetch_sub`].")]
+            pub fn fetch_sub(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v.wrapping_sub(val))
+            }
+
+            /// Bitwise “and” with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_and`].")]
+            pub fn fetch_and(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v & val)
+            }
+
+            /// Bitwise “nand” with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_nand`].")]
+            pub fn fetch_nand(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| !(v & val))
+            }
+
+            /// Bitwise “or” with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_or`].")]
+            pub fn fetch_or(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v | val)
+            }
+
+            /// Bitwise “xor” with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_xor`].")]
+            pub fn fetch_xor(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v ^ val)
+            }
+
+            /// Maximum with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_max`].")]
+            pub fn fetch_max(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v.max(val))
+            }
+
+            /// Minimum with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_min`].")]
+            pub fn fetch_min(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v.min(val))
+            }
+
+            fn rmw(&self, mut f: impl FnMut($int) -> $int) -> $int {
+                match self.update(|v| Some(f(v))) {
+                    Ok(prev) => prev,
+                    Err(prev) => prev,
+                }
+            }
+
+            /// Returns a mutable pointer to the underlying value.
+            #[doc = concat!("\n\n", $doc, "::as_ptr`].")]
+            pub const fn as_ptr(&self) -> *mut $int {
+                self.value.get()
+            }
+
+            /// Creates a reference to an atomic from a pointer to an integer.
+            #[doc = concat!("\n\n", $doc, "::from_ptr`].")]
+            ///
+            /// # Safety
+            ///
+            /// See [`AtomicIntExt::from_ptr`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::from_ptr
+            pub unsafe fn from_ptr<'a>(ptr: *mut $int) -> &'a Self {
+                // SAFETY: Checked by caller. This type consists solely of an
+                // `UnsafeCell` of its integer.
+                unsafe { &*ptr.cast() }
+            }
+        }
+
+        // SAFETY: All accesses to the value are atomic.
+        unsafe impl Sync for $atomic {}
+
+        impl_atomic_int_ext!($atomic, $int, true);
+        impl_serde!([] $atomic, $int);
+        // SAFETY: The type contains only the integer.
+        impl_zeroable!([] $atomic);
+        define_int_traits!($atomic, $int);
+        define_bit_ops!($atomic, $int);
+        impl_atomic_traits!(int $atomic, $int);
+    };
+}
+
+define_asm_atomic!(AtomicI64, i64, "See [`atomic::AtomicI64");
+define_asm_atomic!(AtomicU64, u64, "See [`atomic::AtomicU64");
@@ -0,0 +1,235 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::sync::AtomicBool;
+use core::convert::TryFrom;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// How a thread waits for a fallback spinlock that is held by another
+/// thread.
+///
+/// The policy is set at runtime with [`set_backoff_policy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BackoffPolicy {
+    /// Spin until the lock is released.
+    SpinOnly,
+    /// Spin for up to `spins` iterations, then yield the thread to the OS
+    /// scheduler between checks of the lock.
+    SpinThenYield {
+        /// The number of iterations to spin before yielding.
+        spins: u32,
+    },
+    /// Spin briefly, then sleep until the lock is released, as described in
+    /// the documentation of the feature `adaptive`.
+    #[cfg(feature = "adaptive")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "adaptive")))]
+    Futex,
+    /// Spin with exponentially increasing pauses between checks of the lock,
+    /// then yield the thread to the OS scheduler between checks, as
+    /// described in the documentation of the feature `exponential-backoff`.
+    Exponential,
+}
+
+const SPIN_ONLY: usize = 0;
+const SPIN_THEN_YIELD: usize = 1;
+#[cfg(feature = "adaptive")]
+const FUTEX: usize = 2;
+const EXPONENTIAL: usize = 3;
+const TAG_BITS: u32 = 2;
+const TAG_MASK: usize = (1 << TAG_BITS) - 1;
+
+#[cfg(not(any(feature = "adaptive", feature = "exponential-backoff")))]
+const DEFAULT: usize = SPIN_ONLY;
+#[cfg(all(feature = "exponential-backoff", not(feature = "adaptive")))]
+const DEFAULT: usize = EXPONENTIAL;
+#[cfg(feature = "adaptive")]
+const DEFAULT: usize = FUTEX;
+
+/// The current policy, with its variant in the low [`TAG_BITS`] bits and the
+/// spin count (if any) in the remaining bits, so that the whole policy can be
+/// read with one atomic load.
+static POLICY: AtomicUsize = AtomicUsize::new(DEFAULT);
+
+fn encode(policy: BackoffPolicy) -> usize {
+    match policy {
+        BackoffPolicy::SpinOnly => SPIN_ONLY,
+        BackoffPolicy::SpinThenYield {
+            spins,
+        } => {
+            let max = usize::MAX >> TAG_BITS;
+            let spins = usize::try_from(spins).unwrap_or(max).min(max);
+            (spins << TAG_BITS) | SPIN_THEN_YIELD
+        }
+        #[cfg(feature = "adaptive")]
+        BackoffPolicy::Futex => FUTEX,
+        BackoffPolicy::Exponential => EXPONENTIAL,
+    }
+}
+
+fn decode(bits: usize) -> BackoffPolicy {
+    match bits & TAG_MASK {
+        SPIN_THEN_YIELD => BackoffPolicy::SpinThenYield {
+            spins: u32::try_from(bits >> TAG_BITS).unwrap_or(u32::MAX),
+        },
+        #[cfg(feature = "adaptive")]
+        FUTEX => BackoffPolicy::Futex,
+        EXPONENTIAL => BackoffPolicy::Exponential,
+        _ => BackoffPolicy::SpinOnly,
+    }
+}
+
+/// Sets the policy used by threads waiting for a fallback spinlock.
+///
+/// The default policy is [`BackoffPolicy::Futex`] when the feature
+/// `adaptive` is enabled, [`BackoffPolicy::Exponential`] when the feature
+/// `exponential-backoff` is enabled, and [`BackoffPolicy::SpinOnly`]
+/// otherwise.
+///
+/// The policy may be changed at any time from any thread. Each wait reads the
+/// policy once, when it starts, so threads that are already waiting continue
+/// with the previous policy until they acquire the lock. Changing the policy
+/// affects only how threads wait, never whether they eventually acquire the
+/// lock, so it is always safe.
+pub fn set_backoff_policy(policy: BackoffPolicy) {
+    POLICY.store(encode(policy), Ordering::Relaxed);
+}
+
+/// Returns the policy used by threads waiting for a fallback spinlock.
+///
+/// See [`set_backoff_policy`].
+pub fn backoff_policy() -> BackoffPolicy {
+    decode(POLICY.load(Ordering::Relaxed))
+}
+
+/// Waits until `lock` appears to be unlocked, according to the current
+/// policy.
+pub fn wait_while_locked(lock: &AtomicBool) {
+    let policy = backoff_policy();
+    #[cfg(feature = "adaptive")]
+    if policy != BackoffPolicy::Futex {
+        super::adaptive::record_contended();
+    }
+    match policy {
+        BackoffPolicy::SpinOnly => super::lock::spin_while_locked(lock),
+        BackoffPolicy::SpinThenYield {
+            spins,
+        } => {
+            for _ in 0..spins {
+                if !lock.load(Ordering::Relaxed) {
+                    return;
+                }
+                core::hint::spin_loop();
+            }
+            while lock.load(Ordering::Relaxed) {
+                std::thread::yield_now();
+            }
+        }
+        #[cfg(feature = "adaptive")]
+        BackoffPolicy::Futex => super::adaptive::wait_while_locked(lock),
+        BackoffPolicy::Exponential => super::lock::backoff_while_locked(lock),
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Held by tests that change the policy, so they don't observe each
+    /// other's changes.
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// Checks that each policy reads back as set. Large spin counts are
+    /// limited to what fits beside the tag in a `usize`.
+    #[test]
+    fn policy_round_trips() {
+        let _serial = SERIAL.lock().unwrap();
+        let max_spins =
+            u32::try_from(usize::MAX >> TAG_BITS).unwrap_or(u32::MAX);
+        let policies = [
+            BackoffPolicy::SpinOnly,
+            BackoffPolicy::SpinThenYield {
+                spins: 0,
+            },
+            BackoffPolicy::SpinThenYield {
+                spins: 1000,
+            },
+            BackoffPolicy::SpinThenYield {
+                spins: max_spins,
+            },
+            #[cfg(feature = "adaptive")]
+            BackoffPolicy::Futex,
+            BackoffPolicy::Exponential,
+        ];
+        for policy in policies {
+            assert_eq!(decode(encode(policy)), policy);
+            set_backoff_policy(policy);
+            assert_eq!(backoff_policy(), policy);
+        }
+        let spins = BackoffPolicy::SpinThenYield {
+            spins: u32::MAX,
+        };
+        assert_eq!(
+            decode(encode(spins)),
+            BackoffPolicy::SpinThenYield {
+                spins: max_spins,
+            }
+        );
+        POLICY.store(DEFAULT, Ordering::Relaxed);
+    }
+
+    /// Checks that each policy counts the acquisitions that wait for a held
+    /// lock in `contention_stats`.
+    #[cfg(all(feature = "adaptive", feature = "primitives", fallback_128))]
+    #[test]
+    fn policies_record_contention() {
+        use crate::{AtomicU128, contention_stats};
+        use std::thread;
+
+        let _serial = SERIAL.lock().unwrap();
+
+        let policies = [
+            BackoffPolicy::SpinOnly,
+            BackoffPolicy::SpinThenYield {
+                spins: 100,
+            },
+            BackoffPolicy::Futex,
+            BackoffPolicy::Exponential,
+        ];
+        let a = AtomicU128::new(0);
+        for policy in policies {
+            set_backoff_policy(policy);
+            let before = contention_stats().contended;
+            thread::scope(|s| {
+                a.with_lock(Ordering::AcqRel, |_| {
+                    s.spawn(|| a.fetch_add(1, Ordering::AcqRel));
+                    // Hold the lock until the other thread finds it held.
+                    while contention_stats().contended == before {
+                        thread::yield_now();
+                    }
+                });
+            });
+            assert!(contention_stats().contended > before);
+        }
+        assert_eq!(a.into_inner(), policies.len() as u128);
+        POLICY.store(DEFAULT, Ordering::Relaxed);
+    }
+}
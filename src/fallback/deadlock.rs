@@ -0,0 +1,173 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Detection of a thread that tries to acquire a lock it already holds, used
+//! with the feature `deadlock-detection`.
+//!
+//! Each thread records the addresses of the locks it holds in a small
+//! thread-local table. This also catches two atomics that share a lock
+//! stripe, which deadlock just the same when locked by one thread.
+
+use super::sync::AtomicBool;
+use core::cell::Cell;
+
+/// The number of locks that a thread can hold at once and still have them
+/// checked. Further locks are acquired without being recorded, which only
+/// makes detection less complete.
+const MAX_HELD: usize = 8;
+
+std::thread_local! {
+    static HELD: Cell<[usize; MAX_HELD]> = const { Cell::new([0; MAX_HELD]) };
+}
+
+/// Reports that the current thread tried to acquire a lock that it already
+/// holds, which would otherwise spin forever.
+///
+/// The second acquisition may come from a signal handler, where unwinding is
+/// not allowed, so with `libc` on Unix, this writes a message with `write(2)`
+/// (which is async-signal-safe) and aborts; otherwise, it panics.
+#[cold]
+#[inline(never)]
+fn deadlock() -> ! {
+    const MESSAGE: &str = "atomic-int: deadlock detected: a thread tried to \
+        acquire the lock of a fallback atomic that it already holds";
+
+    #[cfg(all(feature = "libc", not(windows)))]
+    {
+        for part in [MESSAGE, "\n"] {
+            // SAFETY: `part` is valid for reads of `part.len()` bytes.
+            unsafe {
+                libc::write(
+                    libc::STDERR_FILENO,
+                    part.as_ptr().cast(),
+                    part.len(),
+                );
+            }
+        }
+        std::process::abort();
+    }
+
+    #[cfg(not(all(feature = "libc", not(windows))))]
+    panic!("{}", MESSAGE);
+}
+
+/// Checks that the current thread does not hold `lock`, before it is
+/// acquired.
+pub fn check(lock: &AtomicBool) {
+    let addr = lock as *const AtomicBool as usize;
+    // The table is unavailable while the thread is being torn down, in which
+    // case nothing is checked.
+    let _ = HELD.try_with(|held| {
+        if held.get().contains(&addr) {
+            deadlock();
+        }
+    });
+}
+
+/// Records that the current thread has acquired `lock`.
+pub fn acquired(lock: &AtomicBool) {
+    let addr = lock as *const AtomicBool as usize;
+    let _ = HELD.try_with(|held| {
+        let mut locks = held.get();
+        if let Some(slot) = locks.iter_mut().find(|slot| **slot == 0) {
+            *slot = addr;
+            held.set(locks);
+        }
+    });
+}
+
+/// Records that the current thread is about to release `lock`.
+pub fn releasing(lock: &AtomicBool) {
+    let addr = lock as *const AtomicBool as usize;
+    let _ = HELD.try_with(|held| {
+        let mut locks = held.get();
+        if let Some(slot) = locks.iter_mut().find(|slot| **slot == addr) {
+            *slot = 0;
+            held.set(locks);
+        }
+    });
+}
+
+#[cfg(all(test, feature = "primitives", fallback_128))]
+mod tests {
+    use crate::AtomicU128;
+    use core::sync::atomic::Ordering;
+
+    #[cfg(not(all(feature = "libc", not(windows))))]
+    #[test]
+    #[should_panic(expected = "deadlock detected")]
+    fn relock_in_with_lock() {
+        let a = AtomicU128::new(0);
+        a.with_lock(Ordering::AcqRel, |_| a.fetch_add(1, Ordering::AcqRel));
+    }
+
+    #[cfg(not(all(feature = "libc", not(windows))))]
+    #[test]
+    #[should_panic(expected = "deadlock detected")]
+    fn relock_in_fetch_update() {
+        let a = AtomicU128::new(0);
+        let _ = a.fetch_update(Ordering::AcqRel, Ordering::Acquire, |v| {
+            a.fetch_add(1, Ordering::AcqRel);
+            Some(v)
+        });
+    }
+
+    /// Checks that holding more locks than the table can record neither
+    /// reports a deadlock nor leaves stale entries in the table.
+    #[test]
+    fn nesting_beyond_max_held() {
+        use super::{HELD, MAX_HELD};
+        use core::cell::Cell;
+        use std::vec::Vec;
+
+        fn nest(atomics: &[&AtomicU128]) {
+            if let Some((first, rest)) = atomics.split_first() {
+                first.with_lock(Ordering::AcqRel, |scope| {
+                    scope.update(|v| v + 1);
+                    nest(rest);
+                });
+            }
+        }
+
+        // With `striped`, atomics in the same stripe share a lock, so
+        // nesting them would be a real deadlock. Only atomics in distinct
+        // stripes are used.
+        #[cfg(feature = "striped")]
+        let stripe = |a: &AtomicU128| {
+            super::super::stripes::get(a as *const _ as usize) as *const _
+        };
+        let pool: Vec<AtomicU128> =
+            (0..256).map(|_| AtomicU128::new(0)).collect();
+        let mut atomics: Vec<&AtomicU128> = Vec::new();
+        for a in &pool {
+            #[cfg(feature = "striped")]
+            if atomics.iter().any(|b| stripe(a) == stripe(b)) {
+                continue;
+            }
+            atomics.push(a);
+        }
+        atomics.truncate(MAX_HELD + 4);
+
+        nest(&atomics);
+        assert!(atomics.iter().all(|a| a.load(Ordering::Relaxed) == 1));
+        assert_eq!(HELD.with(Cell::get), [0; MAX_HELD]);
+        // Every lock was released, so nesting again is fine too.
+        nest(&atomics);
+        assert_eq!(HELD.with(Cell::get), [0; MAX_HELD]);
+    }
+}
@@ -0,0 +1,482 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Lock-free 128-bit atomics for x86-64 and AArch64, implemented with the
+//! double-width compare-and-swap instructions `cmpxchg16b` and `casp`, or
+//! with the exclusive pair instructions `ldxp` and `stxp` on AArch64 targets
+//! without LSE.
+//!
+//! Every operation is sequentially consistent regardless of the ordering
+//! requested: `lock cmpxchg16b` is a full barrier, and the AArch64 sequences
+//! use the acquire-release forms of the instructions. Loads are performed as
+//! a compare-and-swap that replaces the value with itself, so the atomic must
+//! be in writable memory, which is always the case for an `UnsafeCell`.
+//!
+//! On x86-64 targets that don't enable the `cmpxchg16b` target feature, its
+//! availability is checked with `cpuid` on first use. On the rare processors
+//! that lack it, every operation instead locks the global stripe table (see
+//! [`with_stripe`]), like [`Atomic`](crate::Atomic) does for large types.
+
+use super::{cas_order, with_stripe};
+use core::cell::UnsafeCell;
+use core::sync::atomic::Ordering;
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use core::arch::asm;
+    #[cfg(not(target_feature = "cmpxchg16b"))]
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    pub const ALWAYS_SUPPORTED: bool = cfg!(target_feature = "cmpxchg16b");
+
+    /// Returns whether the processor supports `cmpxchg16b`.
+    #[cfg(target_feature = "cmpxchg16b")]
+    pub fn supported() -> bool {
+        true
+    }
+
+    /// Returns whether the processor supports `cmpxchg16b`.
+    #[cfg(not(target_feature = "cmpxchg16b"))]
+    pub fn supported() -> bool {
+        const UNKNOWN: u8 = 0;
+        const NO: u8 = 1;
+        const YES: u8 = 2;
+        static STATE: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+        match STATE.load(Ordering::Relaxed) {
+            NO => false,
+            YES => true,
+            _ => {
+                // `__cpuid` is safe in newer versions of Rust.
+                #[allow(unused_unsafe)]
+                // SAFETY: `cpuid` is available on every x86-64 processor.
+                let info = unsafe { core::arch::x86_64::__cpuid(1) };
+                let has = info.ecx & (1 << 13) != 0;
+                STATE.store(
+                    if has {
+                        YES
+                    } else {
+                        NO
+                    },
+                    Ordering::Relaxed,
+                );
+                has
+            }
+        }
+    }
+
+    /// Atomically replaces the value at `ptr` with `new` if it is equal to
+    /// `old`. Returns the previous value.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid and 16-byte aligned, and [`supported`] must have
+    /// returned true.
+    pub unsafe fn cas(ptr: *mut u128, old: u128, new: u128) -> u128 {
+        let (lo, hi): (u64, u64);
+        // SAFETY: Checked by caller. `rbx` can't be used as an operand, so
+        // the low half of `new` is swapped into it and then restored.
+        unsafe {
+            asm!(
+                "xchg {rbx_tmp}, rbx",
+                "lock cmpxchg16b xmmword ptr [{ptr}]",
+                "mov rbx, {rbx_tmp}",
+                ptr = in(reg) ptr,
+                rbx_tmp = inout(reg) new as u64 => _,
+                in("rcx") (new >> 64) as u64,
+                inout("rax") old as u64 => lo,
+                inout("rdx") (old >> 64) as u64 => hi,
+                options(nostack),
+            );
+        }
+        (u128::from(hi) << 64) | u128::from(lo)
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    use core::arch::asm;
+
+    pub const ALWAYS_SUPPORTED: bool = true;
+
+    /// Returns whether the processor supports the required instructions,
+    /// which every AArch64 processor does.
+    pub fn supported() -> bool {
+        true
+    }
+
+    /// Atomically replaces the value at `ptr` with `new` if it is equal to
+    /// `old`. Returns the previous value.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid and 16-byte aligned.
+    #[cfg(target_feature = "lse")]
+    pub unsafe fn cas(ptr: *mut u128, old: u128, new: u128) -> u128 {
+        let (lo, hi): (u64, u64);
+        // SAFETY: Checked by caller. `caspal` requires each pair to be an
+        // even-numbered register and its successor.
+        unsafe {
+            asm!(
+                "caspal x4, x5, x6, x7, [{ptr}]",
+                ptr = in(reg) ptr,
+                inout("x4") old as u64 => lo,
+                inout("x5") (old >> 64) as u64 => hi,
+                in("x6") new as u64,
+                in("x7") (new >> 64) as u64,
+                options(nostack, preserves_flags),
+            );
+        }
+        (u128::from(hi) << 64) | u128::from(lo)
+    }
+
+    /// Atomically replaces the value at `ptr` with `new` if it is equal to
+    /// `old`. Returns the previous value.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid and 16-byte aligned.
+    #[cfg(not(target_feature = "lse"))]
+    pub unsafe fn cas(ptr: *mut u128, old: u128, new: u128) -> u128 {
+        let (lo, hi): (u64, u64);
+        // SAFETY: Checked by caller. `ldaxp` is only guaranteed to be
+        // single-copy atomic if the following `stlxp` succeeds, so when the
+        // comparison fails, the loaded value is stored back.
+        unsafe {
+            asm!(
+                "2:",
+                "ldaxp {lo}, {hi}, [{ptr}]",
+                "cmp {lo}, {old_lo}",
+                "ccmp {hi}, {old_hi}, #0, eq",
+                "b.ne 3f",
+                "stlxp {tmp:w}, {new_lo}, {new_hi}, [{ptr}]",
+                "cbnz {tmp:w}, 2b",
+                "b 4f",
+                "3:",
+                "stlxp {tmp:w}, {lo}, {hi}, [{ptr}]",
+                "cbnz {tmp:w}, 2b",
+                "4:",
+                ptr = in(reg) ptr,
+                old_lo = in(reg) old as u64,
+                old_hi = in(reg) (old >> 64) as u64,
+                new_lo = in(reg) new as u64,
+                new_hi = in(reg) (new >> 64) as u64,
+                lo = out(reg) lo,
+                hi = out(reg) hi,
+                tmp = out(reg) _,
+                options(nostack),
+            );
+        }
+        (u128::from(hi) << 64) | u128::from(lo)
+    }
+}
+
+/// Atomically loads the value at `ptr`.
+///
+/// # Safety
+///
+/// `ptr` must be valid and 16-byte aligned.
+unsafe fn load(ptr: *mut u128) -> u128 {
+    // SAFETY: Checked by caller. Replacing 0 with 0 never changes the value.
+    unsafe { cas(ptr, 0, 0) }
+}
+
+/// Atomically replaces the value at `ptr` with `new` if it is equal to
+/// `old`. Returns the previous value.
+///
+/// # Safety
+///
+/// `ptr` must be valid and 16-byte aligned.
+unsafe fn cas(ptr: *mut u128, old: u128, new: u128) -> u128 {
+    if arch::supported() {
+        // SAFETY: Checked by caller and by `arch::supported`.
+        return unsafe { arch::cas(ptr, old, new) };
+    }
+    with_stripe(ptr as usize, Ordering::SeqCst, || {
+        // SAFETY: Checked by caller. Since `arch::supported` returned false,
+        // every access to the value goes through the stripe table.
+        let prev = unsafe { ptr.read() };
+        if prev == old {
+            // SAFETY: See above.
+            unsafe { ptr.write(new) };
+        }
+        prev
+    })
+}
+macro_rules! define_dwcas_atomic {
+    ($atomic:ident, $int:ident, $doc:expr) => {
+        #[repr(C, align(16))]
+        pub struct $atomic {
+            value: UnsafeCell<$int>,
+        }
+
+        impl $atomic {
+            /// Creates a new atomic.
+            #[doc = concat!("\n\n", $doc, "::new`].")]
+            pub const fn new(v: $int) -> Self {
+                Self {
+                    value: UnsafeCell::new(v),
+                }
+            }
+
+            /// Whether this type is always lock-free. This is the case unless
+            /// support for `cmpxchg16b` has to be detected at run time.
+            pub const IS_ALWAYS_LOCK_FREE: bool = arch::ALWAYS_SUPPORTED;
+
+            /// Returns whether operations on this type are lock-free, i.e.,
+            /// whether the processor supports the required instructions.
+            pub fn is_lock_free() -> bool {
+                arch::supported()
+            }
+
+            fn ptr(&self) -> *mut u128 {
+                self.value.get().cast()
+            }
+
+            /// Applies `f` to the value in a compare-and-swap loop until it
+            /// returns [`None`] or the new value is stored successfully.
+            fn update<F>(&self, mut f: F) -> Result<$int, $int>
+            where
+                F: FnMut($int) -> Option<$int>,
+            {
+                // SAFETY: `self.ptr()` is valid and 16-byte aligned.
+                let mut current = unsafe { load(self.ptr()) };
+                let result = loop {
+                    let new = match f(current as $int) {
+                        Some(new) => new as u128,
+                        None => break Err(current as $int),
+                    };
+                    // SAFETY: `self.ptr()` is valid and 16-byte aligned.
+                    let prev = unsafe { cas(self.ptr(), current, new) };
+                    if prev == current {
+                        break Ok(current as $int);
+                    }
+                    current = prev;
+                };
+                result
+            }
+
+            /// Returns a mutable reference to the underlying value.
+            #[doc = concat!("\n\n", $doc, "::get_mut`].")]
+            pub fn get_mut(&mut self) -> &mut $int {
+                self.value.get_mut()
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            #[doc = concat!("\n\n", $doc, "::into_inner`].")]
+            pub fn into_inner(self) -> $int {
+                self.value.into_inner()
+            }
+
+            /// Loads a value from the atomic.
+            #[doc = concat!("\n\n", $doc, "::load`].")]
+            pub fn load(&self, order: Ordering) -> $int {
+                let _ = order;
+                // SAFETY: `self.ptr()` is valid and 16-byte aligned.
+                let value = unsafe { load(self.ptr()) };
+                value as $int
+            }
+
+            /// Stores a value into the atomic.
+            #[doc = concat!("\n\n", $doc, "::store`].")]
+            pub fn store(&self, val: $int, order: Ordering) {
+                self.swap(val, order);
+            }
+
+            /// Stores a value into the atomic, returning the previous
+            /// value.
+            #[doc = concat!("\n\n", $doc, "::swap`].")]
+            pub fn swap(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                match self.update(|_| Some(val)) {
+                    Ok(prev) => prev,
+                    Err(prev) => prev,
+                }
+            }
+
+            /// Stores a value into the atomic if the current value is the same
+            /// as the `current` value.
+            #[doc = concat!("\n\n", $doc, "::compare_and_swap`].")]
+            pub fn compare_and_swap(
+                &self,
+                current: $int,
+                new: $int,
+                order: Ordering,
+            ) -> $int {
+                let failure = crate::ext::load_order(order);
+                match self.compare_exchange(current, new, order, failure) {
+                    Ok(prev) => prev,
+                    Err(prev) => prev,
+                }
+            }
+
+            /// Stores a value into the atomic if the current value is the same
+            /// as the `current` value.
+            #[doc = concat!("\n\n", $doc, "::compare_exchange`].")]
+            pub fn compare_exchange(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                // Every operation is `SeqCst`, but invalid orderings still
+                // panic like they do with the built-in atomics.
+                cas_order(success, failure);
+                // SAFETY: `self.ptr()` is valid and 16-byte aligned.
+                let prev =
+                    unsafe { cas(self.ptr(), current as u128, new as u128) };
+                if prev == current as u128 {
+                    Ok(prev as $int)
+                } else {
+                    Err(prev as $int)
+                }
+            }
+
+            /// Stores a value into the atomic if the current value is the same
+            /// as the `current` value.
+            #[doc = concat!("\n\n", $doc, "::compare_exchange_weak`].")]
+            pub fn compare_exchange_weak(
+                &self,
+                current: $int,
+                new: $int,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$int, $int> {
+                self.compare_exchange(current, new, success, failure)
+            }
+
+            /// Fetches the value, and applies a function to it that returns an
+            /// optional new value.
+            #[doc = concat!("\n\n", $doc, "::fetch_update`].")]
+            pub fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                f: F,
+            ) -> Result<$int, $int>
+            where
+                F: FnMut($int) -> Option<$int>,
+            {
+                cas_order(set_order, fetch_order);
+                self.update(f)
+            }
+
+            /// Adds to the current value, returning the previous value.
+            #[doc = concat!("\n\n", $doc, "::fetch_add`].")]
+            pub fn fetch_add(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v.wrapping_add(val))
+            }
+
+            /// Subtracts from the current value, returning the previous value.
+            #[doc = concat!("\n\n", $doc, "::fetch_sub`].")]
+            pub fn fetch_sub(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v.wrapping_sub(val))
+            }
+
+            /// Bitwise “and” with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_and`].")]
+            pub fn fetch_and(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v & val)
+            }
+
+            /// Bitwise “nand” with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_nand`].")]
+            pub fn fetch_nand(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| !(v & val))
+            }
+
+            /// Bitwise “or” with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_or`].")]
+            pub fn fetch_or(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v | val)
+            }
+
+            /// Bitwise “xor” with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_xor`].")]
+            pub fn fetch_xor(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v ^ val)
+            }
+
+            /// Maximum with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_max`].")]
+            pub fn fetch_max(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v.max(val))
+            }
+
+            /// Minimum with the current value.
+            #[doc = concat!("\n\n", $doc, "::fetch_min`].")]
+            pub fn fetch_min(&self, val: $int, order: Ordering) -> $int {
+                let _ = order;
+                self.rmw(|v| v.min(val))
+            }
+
+            fn rmw(&self, mut f: impl FnMut($int) -> $int) -> $int {
+                match self.update(|v| Some(f(v))) {
+                    Ok(prev) => prev,
+                    Err(prev) => prev,
+                }
+            }
+
+            /// Returns a mutable pointer to the underlying value.
+            #[doc = concat!("\n\n", $doc, "::as_ptr`].")]
+            pub const fn as_ptr(&self) -> *mut $int {
+                self.value.get()
+            }
+
+            /// Creates a reference to an atomic from a pointer to an integer.
+            #[doc = concat!("\n\n", $doc, "::from_ptr`].")]
+            ///
+            /// # Safety
+            ///
+            /// See [`AtomicIntExt::from_ptr`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::from_ptr
+            pub unsafe fn from_ptr<'a>(ptr: *mut $int) -> &'a Self {
+                // SAFETY: Checked by caller. This type consists solely of an
+                // `UnsafeCell` of its integer.
+                unsafe { &*ptr.cast() }
+            }
+        }
+
+        // SAFETY: All accesses to the value are atomic.
+        unsafe impl Sync for $atomic {}
+
+        impl_atomic_int_ext!(
+            $atomic,
+            $int,
+            arch::ALWAYS_SUPPORTED,
+            arch::supported()
+        );
+        impl_serde!([] $atomic, $int);
+        // SAFETY: The type contains only the integer.
+        impl_zeroable!([] $atomic);
+        define_int_traits!($atomic, $int);
+        define_bit_ops!($atomic, $int);
+        impl_atomic_traits!(int $atomic, $int);
+    };
+}
+
+define_dwcas_atomic!(AtomicI128, i128, "See [`atomic::AtomicI128");
+define_dwcas_atomic!(AtomicU128, u128, "See [`atomic::AtomicU128");
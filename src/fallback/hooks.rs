@@ -0,0 +1,65 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering, fence};
+
+/// Callbacks invoked whenever a fallback atomic acquires its lock.
+///
+/// Each callback receives the address of the atomic. The callbacks can be
+/// used, e.g., to emit events or spans for tracing frameworks; they are
+/// installed with [`set_lock_hooks`].
+///
+/// [`after_acquire`](Self::after_acquire) is called while the lock is held,
+/// so it must not perform any operation on the same atomic, which would
+/// deadlock. Callbacks should avoid fallback atomics entirely, as operations
+/// on them would invoke the hooks recursively.
+#[derive(Clone, Copy, Debug)]
+pub struct LockHooks {
+    /// Called when a thread starts to acquire the lock.
+    pub before_acquire: fn(*const ()),
+    /// Called once the thread has acquired the lock.
+    pub after_acquire: fn(*const ()),
+}
+
+static HOOKS: AtomicPtr<LockHooks> = AtomicPtr::new(ptr::null_mut());
+
+/// Installs hooks that are called whenever a fallback atomic acquires its
+/// lock, replacing any existing hooks. If `hooks` is [`None`], existing hooks
+/// are removed.
+///
+/// Operations that are already in progress may still call the previous hooks
+/// after this function returns.
+pub fn set_lock_hooks(hooks: Option<&'static LockHooks>) {
+    let ptr = hooks.map_or(ptr::null_mut(), |h| h as *const _ as *mut _);
+    HOOKS.store(ptr, Ordering::Release);
+}
+
+/// Returns the installed hooks, if any. When no hooks are installed, this
+/// costs only a single relaxed load.
+pub fn get() -> Option<&'static LockHooks> {
+    let ptr = HOOKS.load(Ordering::Relaxed);
+    if ptr.is_null() {
+        return None;
+    }
+    // Synchronizes with the release store in `set_lock_hooks`.
+    fence(Ordering::Acquire);
+    // SAFETY: `HOOKS` is non-null only when it was set from a
+    // `&'static LockHooks`.
+    Some(unsafe { &*ptr })
+}
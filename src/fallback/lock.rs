@@ -0,0 +1,166 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::sync::{AtomicBool, spin_loop};
+use super::{acquire_order, release_order};
+use core::sync::atomic::Ordering;
+
+/// Acquires `lock` for an operation performed with ordering `order`.
+///
+/// Most acquisitions are uncontended, so this is a single
+/// `compare_exchange_weak`, with waiting moved out of line into
+/// [`acquire_slow`].
+///
+/// With the feature `deadlock-detection`, this first checks that the current
+/// thread doesn't already hold `lock`.
+#[inline]
+pub fn acquire(lock: &AtomicBool, order: Ordering) {
+    #[cfg(all(feature = "deadlock-detection", not(loom)))]
+    super::deadlock::check(lock);
+    if try_acquire(lock, order).is_err() {
+        acquire_slow(lock, order);
+    }
+    #[cfg(all(feature = "deadlock-detection", not(loom)))]
+    super::deadlock::acquired(lock);
+}
+
+fn try_acquire(lock: &AtomicBool, order: Ordering) -> Result<bool, bool> {
+    lock.compare_exchange_weak(
+        false,
+        true,
+        acquire_order(order),
+        Ordering::Relaxed,
+    )
+}
+
+#[cold]
+#[inline(never)]
+fn acquire_slow(lock: &AtomicBool, order: Ordering) {
+    loop {
+        wait_while_locked(lock);
+        if try_acquire(lock, order).is_ok() {
+            return;
+        }
+    }
+}
+
+/// Releases `lock` after an operation performed with ordering `order`.
+#[inline]
+pub fn release(lock: &AtomicBool, order: Ordering) {
+    #[cfg(all(feature = "deadlock-detection", not(loom)))]
+    super::deadlock::releasing(lock);
+    // With `adaptive`, the store is `SeqCst` so that `wake` can check for
+    // sleeping threads without a fence.
+    let order = if cfg!(feature = "adaptive") {
+        Ordering::SeqCst
+    } else {
+        release_order(order)
+    };
+    lock.store(false, order);
+    #[cfg(feature = "adaptive")]
+    super::adaptive::wake(lock);
+}
+
+/// Waits until `lock` appears to be unlocked, by spinning.
+///
+/// With loom, this is always the waiting strategy, as loom can only model
+/// threads that wait by spinning, which its [`spin_loop`] yields to other
+/// threads.
+#[cfg_attr(
+    all(
+        not(loom),
+        any(
+            all(feature = "adaptive", not(feature = "backoff")),
+            all(feature = "exponential-backoff", not(feature = "backoff")),
+        ),
+    ),
+    allow(dead_code)
+)]
+pub fn spin_while_locked(lock: &AtomicBool) {
+    while lock.load(Ordering::Relaxed) {
+        spin_loop();
+    }
+}
+
+/// The base-2 logarithm of the maximum number of [`spin_loop`] hints issued
+/// between two checks of the lock by [`backoff_while_locked`].
+///
+/// [`spin_loop`]: core::hint::spin_loop
+const MAX_PAUSE_SHIFT: u32 = 6;
+
+/// The number of checks of the lock after which [`backoff_while_locked`]
+/// starts yielding to the OS scheduler.
+#[cfg(feature = "std")]
+const YIELD_AFTER: u32 = 10;
+
+/// Waits until `lock` appears to be unlocked, with exponential backoff.
+///
+/// Between checks of the lock, this first issues 1, 2, 4, … [`spin_loop`]
+/// hints, up to 2<sup>[`MAX_PAUSE_SHIFT`]</sup>, which reduces traffic on
+/// the lock’s cache line while it is held. With `std`, after `YIELD_AFTER`
+/// checks, the holder has likely been preempted, so the thread yields to the
+/// OS scheduler between checks instead; without `std`, it keeps pausing.
+///
+/// [`spin_loop`]: core::hint::spin_loop
+#[cfg_attr(
+    any(
+        loom,
+        all(
+            not(feature = "backoff"),
+            any(feature = "adaptive", not(feature = "exponential-backoff")),
+        ),
+    ),
+    allow(dead_code)
+)]
+pub fn backoff_while_locked(lock: &AtomicBool) {
+    let mut step = 0;
+    while lock.load(Ordering::Relaxed) {
+        #[cfg(feature = "std")]
+        if step >= YIELD_AFTER {
+            std::thread::yield_now();
+            continue;
+        }
+        for _ in 0..1_u32 << step.min(MAX_PAUSE_SHIFT) {
+            spin_loop();
+        }
+        step = step.saturating_add(1);
+    }
+}
+
+#[cfg(any(
+    loom,
+    not(any(
+        feature = "adaptive",
+        feature = "backoff",
+        feature = "exponential-backoff",
+    )),
+))]
+use spin_while_locked as wait_while_locked;
+
+#[cfg(all(
+    not(loom),
+    feature = "exponential-backoff",
+    not(any(feature = "adaptive", feature = "backoff")),
+))]
+use backoff_while_locked as wait_while_locked;
+
+#[cfg(all(not(loom), feature = "adaptive", not(feature = "backoff")))]
+use super::adaptive::wait_while_locked;
+
+#[cfg(all(not(loom), feature = "backoff"))]
+use super::backoff::wait_while_locked;
@@ -17,105 +17,521 @@
  */
 
 #![allow(unused_macros)]
-#[allow(unused_imports)]
-use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::ManuallyDrop;
 use core::ops::{Deref, DerefMut};
 #[cfg(doc)]
 use core::sync::atomic;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::Ordering;
+
+#[macro_use]
+mod sync;
+#[cfg(any(spinlock, feature = "poisoning"))]
+use sync::AtomicBool;
+#[allow(unused_imports)]
+use sync::{Access, ValueCell};
+
+// Signal blocking on Unix, which `SignalSafeSpinLock` uses even when the
+// fallback types don't.
+#[allow(dead_code)]
+#[cfg(all(feature = "signal-mask", not(windows)))]
+pub(crate) mod sigmask;
 
 #[allow(dead_code)]
-#[cfg_attr(not(feature = "signal"), path = "signal_none.rs")]
+#[cfg(not(all(
+    feature = "signal",
+    not(windows),
+    not(feature = "critical-section"),
+)))]
+#[cfg_attr(feature = "critical-section", path = "signal_cs.rs")]
+#[cfg_attr(
+    all(feature = "signal", windows, not(feature = "critical-section")),
+    path = "signal_windows.rs"
+)]
+#[cfg_attr(
+    all(
+        feature = "signal-nostd",
+        not(any(feature = "signal", feature = "critical-section")),
+    ),
+    path = "signal_linux.rs"
+)]
+#[cfg_attr(
+    not(any(
+        feature = "signal",
+        feature = "signal-nostd",
+        feature = "critical-section",
+    )),
+    path = "signal_none.rs"
+)]
 mod signal;
+#[cfg(all(
+    feature = "signal",
+    not(windows),
+    not(feature = "critical-section"),
+))]
+use sigmask as signal;
 use signal::SignalGuard;
 
-struct Guard<'a, T> {
-    value: &'a mut T,
+#[cfg(spinlock)]
+mod lock;
+#[allow(unused_imports)]
+#[cfg(spinlock)]
+pub(crate) use lock::{acquire, release};
+
+#[cfg(all(feature = "deadlock-detection", spinlock, not(loom)))]
+mod deadlock;
+
+// With loom, only the models in the module itself use it.
+#[cfg(any(seqlock, all(loom, test, spinlock, feature = "seqlock")))]
+mod seqlock;
+
+#[cfg(feature = "trace")]
+mod trace;
+#[cfg(feature = "trace")]
+pub use trace::{TraceEntry, TraceOp, drain_trace};
+
+#[cfg(feature = "hooks")]
+mod hooks;
+#[cfg(feature = "hooks")]
+pub use hooks::{LockHooks, set_lock_hooks};
+
+// Also used by `Atomic<T>` regardless of the feature `striped`.
+#[cfg(spinlock)]
+mod stripes;
+
+#[cfg(all(feature = "backoff", spinlock))]
+#[cfg_attr(loom, allow(dead_code))]
+mod backoff;
+#[cfg(all(feature = "backoff", spinlock))]
+pub use backoff::{BackoffPolicy, backoff_policy, set_backoff_policy};
+
+#[cfg(feature = "poisoning")]
+mod poison;
+#[cfg(feature = "poisoning")]
+pub use poison::Poisoned;
+
+#[cfg(feature = "adaptive")]
+#[cfg_attr(loom, allow(dead_code))]
+mod adaptive;
+#[cfg(feature = "adaptive")]
+pub use adaptive::{ContentionStats, contention_stats};
+
+struct Guard<'a, T: Copy> {
+    /// Dropped before the lock is released; see [`Access`].
+    value: ManuallyDrop<Access<'a, T>>,
+    /// With `seqlock`, the value is accessed through this instead, and
+    /// stored when the guard is dropped.
+    #[cfg(seqlock)]
+    writer: seqlock::Writer<'a, T>,
+    #[cfg(spinlock)]
     lock: &'a AtomicBool,
+    #[cfg_attr(not(spinlock), allow(dead_code))]
     order: Ordering,
     _signal: SignalGuard,
+    /// The atomic's poison flag, and whether the thread was already
+    /// panicking when the lock was acquired.
+    #[cfg(feature = "poisoning")]
+    poison: (&'a AtomicBool, bool),
 }
 
-impl<'a, T> Deref for Guard<'a, T> {
+impl<'a, T: Copy> Deref for Guard<'a, T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        self.value
+        #[cfg(not(seqlock))]
+        let value = &self.value;
+        #[cfg(seqlock)]
+        let value = self.writer.get();
+        value
     }
 }
 
-impl<'a, T> DerefMut for Guard<'a, T> {
+impl<'a, T: Copy> DerefMut for Guard<'a, T> {
     fn deref_mut(&mut self) -> &mut T {
-        self.value
+        #[cfg(not(seqlock))]
+        let value = &mut self.value;
+        #[cfg(seqlock)]
+        let value = self.writer.get_mut();
+        value
     }
 }
 
-impl<'a, T> Drop for Guard<'a, T> {
+impl<'a, T: Copy> Drop for Guard<'a, T> {
     fn drop(&mut self) {
-        self.lock.store(
-            false,
-            match self.order {
-                Ordering::SeqCst => Ordering::SeqCst,
-                _ => Ordering::Release,
-            },
-        );
+        // SAFETY: The lock is still held.
+        #[cfg(seqlock)]
+        unsafe {
+            self.writer.finish()
+        };
+        // SAFETY: `self.value` is not used again.
+        unsafe { ManuallyDrop::drop(&mut self.value) };
+        #[cfg(feature = "poisoning")]
+        if !self.poison.1 && std::thread::panicking() {
+            self.poison.0.store(true, Ordering::Relaxed);
+        }
+        #[cfg(spinlock)]
+        release(self.lock, self.order);
+    }
+}
+
+/// Returns the ordering with which to lock an atomic for an operation that
+/// either stores a value with ordering `success`, or only loads the value
+/// with ordering `failure`, like `compare_exchange`.
+///
+/// The lock is acquired before the outcome is known, so it is acquired with
+/// the stronger of the two orderings; the guard’s ordering should then be
+/// set to `failure` if nothing is stored, so that the lock is released with
+/// that ordering. Like the built-in atomics, this panics if `failure` is
+/// [`Release`](Ordering::Release) or [`AcqRel`](Ordering::AcqRel).
+pub(crate) fn cas_order(success: Ordering, failure: Ordering) -> Ordering {
+    match failure {
+        Ordering::Release => {
+            panic!("there is no such thing as a release failure ordering")
+        }
+        Ordering::AcqRel => panic!(
+            "there is no such thing as an acquire-release failure ordering"
+        ),
+        Ordering::SeqCst => Ordering::SeqCst,
+        _ => success,
+    }
+}
+
+/// Returns the ordering with which the lock is acquired for an operation
+/// performed with ordering `order`.
+///
+/// Every operation acquires the lock with at least [`Acquire`] ordering and
+/// releases it with at least [`Release`] ordering (see [`release_order`]), so
+/// an operation with ordering [`AcqRel`] both observes writes published by
+/// the previous holder of the lock and publishes its own writes to the next
+/// holder. Weaker orderings are strengthened to the same effect, which is
+/// permitted by the memory model. With the feature `conservative-ordering`,
+/// every ordering is strengthened to [`SeqCst`].
+///
+/// [`Acquire`]: Ordering::Acquire
+/// [`Release`]: Ordering::Release
+/// [`AcqRel`]: Ordering::AcqRel
+/// [`SeqCst`]: Ordering::SeqCst
+#[cfg_attr(all(not(spinlock), no_atomic_bool), allow(dead_code))]
+pub(crate) fn acquire_order(order: Ordering) -> Ordering {
+    if cfg!(feature = "conservative-ordering") {
+        return Ordering::SeqCst;
+    }
+    match order {
+        Ordering::SeqCst => Ordering::SeqCst,
+        _ => Ordering::Acquire,
+    }
+}
+
+/// Returns the ordering with which the lock is released for an operation
+/// performed with ordering `order`. See [`acquire_order`].
+#[cfg_attr(all(not(spinlock), no_atomic_bool), allow(dead_code))]
+pub(crate) fn release_order(order: Ordering) -> Ordering {
+    if cfg!(feature = "conservative-ordering") {
+        return Ordering::SeqCst;
+    }
+    match order {
+        Ordering::SeqCst => Ordering::SeqCst,
+        _ => Ordering::Release,
+    }
+}
+
+/// Calls `f` while holding the stripe lock for the value at `addr`, for types
+/// like [`Atomic`](crate::Atomic) that don't contain a lock of their own.
+///
+/// With the feature `critical-section`, `f` runs in a critical section
+/// instead.
+pub(crate) fn with_stripe<R>(
+    addr: usize,
+    order: Ordering,
+    f: impl FnOnce() -> R,
+) -> R {
+    #[cfg(spinlock)]
+    struct Release(&'static AtomicBool, Ordering);
+
+    #[cfg(spinlock)]
+    impl Drop for Release {
+        fn drop(&mut self) {
+            release(self.0, self.1);
+        }
+    }
+
+    let _signal = SignalGuard::new();
+    #[cfg(spinlock)]
+    let lock = &stripes::get(addr).lock;
+    #[cfg(spinlock)]
+    acquire(lock, order);
+    #[cfg(spinlock)]
+    let _release = Release(lock, order);
+    #[cfg(not(spinlock))]
+    let _ = (addr, order);
+    f()
+}
+
+/// Holds the lock of a fallback atomic, giving direct access to its value.
+///
+/// This is passed to the closure given to the `with_lock` method of the
+/// fallback types (see
+/// [`AtomicFallback::with_lock`](crate::AtomicFallback::with_lock)), or
+/// returned by their unsafe `lock_scope` method. The lock is released when
+/// the scope is dropped.
+///
+/// While a scope is held, the current thread must not perform any other
+/// operation on the same atomic, which would deadlock. With the feature
+/// `striped`, this extends to any other atomic that uses the global stripe
+/// table and hashes to the same stripe: any fallback atomic, as well as
+/// [`Atomic`](crate::Atomic) for types without a matching built-in atomic
+/// and, with the feature `asm-atomics`, the 128-bit atomics on x86-64
+/// processors without `cmpxchg16b`.
+pub struct LockScope<'a, T: Copy>(Guard<'a, T>);
+
+impl<'a, T: Copy> LockScope<'a, T> {
+    /// Returns the current value, as seen while holding the lock.
+    ///
+    /// Because the lock is still held, the value reflects every change made
+    /// through this scope, and no other thread can have changed it since.
+    pub fn snapshot(&self) -> T {
+        *self.0
+    }
+
+    /// Sets the value.
+    pub fn set(&mut self, val: T) {
+        *self.0 = val;
+    }
+
+    /// Applies `f` to the value, storing and returning the result.
+    pub fn update(&mut self, f: impl FnOnce(T) -> T) -> T {
+        let value = f(*self.0);
+        *self.0 = value;
+        value
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for LockScope<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LockScope").field(&self.snapshot()).finish()
     }
 }
 
+/// Associates a type with a zero-sized type whose alignment is that of the
+/// built-in atomic for that type, which is always equal to the type’s size.
+///
+/// The fallback types contain a zero-length array of this type so that they
+/// have at least the same alignment as the corresponding built-in atomics.
+trait AtomicAlign {
+    type Align;
+}
+
+macro_rules! define_align {
+    ($name:ident, $align:literal, [$($type:ty),*]) => {
+        #[repr(align($align))]
+        struct $name;
+
+        $(impl AtomicAlign for $type {
+            type Align = $name;
+        })*
+    };
+}
+
+define_align!(Align1, 1, [i8, u8]);
+define_align!(Align2, 2, [i16, u16]);
+define_align!(Align4, 4, [i32, u32]);
+define_align!(Align8, 8, [i64, u64]);
+define_align!(Align16, 16, [i128, u128]);
+#[cfg(feature = "wide")]
+define_align!(Align32, 32, [crate::U256, crate::I256]);
+
+#[cfg(target_pointer_width = "16")]
+type AlignPtr = Align2;
+#[cfg(target_pointer_width = "32")]
+type AlignPtr = Align4;
+#[cfg(target_pointer_width = "64")]
+type AlignPtr = Align8;
+
+impl AtomicAlign for isize {
+    type Align = AlignPtr;
+}
+
+impl AtomicAlign for usize {
+    type Align = AlignPtr;
+}
+
+impl<T> AtomicAlign for *mut T {
+    type Align = AlignPtr;
+}
+
+/// Records an operation on `$self` if the feature `trace` is enabled.
+macro_rules! trace {
+    ($self:expr, $op:ident, $arg:expr, $prev:expr) => {
+        #[cfg(feature = "trace")]
+        trace::record(
+            trace::TraceOp::$op,
+            $self as *const Self as *const (),
+            $arg,
+            $prev,
+        );
+    };
+}
+
 macro_rules! define_fallback {
     ($atomic:ident$(<$generic:ident>)?, $type:ty, $doc:expr) => {
+        // `compact_fallback` (see build.rs) is set when the value is the
+        // only field other than `_align`, so `repr(C)` places it at the
+        // start and gives the type the same size as the value. Unlike
+        // `repr(transparent)`, this keeps the alignment of the built-in
+        // atomic, which can be stricter than that of the value (e.g., for
+        // `u64` on 32-bit x86).
+        #[cfg_attr(compact_fallback, repr(C))]
         pub struct $atomic$(<$generic>)? {
-            value: UnsafeCell<$type>,
+            value: ValueCell<$type>,
+            #[cfg(all(spinlock, not(feature = "striped")))]
             lock: AtomicBool,
+            #[cfg(all(seqlock, not(feature = "striped")))]
+            seq: core::sync::atomic::AtomicUsize,
+            #[cfg(feature = "poisoning")]
+            poison: AtomicBool,
+            _align: [<$type as AtomicAlign>::Align; 0],
         }
 
+        // SAFETY: The value is an integer or a pointer, for which zero is
+        // valid, and all-zero flags are unlocked and unpoisoned. loom's
+        // cells and atomics are not plain memory, so this is omitted there.
+        #[cfg(not(loom))]
+        impl_zeroable!([$($generic)?] $atomic$(<$generic>)?);
+
         impl$(<$generic>)? $atomic$(<$generic>)? {
-            /// Creates a new atomic.
-            #[doc = concat!("\n\n", $doc, "::new`].")]
-            pub const fn new(v: $type) -> Self {
-                Self {
-                    value: UnsafeCell::new(v),
-                    lock: AtomicBool::new(false),
+            loom_const_fn! {
+                /// Creates a new atomic.
+                #[doc = concat!("\n\n", $doc, "::new`].")]
+                pub fn new(v: $type) -> Self {
+                    Self {
+                        value: ValueCell::new(v),
+                        #[cfg(all(spinlock, not(feature = "striped")))]
+                        lock: AtomicBool::new(false),
+                        #[cfg(all(seqlock, not(feature = "striped")))]
+                        seq: core::sync::atomic::AtomicUsize::new(0),
+                        #[cfg(feature = "poisoning")]
+                        poison: AtomicBool::new(false),
+                        _align: [],
+                    }
                 }
             }
 
+            /// Whether this type is always lock-free. This is never the case
+            /// for a fallback type.
+            pub const IS_ALWAYS_LOCK_FREE: bool = false;
+
+            /// Returns whether operations on this type are lock-free. This is
+            /// never the case for a fallback type.
+            pub const fn is_lock_free() -> bool {
+                Self::IS_ALWAYS_LOCK_FREE
+            }
+
+            #[cfg(spinlock)]
+            fn lock_flag(&self) -> &AtomicBool {
+                #[cfg(not(feature = "striped"))]
+                let lock = &self.lock;
+                #[cfg(feature = "striped")]
+                let lock = &stripes::get(self as *const Self as usize).lock;
+                lock
+            }
+
+            /// Returns the sequence counter, which with `striped` belongs
+            /// to the stripe.
+            #[cfg(seqlock)]
+            fn seq(&self) -> &core::sync::atomic::AtomicUsize {
+                #[cfg(not(feature = "striped"))]
+                let seq = &self.seq;
+                #[cfg(feature = "striped")]
+                let seq = &stripes::get(self as *const Self as usize).seq;
+                seq
+            }
+
             fn lock(&self, order: Ordering) -> Guard<'_, $type> {
+                #[cfg(feature = "hooks")]
+                let hooks = hooks::get();
+                #[cfg(feature = "hooks")]
+                if let Some(hooks) = hooks {
+                    (hooks.before_acquire)(self as *const Self as *const ());
+                }
                 let signal = SignalGuard::new();
-                while self
-                    .lock
-                    .compare_exchange_weak(
-                        false,
-                        true,
-                        match order {
-                            Ordering::SeqCst => Ordering::SeqCst,
-                            _ => Ordering::Acquire,
-                        },
-                        Ordering::Relaxed,
-                    )
-                    .is_err()
-                {
-                    while self.lock.load(Ordering::Relaxed) {
-                        core::hint::spin_loop();
-                    }
+                #[cfg(spinlock)]
+                let lock = self.lock_flag();
+                #[cfg(spinlock)]
+                acquire(lock, order);
+                #[cfg(feature = "hooks")]
+                if let Some(hooks) = hooks {
+                    (hooks.after_acquire)(self as *const Self as *const ());
                 }
+                // SAFETY: The lock ensures the value won't be accessed
+                // concurrently. References obtained through the guard can't
+                // outlive borrows of it, so they can't overlap with accesses
+                // made by callbacks (e.g., in `fetch_update`) while the guard
+                // is held.
+                let value = unsafe { self.value.access() };
                 Guard {
-                    // SAFETY: This type uses locks to ensure the value won't
-                    // be accessed concurrently.
-                    value: unsafe { &mut *self.value.get() },
-                    lock: &self.lock,
+                    value: ManuallyDrop::new(value),
+                    // SAFETY: The lock is held until the guard is dropped.
+                    #[cfg(seqlock)]
+                    writer: unsafe {
+                        seqlock::Writer::new(self.seq(), self.value.as_ptr())
+                    },
+                    #[cfg(spinlock)]
+                    lock,
                     order,
                     _signal: signal,
+                    #[cfg(feature = "poisoning")]
+                    poison: (&self.poison, std::thread::panicking()),
                 }
             }
 
             /// Returns a mutable reference to the underlying value.
+            ///
+            /// This does not acquire the lock: the mutable reference
+            /// guarantees that no other thread can access the atomic.
             #[doc = concat!("\n\n", $doc, "::get_mut`].")]
+            ///
+            #[cfg_attr(fallback_128, doc = "```")]
+            #[cfg_attr(not(fallback_128), doc = "```ignore")]
+            /// use atomic_int::AtomicU128;
+            /// use std::sync::atomic::Ordering;
+            ///
+            /// // `AtomicU128` is a fallback type on most targets.
+            /// let mut a = AtomicU128::new(1);
+            /// // Leave the lock held forever. If `get_mut` or `into_inner`
+            /// // acquired it, they would never return.
+            /// // SAFETY: Leaking a scope is allowed.
+            /// std::mem::forget(unsafe { a.lock_scope(Ordering::Relaxed) });
+            /// *a.get_mut() += 1;
+            /// assert_eq!(*a.get_mut(), 2);
+            /// assert_eq!(a.into_inner(), 2);
+            /// ```
             pub fn get_mut(&mut self) -> &mut $type {
                 self.value.get_mut()
             }
 
             /// Consumes the atomic and returns the contained value.
+            ///
+            /// Like [`Self::get_mut`], this does not acquire the lock, as
+            /// taking ownership of the atomic guarantees that no other thread
+            /// can access it.
             #[doc = concat!("\n\n", $doc, "::into_inner`].")]
+            ///
+            #[cfg_attr(fallback_128, doc = "```")]
+            #[cfg_attr(not(fallback_128), doc = "```ignore")]
+            /// use atomic_int::AtomicU128;
+            /// use std::sync::atomic::Ordering;
+            /// use std::thread;
+            ///
+            /// // `AtomicU128` is a fallback type on most targets.
+            /// let a = AtomicU128::new(0);
+            /// thread::scope(|s| {
+            ///     for _ in 0..4 {
+            ///         s.spawn(|| a.fetch_add(1, Ordering::Relaxed));
+            ///     }
+            /// });
+            /// // The threads have finished, so `a` can be consumed.
+            /// assert_eq!(a.into_inner(), 4);
+            /// ```
             pub fn into_inner(self) -> $type {
                 self.value.into_inner()
             }
@@ -123,6 +539,12 @@ macro_rules! define_fallback {
             /// Loads a value from the atomic.
             #[doc = concat!("\n\n", $doc, "::load`].")]
             pub fn load(&self, order: Ordering) -> $type {
+                #[cfg(seqlock)]
+                if let Some(value) =
+                    seqlock::load(self.seq(), self.value.as_ptr(), order)
+                {
+                    return value;
+                }
                 *self.lock(order)
             }
 
@@ -138,7 +560,9 @@ macro_rules! define_fallback {
             #[doc = concat!("\n\n", $doc, "::swap`].")]
             pub fn swap(&self, val: $type, order: Ordering) -> $type {
                 let mut guard = self.lock(order);
-                core::mem::replace(&mut *guard, val)
+                let prev = core::mem::replace(&mut *guard, val);
+                trace!(self, Swap, val, prev);
+                prev
             }
 
             /// Stores a value into the atomic if the current value is the same
@@ -150,7 +574,8 @@ macro_rules! define_fallback {
                 new: $type,
                 order: Ordering,
             ) -> $type {
-                match self.compare_exchange(current, new, order, order) {
+                let failure = crate::ext::load_order(order);
+                match self.compare_exchange(current, new, order, failure) {
                     Ok(prev) => prev,
                     Err(prev) => prev,
                 }
@@ -159,6 +584,23 @@ macro_rules! define_fallback {
             /// Stores a value into the atomic if the current value is the same
             /// as the `current` value.
             #[doc = concat!("\n\n", $doc, "::compare_exchange`].")]
+            ///
+            /// As with the built-in atomics, `failure` is the ordering of the
+            /// operation when it doesn’t store a value, and this panics if it
+            /// is [`Release`](Ordering::Release) or
+            /// [`AcqRel`](Ordering::AcqRel). The same applies to the
+            /// orderings of [`Self::fetch_update`].
+            ///
+            /// ```should_panic
+            /// use atomic_int::AtomicI128;
+            /// use std::sync::atomic::Ordering::{AcqRel, Acquire, Release};
+            ///
+            /// let a = AtomicI128::new(1);
+            /// assert_eq!(a.compare_exchange(1, 2, AcqRel, Acquire), Ok(1));
+            /// assert_eq!(a.compare_exchange(1, 3, AcqRel, Acquire), Err(2));
+            /// // Panics.
+            /// let _ = a.compare_exchange(2, 3, AcqRel, Release);
+            /// ```
             pub fn compare_exchange(
                 &self,
                 current: $type,
@@ -166,8 +608,9 @@ macro_rules! define_fallback {
                 success: Ordering,
                 failure: Ordering,
             ) -> Result<$type, $type> {
-                let mut guard = self.lock(success);
+                let mut guard = self.lock(cas_order(success, failure));
                 let prev = *guard;
+                trace!(self, CompareExchange, new, prev);
                 if prev == current {
                     *guard = new;
                     Ok(prev)
@@ -193,6 +636,58 @@ macro_rules! define_fallback {
             /// Fetches the value, and applies a function to it that returns an
             /// optional new value.
             #[doc = concat!("\n\n", $doc, "::fetch_update`].")]
+            ///
+            /// Unlike with the built-in atomics, `f` is called only once, and
+            /// it runs while the lock is held. It must therefore not access
+            /// this atomic, which would deadlock. With the feature `striped`,
+            /// the lock is shared with other atomics; see [`LockScope`] for
+            /// which ones `f` must not access either.
+            ///
+            /// With the feature `signal` (or `critical-section`), this method
+            /// is async-signal-safe as a whole, as long as `f` is: signals
+            /// are blocked while the lock is held, so a signal handler that
+            /// uses this atomic can't interrupt the critical section. This
+            /// means that `f` also runs with signals blocked: a signal raised
+            /// by `f` (or sent to this thread) is delivered only once this
+            /// method returns, so `f` must not wait for a signal to be
+            /// handled. `f` needs to be async-signal-safe only if this method
+            /// is itself called from a signal handler.
+            ///
+            // The test needs `libc`, which `signal` enables.
+            #[cfg_attr(
+                all(feature = "signal", unix, fallback_128),
+                doc = "```"
+            )]
+            #[cfg_attr(
+                not(all(feature = "signal", unix, fallback_128)),
+                doc = "```ignore"
+            )]
+            /// use atomic_int::{AtomicBool, AtomicU128};
+            /// use std::sync::atomic::Ordering::SeqCst;
+            ///
+            /// // `AtomicU128` is a fallback type on most targets.
+            /// static A: AtomicU128 = AtomicU128::new(0);
+            /// static HANDLED: AtomicBool = AtomicBool::new(false);
+            ///
+            /// extern "C" fn handler(_: libc::c_int) {
+            ///     let _ = A.fetch_update(SeqCst, SeqCst, |v| Some(v + 10));
+            ///     HANDLED.store(true, SeqCst);
+            /// }
+            ///
+            /// unsafe {
+            ///     let handler = handler as extern "C" fn(_) as usize;
+            ///     libc::signal(libc::SIGUSR1, handler as libc::sighandler_t);
+            /// }
+            /// let _ = A.fetch_update(SeqCst, SeqCst, |v| {
+            ///     // The signal is blocked, so the handler can't run (and
+            ///     // deadlock) until the lock is released.
+            ///     assert_eq!(unsafe { libc::raise(libc::SIGUSR1) }, 0);
+            ///     assert!(!HANDLED.load(SeqCst));
+            ///     Some(v + 1)
+            /// });
+            /// assert!(HANDLED.load(SeqCst));
+            /// assert_eq!(A.load(SeqCst), 11);
+            /// ```
             pub fn fetch_update<F>(
                 &self,
                 set_order: Ordering,
@@ -202,21 +697,206 @@ macro_rules! define_fallback {
             where
                 F: FnMut($type) -> Option<$type>,
             {
-                let _ = fetch_order;
-                let mut guard = self.lock(set_order);
+                let mut guard = self.lock(cas_order(set_order, fetch_order));
                 let prev = *guard;
                 if let Some(value) = f(prev) {
                     *guard = value;
+                    trace!(self, FetchUpdate, value, prev);
                     Ok(prev)
                 } else {
+                    guard.order = fetch_order;
+                    trace!(self, FetchUpdate, prev, prev);
                     Err(prev)
                 }
             }
 
-            /// Returns a mutable pointer to the underlying value.
-            #[doc = concat!("\n\n", $doc, "::as_ptr`].")]
-            pub const fn as_ptr(&self) -> *mut $type {
-                self.value.get()
+            /// Acquires the lock, returning a scope through which the value
+            /// can be read and modified repeatedly in one critical section.
+            ///
+            /// `order` describes the ordering of the critical section as a
+            /// whole, like that of a read-modify-write operation. This method
+            /// is available only on the fallback types; see [`LockScope`] for
+            /// restrictions while the scope is held.
+            ///
+            #[cfg_attr(fallback_128, doc = "```")]
+            #[cfg_attr(not(fallback_128), doc = "```ignore")]
+            /// use atomic_int::AtomicU128;
+            /// use std::sync::atomic::Ordering;
+            ///
+            /// // `AtomicU128` is a fallback type on most targets.
+            /// let a = AtomicU128::new(1);
+            /// let snapshot = {
+            ///     // SAFETY: No other scope is created while this is held.
+            ///     let mut scope = unsafe { a.lock_scope(Ordering::AcqRel) };
+            ///     scope.update(|v| v * 10);
+            ///     scope.snapshot()
+            /// };
+            /// assert_eq!(snapshot, 10);
+            /// ```
+            ///
+            /// Prefer [`Self::with_lock`], which does not require `unsafe`.
+            ///
+            /// # Safety
+            ///
+            /// Scopes returned by this method must be dropped in the reverse
+            /// order of their creation, relative to each other and to the
+            /// other locks taken by the current thread. With the features
+            /// `critical-section` or `signal`, each scope saves the state to
+            /// restore when it is dropped (such as whether interrupts were
+            /// enabled), so dropping them out of order can restore the wrong
+            /// state while a lock is still held.
+            ///
+            /// Never dropping a scope (e.g., with [`core::mem::forget`]) is
+            /// allowed, but leaves the lock held forever.
+            ///
+            /// As this method is `unsafe`, code like the following, which
+            /// drops the scopes out of order, does not compile:
+            ///
+            /// ```compile_fail
+            /// use atomic_int::AtomicU128;
+            /// use std::sync::atomic::Ordering;
+            ///
+            /// let a = AtomicU128::new(1);
+            /// let b = AtomicU128::new(2);
+            /// let x = a.lock_scope(Ordering::Relaxed);
+            /// let y = b.lock_scope(Ordering::Relaxed);
+            /// drop(x);
+            /// drop(y);
+            /// ```
+            pub unsafe fn lock_scope(
+                &self,
+                order: Ordering,
+            ) -> LockScope<'_, $type> {
+                LockScope(self.lock(order))
+            }
+
+            /// Calls `f` with a [`LockScope`] for this atomic, returning the
+            /// result of `f`. The lock is released when `f` returns.
+            ///
+            /// See [`Self::lock_scope`]. Like with [`Self::fetch_update`], `f`
+            /// must not access this atomic (or, with the feature `striped`,
+            /// the other atomics listed under [`LockScope`]).
+            pub fn with_lock<R>(
+                &self,
+                order: Ordering,
+                f: impl FnOnce(&mut LockScope<'_, $type>) -> R,
+            ) -> R {
+                // SAFETY: The scope is local to this call and is dropped
+                // before it returns, after any scope created by `f`. As `f`
+                // receives only a reference with a fresh lifetime, it cannot
+                // swap this scope with another or move it out.
+                f(&mut unsafe { self.lock_scope(order) })
+            }
+
+            /// Returns whether an operation on this atomic panicked while
+            /// holding its lock, such as when the closure passed to
+            /// [`Self::fetch_update`] panics.
+            ///
+            /// Poisoning has no effect on the other methods, which behave
+            /// exactly like those of the built-in atomics; it is observable
+            /// only through this method and
+            /// [`Self::fetch_update_poisonable`]. Built-in atomics have no
+            /// lock and are never poisoned.
+            #[cfg(feature = "poisoning")]
+            #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "poisoning")))]
+            pub fn is_poisoned(&self) -> bool {
+                self.poison.load(Ordering::Relaxed)
+            }
+
+            /// Clears the poison flag. See [`Self::is_poisoned`].
+            #[cfg(feature = "poisoning")]
+            #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "poisoning")))]
+            pub fn clear_poison(&self) {
+                self.poison.store(false, Ordering::Relaxed);
+            }
+
+            /// Like [`Self::fetch_update`], but fails with [`Poisoned`]
+            /// without calling `f` if the atomic is poisoned, like
+            /// [`Mutex::lock`](std::sync::Mutex::lock). The same restrictions
+            /// on `f` apply: it must not access this atomic (or, with the
+            /// feature `striped`, the other atomics listed under
+            /// [`LockScope`]).
+            ///
+            #[cfg_attr(fallback_128, doc = "```")]
+            #[cfg_attr(not(fallback_128), doc = "```ignore")]
+            /// use atomic_int::AtomicI128;
+            /// use std::panic::{AssertUnwindSafe, catch_unwind};
+            /// use std::sync::atomic::Ordering::SeqCst;
+            ///
+            /// // `AtomicI128` is a fallback type on most targets.
+            /// let a = AtomicI128::new(1);
+            /// let _ = catch_unwind(AssertUnwindSafe(|| {
+            ///     a.fetch_update(SeqCst, SeqCst, |_| panic!())
+            /// }));
+            /// assert!(a.is_poisoned());
+            /// let result = a.fetch_update_poisonable(SeqCst, SeqCst, Some);
+            /// assert_eq!(result.unwrap_err().current, 1);
+            ///
+            /// a.clear_poison();
+            /// let result = a.fetch_update_poisonable(SeqCst, SeqCst, |v| {
+            ///     Some(v + 1)
+            /// });
+            /// assert_eq!(result, Ok(Ok(1)));
+            /// ```
+            #[cfg(feature = "poisoning")]
+            #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "poisoning")))]
+            pub fn fetch_update_poisonable<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                mut f: F,
+            ) -> Result<Result<$type, $type>, Poisoned<$type>>
+            where
+                F: FnMut($type) -> Option<$type>,
+            {
+                let mut poisoned = None;
+                let result = self.fetch_update(set_order, fetch_order, |v| {
+                    if self.poison.load(Ordering::Relaxed) {
+                        poisoned = Some(v);
+                        return None;
+                    }
+                    f(v)
+                });
+                match poisoned {
+                    Some(current) => Err(Poisoned {
+                        current,
+                    }),
+                    None => Ok(result),
+                }
+            }
+
+            loom_const_fn! {
+                /// Returns a mutable pointer to the underlying value.
+                #[doc = concat!("\n\n", $doc, "::as_ptr`].")]
+                pub fn as_ptr(&self) -> *mut $type {
+                    self.value.as_ptr()
+                }
+            }
+
+            /// Creates a reference to an atomic from a pointer to its value.
+            #[doc = concat!("\n\n", $doc, "::from_ptr`].")]
+            ///
+            /// This is available only when this type has the same size as
+            /// its value, which the feature `striped` guarantees whatever
+            /// other features are enabled. This is also the case with
+            /// `critical-section`, unless `poisoning` is enabled.
+            ///
+            /// # Safety
+            ///
+            /// See [`AtomicIntExt::from_ptr`][ext]. In particular, accesses
+            /// through the returned reference are not synchronized with
+            /// atomic accesses made by C code.
+            ///
+            /// [ext]: crate::AtomicIntExt::from_ptr
+            #[cfg(any(doc, compact_fallback))]
+            pub unsafe fn from_ptr<'a>(ptr: *mut $type) -> &'a Self {
+                #[allow(clippy::let_unit_value)]
+                let () = crate::ext::SameLayout::<Self, $type>::CHECK;
+                // SAFETY: Checked by caller. `Self` has the same size as its
+                // value, so apart from `_align`, which is zero-sized, it
+                // consists solely of its `ValueCell`, which `repr(C)` places
+                // at the start.
+                unsafe { &*ptr.cast() }
             }
         }
 
@@ -225,9 +905,98 @@ macro_rules! define_fallback {
     };
 }
 
+/// Implements the traits that the built-in atomic integers implement.
+macro_rules! define_int_traits {
+    ($atomic:ident, $int:ty) => {
+        impl Default for $atomic {
+            fn default() -> Self {
+                Self::new(<$int as crate::PrimInt>::ZERO)
+            }
+        }
+
+        impl From<$int> for $atomic {
+            fn from(v: $int) -> Self {
+                Self::new(v)
+            }
+        }
+
+        impl core::fmt::Debug for $atomic {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                core::fmt::Debug::fmt(&self.load(Ordering::SeqCst), f)
+            }
+        }
+
+        // Like the built-in atomics, the value is always valid, even if an
+        // operation panics (e.g., in the closure passed to `fetch_update`).
+        impl core::panic::RefUnwindSafe for $atomic {}
+    };
+}
+
+macro_rules! define_bit_ops {
+    ($atomic:ident, $int:ty) => {
+        impl $atomic {
+            /// Sets the bit at `index`, returning its previous value.
+            ///
+            /// See [`AtomicIntExt::fetch_bit_set`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_bit_set
+            pub fn fetch_bit_set(&self, index: u32, order: Ordering) -> bool {
+                let mask = crate::ext::bit_mask::<$int>(index);
+                self.fetch_or(mask, order) & mask != crate::PrimInt::ZERO
+            }
+
+            /// Clears the bit at `index`, returning its previous value.
+            ///
+            /// See [`AtomicIntExt::fetch_bit_clear`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_bit_clear
+            pub fn fetch_bit_clear(
+                &self,
+                index: u32,
+                order: Ordering,
+            ) -> bool {
+                let mask = crate::ext::bit_mask::<$int>(index);
+                self.fetch_and(!mask, order) & mask != crate::PrimInt::ZERO
+            }
+
+            /// Toggles the bit at `index`, returning its previous value.
+            ///
+            /// See [`AtomicIntExt::fetch_bit_toggle`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_bit_toggle
+            pub fn fetch_bit_toggle(
+                &self,
+                index: u32,
+                order: Ordering,
+            ) -> bool {
+                let mask = crate::ext::bit_mask::<$int>(index);
+                self.fetch_xor(mask, order) & mask != crate::PrimInt::ZERO
+            }
+
+            /// Loads the value and returns the bit at `index`.
+            ///
+            /// See [`AtomicIntExt::bit_test`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::bit_test
+            pub fn bit_test(&self, index: u32, order: Ordering) -> bool {
+                let mask = crate::ext::bit_mask::<$int>(index);
+                self.load(order) & mask != crate::PrimInt::ZERO
+            }
+        }
+    };
+}
+
 macro_rules! define_fallback_int {
     ($atomic:ident, $int:ty, $doc:expr) => {
         define_fallback!($atomic, $int, $doc);
+        impl_atomic_int_ext!($atomic, $int, false);
+        impl_serde!([] $atomic, $int);
+        define_int_traits!($atomic, $int);
+        define_bit_ops!($atomic, $int);
+        impl_atomic_traits!(int $atomic, $int);
 
         impl $atomic {
             /// Adds to the current value, returning the previous value.
@@ -235,7 +1004,8 @@ macro_rules! define_fallback_int {
             pub fn fetch_add(&self, val: $int, order: Ordering) -> $int {
                 let mut guard = self.lock(order);
                 let prev = *guard;
-                *guard += val;
+                *guard = prev.wrapping_add(val);
+                trace!(self, FetchAdd, val, prev);
                 prev
             }
 
@@ -244,7 +1014,8 @@ macro_rules! define_fallback_int {
             pub fn fetch_sub(&self, val: $int, order: Ordering) -> $int {
                 let mut guard = self.lock(order);
                 let prev = *guard;
-                *guard -= val;
+                *guard = prev.wrapping_sub(val);
+                trace!(self, FetchSub, val, prev);
                 prev
             }
 
@@ -254,6 +1025,7 @@ macro_rules! define_fallback_int {
                 let mut guard = self.lock(order);
                 let prev = *guard;
                 *guard &= val;
+                trace!(self, FetchAnd, val, prev);
                 prev
             }
 
@@ -263,6 +1035,7 @@ macro_rules! define_fallback_int {
                 let mut guard = self.lock(order);
                 let prev = *guard;
                 *guard = !(prev & val);
+                trace!(self, FetchNand, val, prev);
                 prev
             }
 
@@ -272,6 +1045,7 @@ macro_rules! define_fallback_int {
                 let mut guard = self.lock(order);
                 let prev = *guard;
                 *guard |= val;
+                trace!(self, FetchOr, val, prev);
                 prev
             }
 
@@ -281,6 +1055,7 @@ macro_rules! define_fallback_int {
                 let mut guard = self.lock(order);
                 let prev = *guard;
                 *guard ^= val;
+                trace!(self, FetchXor, val, prev);
                 prev
             }
 
@@ -290,6 +1065,7 @@ macro_rules! define_fallback_int {
                 let mut guard = self.lock(order);
                 let prev = *guard;
                 *guard = prev.max(val);
+                trace!(self, FetchMax, val, prev);
                 prev
             }
 
@@ -299,15 +1075,203 @@ macro_rules! define_fallback_int {
                 let mut guard = self.lock(order);
                 let prev = *guard;
                 *guard = prev.min(val);
+                trace!(self, FetchMin, val, prev);
+                prev
+            }
+
+            /// Adds to the current value, saturating at the numeric bounds
+            /// instead of overflowing.
+            ///
+            /// See [`AtomicIntExt::fetch_saturating_add`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_saturating_add
+            pub fn fetch_saturating_add(
+                &self,
+                val: $int,
+                order: Ordering,
+            ) -> $int {
+                let mut guard = self.lock(order);
+                let prev = *guard;
+                *guard = prev.saturating_add(val);
+                trace!(self, FetchUpdate, *guard, prev);
+                prev
+            }
+
+            /// Subtracts from the current value, saturating at the numeric
+            /// bounds instead of overflowing.
+            ///
+            /// See [`AtomicIntExt::fetch_saturating_sub`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_saturating_sub
+            pub fn fetch_saturating_sub(
+                &self,
+                val: $int,
+                order: Ordering,
+            ) -> $int {
+                let mut guard = self.lock(order);
+                let prev = *guard;
+                *guard = prev.saturating_sub(val);
+                trace!(self, FetchUpdate, *guard, prev);
                 prev
             }
+
+            /// Adds to the current value, unless the addition would
+            /// overflow.
+            ///
+            /// See [`AtomicIntExt::fetch_checked_add`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_checked_add
+            pub fn fetch_checked_add(
+                &self,
+                val: $int,
+                order: Ordering,
+            ) -> Option<$int> {
+                let fetch_order = crate::ext::load_order(order);
+                self.fetch_update(order, fetch_order, |v| v.checked_add(val))
+                    .ok()
+            }
+
+            /// Subtracts from the current value, unless the subtraction
+            /// would overflow.
+            ///
+            /// See [`AtomicIntExt::fetch_checked_sub`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_checked_sub
+            pub fn fetch_checked_sub(
+                &self,
+                val: $int,
+                order: Ordering,
+            ) -> Option<$int> {
+                let fetch_order = crate::ext::load_order(order);
+                self.fetch_update(order, fetch_order, |v| v.checked_sub(val))
+                    .ok()
+            }
+
+            /// Multiplies the current value by `val`, wrapping around on
+            /// overflow.
+            ///
+            /// See [`AtomicIntExt::fetch_mul`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_mul
+            pub fn fetch_mul(&self, val: $int, order: Ordering) -> $int {
+                let mut guard = self.lock(order);
+                let prev = *guard;
+                *guard = prev.wrapping_mul(val);
+                trace!(self, FetchUpdate, *guard, prev);
+                prev
+            }
+
+            /// Divides the current value by `val`, rounding toward zero.
+            ///
+            /// See [`AtomicIntExt::fetch_div`][ext].
+            ///
+            /// # Panics
+            ///
+            /// Panics if `val` is 0, without modifying the value.
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_div
+            pub fn fetch_div(&self, val: $int, order: Ordering) -> $int {
+                assert!(
+                    val != crate::PrimInt::ZERO,
+                    "attempt to divide by zero",
+                );
+                let mut guard = self.lock(order);
+                let prev = *guard;
+                *guard = prev.wrapping_div(val);
+                trace!(self, FetchUpdate, *guard, prev);
+                prev
+            }
+
+            /// Replaces the current value with the remainder of dividing it
+            /// by `val`.
+            ///
+            /// See [`AtomicIntExt::fetch_rem`][ext].
+            ///
+            /// # Panics
+            ///
+            /// Panics if `val` is 0, without modifying the value.
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_rem
+            pub fn fetch_rem(&self, val: $int, order: Ordering) -> $int {
+                assert!(
+                    val != crate::PrimInt::ZERO,
+                    concat!(
+                        "attempt to calculate the remainder ",
+                        "with a divisor of zero",
+                    ),
+                );
+                let mut guard = self.lock(order);
+                let prev = *guard;
+                *guard = prev.wrapping_rem(val);
+                trace!(self, FetchUpdate, *guard, prev);
+                prev
+            }
+
+            /// Negates the current value, wrapping around on overflow.
+            ///
+            /// See [`AtomicIntExt::fetch_neg`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_neg
+            pub fn fetch_neg(&self, order: Ordering) -> $int {
+                let mut guard = self.lock(order);
+                let prev = *guard;
+                *guard = <$int as crate::PrimInt>::ZERO.wrapping_sub(prev);
+                trace!(self, FetchUpdate, *guard, prev);
+                prev
+            }
+
+            /// Inverts every bit of the current value.
+            ///
+            /// See [`AtomicIntExt::fetch_not`][ext].
+            ///
+            /// [ext]: crate::AtomicIntExt::fetch_not
+            pub fn fetch_not(&self, order: Ordering) -> $int {
+                self.fetch_xor(!<$int as crate::PrimInt>::ZERO, order)
+            }
         }
     };
 }
 
+#[cfg(all(asm_atomic64, not(doc)))]
+mod armv7;
+
+#[cfg(all(asm_atomic128, not(doc)))]
+mod dwcas;
+
 macro_rules! define_primitive_fallback {
+    (AtomicI64, i64, $($cfg:tt)*) => {
+        define_primitive_fallback!(@asm AtomicI64, i64, $($cfg)*);
+    };
+    (AtomicU64, u64, $($cfg:tt)*) => {
+        define_primitive_fallback!(@asm AtomicU64, u64, $($cfg)*);
+    };
+    (AtomicI128, i128, $($cfg:tt)*) => {
+        define_primitive_fallback!(@asm128 AtomicI128, i128, $($cfg)*);
+    };
+    (AtomicU128, u128, $($cfg:tt)*) => {
+        define_primitive_fallback!(@asm128 AtomicU128, u128, $($cfg)*);
+    };
+    (@asm $atomic:ident, $int:ident, $($cfg:tt)*) => {
+        #[cfg(all(asm_atomic64, not(doc)))]
+        pub use armv7::$atomic;
+        #[cfg(not(all(asm_atomic64, not(doc))))]
+        define_primitive_fallback!(@lock $atomic, $int, $($cfg)*);
+    };
+    (@asm128 $atomic:ident, $int:ident, $($cfg:tt)*) => {
+        #[cfg(all(asm_atomic128, not(doc)))]
+        pub use dwcas::$atomic;
+        #[cfg(not(all(asm_atomic128, not(doc))))]
+        define_primitive_fallback!(@lock $atomic, $int, $($cfg)*);
+    };
     ($atomic:ident, $int:ident, $($cfg:tt)*) => {
-        #[cfg(any(doc, not($($cfg)*)))]
+        define_primitive_fallback!(@lock $atomic, $int, $($cfg)*);
+    };
+    (@lock $atomic:ident, $int:ident, $($cfg:tt)*) => {
+        #[cfg(any(
+            doc,
+            force_fallback,
+            not(any(feature = "portable-atomic", $($cfg)*)),
+        ))]
         define_fallback_int!(
             $atomic,
             $int,
@@ -319,13 +1283,138 @@ macro_rules! define_primitive_fallback {
 #[cfg(feature = "primitives")]
 with_primitive_atomics!(define_primitive_fallback);
 
+macro_rules! define_fallback_ptr {
+    ($atomic:ident, $doc:expr) => {
+        define_fallback!($atomic<T>, *mut T, $doc);
+
+        // SAFETY: Like the built-in `AtomicPtr`, this type only stores the
+        // pointer and never dereferences it.
+        unsafe impl<T> Send for $atomic<T> {}
+        impl_atomic_ptr_ext!($atomic, false);
+
+        impl<T> Default for $atomic<T> {
+            fn default() -> Self {
+                Self::new(core::ptr::null_mut())
+            }
+        }
+
+        impl<T> From<*mut T> for $atomic<T> {
+            fn from(p: *mut T) -> Self {
+                Self::new(p)
+            }
+        }
+
+        impl<T> fmt::Debug for $atomic<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.load(Ordering::SeqCst), f)
+            }
+        }
+
+        // See `define_int_traits`. The pointer is never dereferenced, so,
+        // like the built-in `AtomicPtr`, this doesn't depend on `T`.
+        impl<T> core::panic::RefUnwindSafe for $atomic<T> {}
+        impl<T> core::panic::UnwindSafe for $atomic<T> {}
+
+        impl<T> $atomic<T> {
+            fn fetch_map_addr(
+                &self,
+                order: Ordering,
+                f: impl FnOnce(usize) -> usize,
+            ) -> *mut T {
+                let mut guard = self.lock(order);
+                let prev = *guard;
+                *guard = crate::ext::map_addr(prev, f);
+                trace!(self, FetchUpdate, *guard, prev);
+                prev
+            }
+
+            /// Offsets the pointer by `val` elements of `T`, returning the
+            /// previous pointer.
+            #[doc = concat!("\n\n", $doc, "::fetch_ptr_add`].")]
+            pub fn fetch_ptr_add(
+                &self,
+                val: usize,
+                order: Ordering,
+            ) -> *mut T {
+                let val = val.wrapping_mul(core::mem::size_of::<T>());
+                self.fetch_byte_add(val, order)
+            }
+
+            /// Offsets the pointer backwards by `val` elements of `T`,
+            /// returning the previous pointer.
+            #[doc = concat!("\n\n", $doc, "::fetch_ptr_sub`].")]
+            pub fn fetch_ptr_sub(
+                &self,
+                val: usize,
+                order: Ordering,
+            ) -> *mut T {
+                let val = val.wrapping_mul(core::mem::size_of::<T>());
+                self.fetch_byte_sub(val, order)
+            }
+
+            /// Offsets the pointer by `val` bytes, returning the previous
+            /// pointer.
+            #[doc = concat!("\n\n", $doc, "::fetch_byte_add`].")]
+            pub fn fetch_byte_add(
+                &self,
+                val: usize,
+                order: Ordering,
+            ) -> *mut T {
+                self.fetch_map_addr(order, |a| a.wrapping_add(val))
+            }
+
+            /// Offsets the pointer backwards by `val` bytes, returning the
+            /// previous pointer.
+            #[doc = concat!("\n\n", $doc, "::fetch_byte_sub`].")]
+            pub fn fetch_byte_sub(
+                &self,
+                val: usize,
+                order: Ordering,
+            ) -> *mut T {
+                self.fetch_map_addr(order, |a| a.wrapping_sub(val))
+            }
+
+            /// Performs a bitwise “or” on the address of the pointer,
+            /// returning the previous pointer.
+            #[doc = concat!("\n\n", $doc, "::fetch_or`].")]
+            pub fn fetch_or(&self, val: usize, order: Ordering) -> *mut T {
+                self.fetch_map_addr(order, |a| a | val)
+            }
+
+            /// Performs a bitwise “and” on the address of the pointer,
+            /// returning the previous pointer.
+            #[doc = concat!("\n\n", $doc, "::fetch_and`].")]
+            pub fn fetch_and(&self, val: usize, order: Ordering) -> *mut T {
+                self.fetch_map_addr(order, |a| a & val)
+            }
+
+            /// Performs a bitwise “xor” on the address of the pointer,
+            /// returning the previous pointer.
+            #[doc = concat!("\n\n", $doc, "::fetch_xor`].")]
+            pub fn fetch_xor(&self, val: usize, order: Ordering) -> *mut T {
+                self.fetch_map_addr(order, |a| a ^ val)
+            }
+        }
+        impl_atomic_traits!([T] $atomic<T>, *mut T);
+    };
+}
+
 #[cfg(feature = "primitives")]
-#[cfg(any(doc, not(target_has_atomic = "ptr")))]
-define_fallback!(AtomicPtr<T>, *mut T, "See [`atomic::AtomicPtr");
+#[cfg(any(
+    doc,
+    test,
+    force_fallback,
+    not(any(feature = "portable-atomic", target_has_atomic = "ptr")),
+))]
+define_fallback_ptr!(AtomicPtr, "See [`atomic::AtomicPtr");
 
 macro_rules! define_c_fallback {
     ($atomic:ident, $int:ident, $feature:literal, $cfg:ident) => {
-        #[cfg(any(doc, not($cfg)))]
+        #[cfg(any(
+            doc,
+            force_fallback,
+            not(any(feature = "portable-atomic", $cfg)),
+        ))]
         define_fallback_int!(
             $atomic,
             super::ffi::$int,
@@ -339,5 +1428,21 @@ with_c_atomics!(define_c_fallback);
 #[cfg(doc)]
 define_fallback_int!(AtomicFallback, i32, "See, e.g., [`atomic::AtomicI32");
 
+#[cfg(feature = "wide")]
+define_fallback_int!(
+    AtomicU256,
+    crate::U256,
+    "See, e.g., [`atomic::AtomicU64"
+);
+#[cfg(feature = "wide")]
+define_fallback_int!(
+    AtomicI256,
+    crate::I256,
+    "See, e.g., [`atomic::AtomicI64"
+);
+
 #[cfg(doc)]
-define_fallback!(AtomicFallbackPtr<T>, *mut T, "See [`atomic::AtomicPtr");
+define_fallback_ptr!(AtomicFallbackPtr, "See [`atomic::AtomicPtr");
+
+#[cfg(all(test, not(loom)))]
+mod tests;
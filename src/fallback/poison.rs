@@ -0,0 +1,37 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use core::fmt::{self, Debug};
+
+/// The error returned by `fetch_update_poisonable` on a fallback type when
+/// an earlier operation on the atomic panicked while holding its lock.
+///
+/// `current` holds the value of the atomic, which was left unchanged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Poisoned<T> {
+    /// The current value of the atomic.
+    pub current: T,
+}
+
+impl<T> fmt::Display for Poisoned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("atomic was poisoned by a panic")
+    }
+}
+
+impl<T: Debug> std::error::Error for Poisoned<T> {}
@@ -0,0 +1,379 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Optimistic reads for the feature `seqlock`.
+//!
+//! Each fallback atomic (or, with the feature `striped`, each stripe) has a
+//! sequence counter, which is odd while a value it covers is being written. A
+//! writer holds the atomic's lock, makes the counter odd, writes the value,
+//! and makes the counter even again. A reader doesn't take
+//! the lock: it reads the counter, then the value, then the counter again,
+//! and retries if the counter was odd or changed.
+//!
+//! Since a reader can run concurrently with a writer, the value is read and
+//! written only with relaxed atomic operations on `usize` words (or on bytes,
+//! if the value isn't made of aligned words), so these accesses are never a
+//! data race; a torn read is detected by the counter and discarded. Outside
+//! of the writes performed here, the value is accessed only while the lock is
+//! held, and only by reading it, which can't race with optimistic readers.
+
+use super::sync::spin_loop;
+use core::mem::{MaybeUninit, align_of, size_of};
+use core::sync::atomic::Ordering;
+#[cfg(not(loom))]
+use core::sync::atomic::{AtomicU8, AtomicUsize, fence};
+#[cfg(loom)]
+use loom::sync::atomic::{AtomicUsize, fence};
+
+/// A value that can be read optimistically.
+pub trait Value: Copy {
+    /// Whether the value is read optimistically. This is false for
+    /// pointers, whose provenance would be lost by copying them in chunks,
+    /// so they are always loaded while holding the lock.
+    const OPTIMISTIC: bool;
+}
+
+macro_rules! impl_value {
+    ($($type:ty),*) => {$(
+        impl Value for $type {
+            const OPTIMISTIC: bool = true;
+        }
+    )*};
+}
+
+impl_value!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize);
+
+#[cfg(feature = "wide")]
+impl_value!(crate::U256, crate::I256);
+
+impl<T> Value for *mut T {
+    const OPTIMISTIC: bool = false;
+}
+
+/// Whether a `T` can be copied as a sequence of `usize` words rather than
+/// bytes.
+const fn word_sized<T>() -> bool {
+    // The size of `usize` is a power of two.
+    size_of::<T>() & (size_of::<usize>() - 1) == 0
+        && align_of::<T>() >= align_of::<usize>()
+}
+
+/// Copies a `T` from `src` to `dst` in chunks of type `C`, reading or
+/// writing each chunk of whichever pointer is atomic with `f`.
+///
+/// # Safety
+///
+/// `src` must be valid for reads and `dst` for writes, and both must be
+/// aligned to `C`. `T` must consist of a whole number of `C`s.
+unsafe fn copy_chunks<T, C>(
+    src: *const T,
+    dst: *mut T,
+    f: impl Fn(*const C, *mut C),
+) {
+    let src = src.cast::<C>();
+    let dst = dst.cast::<C>();
+    for i in 0..size_of::<T>() / size_of::<C>() {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { f(src.add(i), dst.add(i)) };
+    }
+}
+
+/// Reads a `T` from `src` with relaxed atomic loads.
+///
+/// # Safety
+///
+/// `src` must be valid for reads and aligned, and must not be written
+/// concurrently except with [`write`]. The result may be torn, so it must be
+/// discarded unless the sequence counter shows that no write overlapped the
+/// read.
+#[cfg(not(loom))]
+unsafe fn read<T: Copy>(src: *const T) -> T {
+    let mut value = MaybeUninit::<T>::uninit();
+    let dst = value.as_mut_ptr();
+    // SAFETY: Guaranteed by the caller; `word_sized` ensures alignment.
+    unsafe {
+        if word_sized::<T>() {
+            copy_chunks(src, dst, |s: *const usize, d: *mut usize| {
+                d.write((*s.cast::<AtomicUsize>()).load(Ordering::Relaxed));
+            });
+        } else {
+            copy_chunks(src, dst, |s: *const u8, d: *mut u8| {
+                d.write((*s.cast::<AtomicU8>()).load(Ordering::Relaxed));
+            });
+        }
+    }
+    // SAFETY: Every byte was initialized, and `T` is an integer.
+    unsafe { value.assume_init() }
+}
+
+/// Writes `value` to `dst` with relaxed atomic stores.
+///
+/// # Safety
+///
+/// `dst` must be valid for writes and aligned, and the lock protecting it
+/// must be held.
+#[cfg(not(loom))]
+unsafe fn write<T: Copy>(dst: *mut T, value: T) {
+    let src: *const T = &value;
+    // SAFETY: Guaranteed by the caller; `word_sized` ensures alignment.
+    unsafe {
+        if word_sized::<T>() {
+            copy_chunks(src, dst, |s: *const usize, d: *mut usize| {
+                (*d.cast::<AtomicUsize>()).store(*s, Ordering::Relaxed);
+            });
+        } else {
+            copy_chunks(src, dst, |s: *const u8, d: *mut u8| {
+                (*d.cast::<AtomicU8>()).store(*s, Ordering::Relaxed);
+            });
+        }
+    }
+}
+
+/// Like the other [`read`], but for the models in this module: loom's atomics
+/// are larger than the values they hold, so with loom, a `T` is stored as an
+/// array of atomic words instead. The fallback types never use `seqlock`
+/// with loom.
+///
+/// # Safety
+///
+/// `src` must point to such an array; otherwise, as for the other [`read`].
+#[cfg(loom)]
+unsafe fn read<T: Copy>(src: *const T) -> T {
+    assert!(word_sized::<T>());
+    let src = src.cast::<AtomicUsize>();
+    let mut value = MaybeUninit::<T>::uninit();
+    let dst = value.as_mut_ptr().cast::<usize>();
+    for i in 0..size_of::<T>() / size_of::<usize>() {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { dst.add(i).write((*src.add(i)).load(Ordering::Relaxed)) };
+    }
+    // SAFETY: Every word was initialized, and `T` is an integer.
+    unsafe { value.assume_init() }
+}
+
+/// Like the other [`write`], but for the models in this module; see the
+/// loom version of [`read`].
+///
+/// # Safety
+///
+/// `dst` must point to an array of atomic words, as for [`read`];
+/// otherwise, as for the other [`write`].
+#[cfg(loom)]
+unsafe fn write<T: Copy>(dst: *mut T, value: T) {
+    assert!(word_sized::<T>());
+    let src = (&value as *const T).cast::<usize>();
+    let dst = dst.cast::<AtomicUsize>();
+    for i in 0..size_of::<T>() / size_of::<usize>() {
+        // SAFETY: Guaranteed by the caller.
+        unsafe { (*dst.add(i)).store(src.add(i).read(), Ordering::Relaxed) };
+    }
+}
+
+/// Loads the value at `ptr` without taking the lock, or returns [`None`] if
+/// it must be loaded while holding the lock instead.
+///
+/// That is the case for pointers (see [`Value::OPTIMISTIC`]), and for
+/// [`SeqCst`](Ordering::SeqCst) loads (or any load, with the feature
+/// `conservative-ordering`), which must take part in the single total order
+/// of `SeqCst` operations that the lock provides. Other loads behave as if
+/// they were [`Acquire`](Ordering::Acquire).
+pub fn load<T: Value>(
+    seq: &AtomicUsize,
+    ptr: *const T,
+    order: Ordering,
+) -> Option<T> {
+    if !T::OPTIMISTIC
+        || order == Ordering::SeqCst
+        || cfg!(feature = "conservative-ordering")
+    {
+        return None;
+    }
+    loop {
+        let before = seq.load(Ordering::Acquire);
+        if before & 1 == 0 {
+            // SAFETY: The value is written concurrently only by `write`.
+            let value = unsafe { read(ptr) };
+            fence(Ordering::Acquire);
+            if seq.load(Ordering::Relaxed) == before {
+                return Some(value);
+            }
+        }
+        spin_loop();
+    }
+}
+
+/// Write access to the value of a fallback atomic whose lock is held,
+/// through a copy of the value that is stored when [`Self::finish`] is
+/// called, if it was modified, so that optimistic readers can detect the
+/// store.
+pub struct Writer<'a, T> {
+    seq: &'a AtomicUsize,
+    ptr: *mut T,
+    optimistic: bool,
+    local: T,
+    modified: bool,
+}
+
+impl<'a, T: Copy> Writer<'a, T> {
+    /// Starts writing the value at `ptr`, whose sequence counter is `seq`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads and writes and aligned, and the lock
+    /// protecting it must be held until [`Self::finish`] is called.
+    pub unsafe fn new(seq: &'a AtomicUsize, ptr: *mut T) -> Self
+    where
+        T: Value,
+    {
+        Self {
+            seq,
+            ptr,
+            optimistic: T::OPTIMISTIC,
+            // SAFETY: Guaranteed by the caller. While the lock is held, the
+            // value is written only by this writer.
+            #[cfg(not(loom))]
+            local: unsafe { ptr.read() },
+            // SAFETY: See above.
+            #[cfg(loom)]
+            local: unsafe { read(ptr) },
+            modified: false,
+        }
+    }
+
+    pub fn get(&self) -> &T {
+        &self.local
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.modified = true;
+        &mut self.local
+    }
+
+    /// Stores the value if it was modified.
+    ///
+    /// # Safety
+    ///
+    /// The lock must still be held.
+    pub unsafe fn finish(&mut self) {
+        if !self.modified {
+            return;
+        }
+        self.modified = false;
+        if !self.optimistic {
+            // SAFETY: Guaranteed by the caller of `Self::new`; no reader
+            // accesses the value without holding the lock.
+            unsafe { self.ptr.write(self.local) };
+            return;
+        }
+        // Only the holder of the lock modifies `seq`.
+        let before = self.seq.load(Ordering::Relaxed);
+        self.seq.store(before.wrapping_add(1), Ordering::Relaxed);
+        // Ensures a reader that sees any of the stores below also sees the
+        // odd counter when it checks the counter again.
+        fence(Ordering::Release);
+        // SAFETY: Guaranteed by the caller of `Self::new`.
+        unsafe { write(self.ptr, self.local) };
+        self.seq.store(before.wrapping_add(2), Ordering::Release);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    const WORDS: usize = size_of::<u128>() / size_of::<usize>();
+
+    /// A `u128` stored as loom atomics (see [`read`]).
+    struct Value128([AtomicUsize; WORDS]);
+
+    impl Value128 {
+        fn new() -> Self {
+            Self(core::array::from_fn(|_| AtomicUsize::new(0)))
+        }
+
+        fn ptr(&self) -> *mut u128 {
+            self.0.as_ptr() as *mut u128
+        }
+    }
+
+    /// Stores `1 | 1 << 64`, so that a torn read has different halves.
+    ///
+    /// # Safety
+    ///
+    /// No other thread may write `value` concurrently.
+    unsafe fn write_one(seq: &AtomicUsize, value: &Value128) {
+        // SAFETY: Guaranteed by the caller, which stands in for the lock.
+        let mut writer = unsafe { Writer::new(seq, value.ptr()) };
+        *writer.get_mut() = 1 | 1 << 64;
+        // SAFETY: See above.
+        unsafe { writer.finish() };
+    }
+
+    fn check(v: u128) {
+        assert_eq!(v as u64, (v >> 64) as u64);
+        assert!(v as u64 <= 1);
+    }
+
+    /// Checks that a load concurrent with a write never returns a torn value.
+    ///
+    /// In these models, the reader runs on the spawned thread: with the
+    /// writer there instead, loom misses the interleavings in which the
+    /// write overlaps the read.
+    #[test]
+    fn load_during_finish() {
+        loom::model(|| {
+            let seq = Arc::new(AtomicUsize::new(0));
+            let value = Arc::new(Value128::new());
+            let t = {
+                let (seq, value) = (seq.clone(), value.clone());
+                thread::spawn(move || {
+                    check(load(&seq, value.ptr(), Ordering::Acquire).unwrap());
+                })
+            };
+            // SAFETY: This is the only writer.
+            unsafe { write_one(&seq, &value) };
+            t.join().unwrap();
+        });
+    }
+
+    /// With the feature `striped`, atomics in the same stripe share a
+    /// counter, so writes to one make loads of the other retry.
+    #[test]
+    fn shared_counter() {
+        loom::model(|| {
+            let seq = Arc::new(AtomicUsize::new(0));
+            let a = Arc::new(Value128::new());
+            let b = Arc::new(Value128::new());
+            let t = {
+                let (seq, a, b) = (seq.clone(), a.clone(), b.clone());
+                thread::spawn(move || {
+                    assert_eq!(
+                        load(&seq, b.ptr(), Ordering::Acquire),
+                        Some(0)
+                    );
+                    check(load(&seq, a.ptr(), Ordering::Acquire).unwrap());
+                })
+            };
+            // SAFETY: This is the only writer.
+            unsafe { write_one(&seq, &a) };
+            t.join().unwrap();
+        });
+    }
+}
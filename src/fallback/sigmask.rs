@@ -0,0 +1,210 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! All functions in this module must be async-signal-safe.
+
+use crate::signal::SignalMaskFailure;
+use core::cell::{Cell, UnsafeCell};
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering};
+
+fn stderr(msg: &[u8]) {
+    unsafe {
+        libc::write(libc::STDERR_FILENO, msg.as_ptr().cast(), msg.len());
+    }
+}
+
+/// The hook installed with [`set_failure_hook`], or null to abort.
+static FAILURE_HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// See [`crate::signal::set_failure_hook`].
+pub fn set_failure_hook(hook: Option<fn(SignalMaskFailure)>) {
+    let hook = hook.map_or(ptr::null_mut(), |f| f as *mut ());
+    FAILURE_HOOK.store(hook, Ordering::Release);
+}
+
+/// Passes `failure` to the installed hook, or, if there is none, writes
+/// `msg` to stderr and aborts.
+fn fail(failure: SignalMaskFailure, msg: &[u8]) {
+    let hook = FAILURE_HOOK.load(Ordering::Acquire);
+    if hook.is_null() {
+        stderr(msg);
+        unsafe {
+            libc::abort();
+        }
+    }
+    // SAFETY: Non-null values are stored only by `set_failure_hook`, which
+    // converts them from a `fn(SignalMaskFailure)`.
+    let hook: fn(SignalMaskFailure) = unsafe { mem::transmute(hook) };
+    hook(failure);
+}
+
+const UNINIT: u8 = 0;
+const BUSY: u8 = 1;
+const READY: u8 = 2;
+
+struct SignalSet(UnsafeCell<MaybeUninit<libc::sigset_t>>);
+
+// SAFETY: The set is written only once, by the thread that moves `STATE`
+// from `UNINIT` to `BUSY`, and read only after `STATE` is `READY`.
+unsafe impl Sync for SignalSet {}
+
+static STATE: AtomicU8 = AtomicU8::new(UNINIT);
+static BLOCKED_SET: SignalSet =
+    SignalSet(UnsafeCell::new(MaybeUninit::uninit()));
+static INIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Fills `set` with all signals, returning whether this succeeded.
+fn fill(set: *mut libc::sigset_t) -> bool {
+    if unsafe { libc::sigfillset(set) } != 0 {
+        fail(SignalMaskFailure::FillSet, b"[new] sigfillset() failed\n");
+        return false;
+    }
+    true
+}
+
+/// Returns the cached set of signals to block, computing the full set if
+/// no other set was configured. Returns [`None`] if another thread (or the
+/// code this signal handler interrupted) is currently initializing it, or if
+/// computing it failed; this never waits, so that it remains
+/// async-signal-safe.
+fn blocked_set() -> Option<*const libc::sigset_t> {
+    let set = BLOCKED_SET.0.get().cast::<libc::sigset_t>();
+    match STATE.compare_exchange(
+        UNINIT,
+        BUSY,
+        Ordering::Acquire,
+        Ordering::Acquire,
+    ) {
+        Ok(_) => {
+            if !fill(set) {
+                STATE.store(UNINIT, Ordering::Release);
+                return None;
+            }
+            INIT_COUNT.fetch_add(1, Ordering::Relaxed);
+            STATE.store(READY, Ordering::Release);
+            Some(set)
+        }
+        Err(READY) => Some(set),
+        Err(_) => None,
+    }
+}
+
+/// See [`crate::signal::prewarm`].
+pub fn prewarm() {
+    blocked_set();
+}
+
+/// See [`crate::signal::set_blocked_signals`]. Returns whether the set was
+/// stored, which is possible only if the cached set is uninitialized.
+pub fn set_blocked(new_set: &libc::sigset_t) -> bool {
+    let set = BLOCKED_SET.0.get().cast::<libc::sigset_t>();
+    if STATE
+        .compare_exchange(UNINIT, BUSY, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        return false;
+    }
+    unsafe {
+        ptr::copy_nonoverlapping(new_set, set, 1);
+    }
+    STATE.store(READY, Ordering::Release);
+    true
+}
+
+/// The number of times the full signal set has been computed.
+pub fn init_count() -> usize {
+    INIT_COUNT.load(Ordering::Relaxed)
+}
+
+std::thread_local! {
+    /// Whether a [`SignalGuard`] on this thread currently has signals
+    /// blocked.
+    ///
+    /// The flag is set only after the signals are blocked and cleared before
+    /// they are unblocked, so a signal handler that interrupts this thread
+    /// never sees it set while they are not blocked. (This thread-local has
+    /// no destructor and is initialized with a constant, so accessing it is
+    /// async-signal-safe in practice.)
+    static BLOCKED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Blocks signals until dropped. Holds the previous signal mask, or
+/// [`None`] if signals were already blocked by an enclosing guard, or if
+/// they could not be blocked and a failure hook chose to continue anyway.
+///
+/// Nested guards don't make any system calls, so a batch of operations on
+/// fallback atomics within one guard (e.g., `with_signals_blocked`) blocks
+/// and unblocks signals only once. Guards are not [`Send`], as the flag that
+/// tracks this is per-thread, like signal masks.
+pub struct SignalGuard(Option<libc::sigset_t>, PhantomData<*const ()>);
+
+impl SignalGuard {
+    pub fn new() -> Self {
+        if BLOCKED.try_with(Cell::get).unwrap_or(false) {
+            return Self(None, PhantomData);
+        }
+        // If the cached set is being initialized, block all signals, which
+        // is always at least as strict as the configured set.
+        let mut local_set = MaybeUninit::uninit();
+        let new_set = match blocked_set() {
+            Some(set) => set,
+            None if fill(local_set.as_mut_ptr()) => local_set.as_ptr(),
+            None => return Self(None, PhantomData),
+        };
+        let mut old_set = MaybeUninit::uninit();
+        let code = unsafe {
+            libc::pthread_sigmask(
+                libc::SIG_SETMASK,
+                new_set,
+                old_set.as_mut_ptr(),
+            )
+        };
+        if code != 0 {
+            fail(
+                SignalMaskFailure::Block(code),
+                b"[new] pthread_sigmask() failed\n",
+            );
+            return Self(None, PhantomData);
+        }
+        let _ = BLOCKED.try_with(|blocked| blocked.set(true));
+        // SAFETY: `pthread_sigmask` initializes `old_set`.
+        Self(Some(unsafe { old_set.assume_init() }), PhantomData)
+    }
+}
+
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        let old_set = match &self.0 {
+            Some(set) => set,
+            None => return,
+        };
+        let _ = BLOCKED.try_with(|blocked| blocked.set(false));
+        let code = unsafe {
+            libc::pthread_sigmask(libc::SIG_SETMASK, old_set, ptr::null_mut())
+        };
+        if code != 0 {
+            fail(
+                SignalMaskFailure::Restore(code),
+                b"[drop] pthread_sigmask() failed\n",
+            );
+        }
+    }
+}
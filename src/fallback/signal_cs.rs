@@ -0,0 +1,49 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Runs every operation on a fallback atomic in a [`critical_section`], in
+//! place of a spinlock. The `critical-section` implementation provides mutual
+//! exclusion (by disabling interrupts on single-core targets, and with a
+//! hardware lock across cores on multi-core ones), so interrupt handlers
+//! can't deadlock by waiting for a lock held by the code they interrupted.
+
+use critical_section::RestoreState;
+
+pub struct SignalGuard(RestoreState);
+
+impl SignalGuard {
+    pub fn new() -> Self {
+        // SAFETY: The critical section is released exactly once, when the
+        // guard is dropped. Guards must be dropped in the reverse order of
+        // their creation: each is owned by a `Guard`, which the crate drops
+        // before returning (or is local to `with_stripe`), except for those
+        // in a `LockScope`, whose ordering is left to the caller of the
+        // unsafe `lock_scope`.
+        Self(unsafe { critical_section::acquire() })
+    }
+}
+
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        // SAFETY: `self.0` was returned by `critical_section::acquire` in
+        // `Self::new`.
+        unsafe {
+            critical_section::release(self.0);
+        }
+    }
+}
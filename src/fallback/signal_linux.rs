@@ -0,0 +1,137 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Blocks signals on Linux by invoking the `rt_sigprocmask` system call
+//! directly, for the feature `signal-nostd`, which provides the same
+//! protection as `signal` without depending on `libc` (and thus `std`).
+//!
+//! Every signal is blocked; `signal::set_blocked_signals`, the failure hook,
+//! and the other parts of the `signal` module are available only with
+//! `libc`. The kernel silently keeps `SIGKILL` and `SIGSTOP` unblocked.
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+compile_error!("the feature `signal-nostd` is supported only on Linux");
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64",
+)))]
+compile_error!(
+    "the feature `signal-nostd` is supported only on x86-64, AArch64, and \
+     RISC-V (64-bit)"
+);
+
+use core::arch::asm;
+
+/// The kernel's signal set, which has 64 bits on every supported
+/// architecture (unlike `libc::sigset_t`, which reserves more space).
+type KernelSigset = u64;
+
+const SIG_SETMASK: usize = 2;
+
+#[cfg(target_arch = "x86_64")]
+const SYS_RT_SIGPROCMASK: usize = 14;
+#[cfg(any(target_arch = "aarch64", target_arch = "riscv64"))]
+const SYS_RT_SIGPROCMASK: usize = 135;
+
+/// Replaces the signal mask of the current thread with `new`, storing the
+/// previous mask in `old`. Returns whether this succeeded.
+///
+/// # Safety
+///
+/// `new` must be valid for reads and `old` must be valid for writes.
+unsafe fn sigprocmask(
+    new: *const KernelSigset,
+    old: *mut KernelSigset,
+) -> bool {
+    let ret: isize;
+    let size = core::mem::size_of::<KernelSigset>();
+    // SAFETY: Checked by caller. `rt_sigprocmask` reads `new` and writes
+    // `old`, and has no other effect on memory.
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        asm!(
+            "syscall",
+            inlateout("rax") SYS_RT_SIGPROCMASK as isize => ret,
+            in("rdi") SIG_SETMASK,
+            in("rsi") new,
+            in("rdx") old,
+            in("r10") size,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    // SAFETY: See above.
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        asm!(
+            "svc 0",
+            in("x8") SYS_RT_SIGPROCMASK,
+            inlateout("x0") SIG_SETMASK as isize => ret,
+            in("x1") new,
+            in("x2") old,
+            in("x3") size,
+            options(nostack),
+        );
+    }
+    // SAFETY: See above.
+    #[cfg(target_arch = "riscv64")]
+    unsafe {
+        asm!(
+            "ecall",
+            in("a7") SYS_RT_SIGPROCMASK,
+            inlateout("a0") SIG_SETMASK as isize => ret,
+            in("a1") new,
+            in("a2") old,
+            in("a3") size,
+            options(nostack),
+        );
+    }
+    // The system call fails only if given an invalid argument.
+    ret == 0
+}
+
+/// Blocks all signals until dropped. Holds the previous signal mask, or
+/// [`None`] if signals could not be blocked.
+pub struct SignalGuard(Option<KernelSigset>);
+
+impl SignalGuard {
+    pub fn new() -> Self {
+        let mut old = 0;
+        // SAFETY: Both pointers refer to valid local variables.
+        if unsafe { sigprocmask(&!0, &mut old) } {
+            Self(Some(old))
+        } else {
+            Self(None)
+        }
+    }
+}
+
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        if let Some(old) = &self.0 {
+            let mut prev = 0;
+            // SAFETY: Both pointers refer to valid variables.
+            unsafe {
+                sigprocmask(old, &mut prev);
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! The Windows counterpart of the Unix signal blocking in `sigmask.rs`.
+//!
+//! Windows has no asynchronous signals that can interrupt a thread while it
+//! holds a fallback spinlock, so there is nothing to block:
+//!
+//! * Console control handlers (and the C runtime’s `SIGINT` and `SIGBREAK`,
+//!   which are built on them) run on a new thread, which simply waits for the
+//!   lock like any other thread.
+//! * Normal user-mode APCs run only while their thread is in an alertable
+//!   wait, which the fallback types never perform while holding a lock.
+//! * The remaining C runtime signals are synchronous: they are raised by the
+//!   thread itself, e.g. with `raise` or by a faulting instruction. Unlike on
+//!   Unix, a signal raised while the lock is held is delivered immediately, so
+//!   the closure passed to `fetch_update` must not raise one whose handler
+//!   uses the same atomic.
+//!
+//! Special user-mode APCs, which can interrupt arbitrary code, cannot be
+//! masked from user mode at all; code that queues them must not use
+//! fallback types from them.
+
+pub struct SignalGuard;
+
+impl SignalGuard {
+    pub fn new() -> Self {
+        Self
+    }
+}
@@ -0,0 +1,136 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A global table of spinlocks shared by all fallback atomics, used with the
+//! feature `striped` in place of a lock inside each atomic, and by
+//! [`Atomic`](crate::Atomic) for values without a built-in atomic.
+//!
+//! Each atomic is assigned a lock (a “stripe”) by hashing its address. The
+//! number of stripes is set at build time with the environment variable
+//! `ATOMIC_INT_LOCK_STRIPES` (default 64). More stripes make it less likely
+//! that unrelated atomics contend for the same lock, at the cost of more
+//! static memory: each stripe occupies its own cache line.
+//!
+//! With `striped`, the sequence counter that the feature `seqlock` would
+//! otherwise add to each atomic is kept in its stripe too, so that the
+//! fallback types always consist only of their values. The counter is
+//! updated only by writers holding the stripe’s lock, so sharing it just makes
+//! readers retry after writes to other atomics in the stripe. A poison flag
+//! can't be shared like this without poisoning unrelated atomics, so
+//! `poisoning` is incompatible with `striped`.
+
+use super::super::CachePadded;
+use super::sync::AtomicBool;
+
+/// Parses the stripe count, which `build.rs` has already validated.
+const fn parse(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut n = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        n = n * 10 + (bytes[i] - b'0') as usize;
+        i += 1;
+    }
+    n
+}
+
+/// The number of stripes in [`STRIPES`].
+const NUM_STRIPES: usize = parse(env!("ATOMIC_INT_LOCK_STRIPES"));
+
+/// An entry in [`STRIPES`].
+pub struct Stripe {
+    #[cfg(spinlock)]
+    pub lock: AtomicBool,
+    /// The sequence counter of the atomics that use this stripe.
+    #[cfg(all(seqlock, feature = "striped"))]
+    pub seq: core::sync::atomic::AtomicUsize,
+}
+
+impl Stripe {
+    loom_const_fn! {
+        fn new() -> Self {
+            Self {
+                #[cfg(spinlock)]
+                lock: AtomicBool::new(false),
+                #[cfg(all(seqlock, feature = "striped"))]
+                seq: core::sync::atomic::AtomicUsize::new(0),
+            }
+        }
+    }
+}
+
+#[cfg(not(loom))]
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: CachePadded<Stripe> = CachePadded::new(Stripe::new());
+
+#[cfg(not(loom))]
+static STRIPES: [CachePadded<Stripe>; NUM_STRIPES] = [EMPTY; NUM_STRIPES];
+
+// loom's atomics can't be created in a `const` context, and must be created
+// anew for each execution of a model.
+#[cfg(loom)]
+loom::lazy_static! {
+    static ref STRIPES: [CachePadded<Stripe>; NUM_STRIPES] =
+        core::array::from_fn(|_| CachePadded::new(Stripe::new()));
+}
+
+/// Returns the stripe for the atomic at `addr`.
+///
+/// Adjacent atomics differ only in their low address bits, so the address is
+/// passed through a multiplicative mix (the finalizer of MurmurHash3) before
+/// being reduced, so that every address bit affects the chosen stripe.
+pub fn get(addr: usize) -> &'static Stripe {
+    let mut x = addr as u64;
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^= x >> 33;
+    &STRIPES[(x % NUM_STRIPES as u64) as usize]
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+
+    fn index(addr: usize) -> usize {
+        let stripe = get(addr);
+        STRIPES.iter().position(|s| core::ptr::eq(&**s, stripe)).unwrap()
+    }
+
+    /// Checks that adjacent atomics, such as the elements of an array of
+    /// 16-byte atomics, mostly use different stripes.
+    #[test]
+    fn adjacent_addresses_spread() {
+        const LEN: usize = 16;
+        for base in [0x1000, 0x7fe0_1230, 0x5555_8000] {
+            let mut seen = [false; NUM_STRIPES];
+            for i in 0..LEN {
+                seen[index(base + i * 16)] = true;
+            }
+            let distinct = seen.iter().filter(|&&s| s).count();
+            assert!(
+                distinct >= LEN.min(NUM_STRIPES) * 3 / 4,
+                "{} stripes for {} atomics at {:#x}",
+                distinct,
+                LEN,
+                base,
+            );
+        }
+    }
+}
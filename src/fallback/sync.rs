@@ -0,0 +1,169 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The primitives that the fallback types are built on, which are replaced
+//! with [loom]’s model-checked equivalents when the crate is built with
+//! `--cfg loom`.
+//!
+//! loom tracks every access to a [`ValueCell`] made through [`Access`], and
+//! reports a data race if two such accesses can occur concurrently, so any
+//! bug in the locking of the fallback types is caught by a loom model.
+//!
+//! [loom]: https://docs.rs/loom/0.7
+
+#[cfg(all(spinlock, not(loom)))]
+pub use core::hint::spin_loop;
+#[cfg(all(spinlock, loom))]
+pub use loom::hint::spin_loop;
+
+#[cfg(all(
+    any(spinlock, feature = "poisoning"),
+    not(loom),
+    not(all(no_atomic_bool, feature = "portable-atomic")),
+))]
+pub use core::sync::atomic::AtomicBool;
+#[cfg(all(any(spinlock, feature = "poisoning"), loom))]
+pub use loom::sync::atomic::AtomicBool;
+// Without read-modify-write operations on the built-in `AtomicBool`, the
+// spinlocks can only be built on the one from `portable-atomic` (see the
+// check in lib.rs); the poison flags need only loads and stores.
+#[cfg(all(
+    any(spinlock, feature = "poisoning"),
+    not(loom),
+    no_atomic_bool,
+    feature = "portable-atomic",
+))]
+pub use portable_atomic::AtomicBool;
+
+#[cfg(not(loom))]
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+#[cfg(loom)]
+use loom::cell::UnsafeCell;
+
+/// Defines a function that is `const` unless loom is used, as loom’s
+/// primitives can't be constructed in a `const` context.
+macro_rules! loom_const_fn {
+    ($(#[$attr:meta])* $vis:vis fn $($rest:tt)*) => {
+        #[cfg(not(loom))]
+        $(#[$attr])*
+        $vis const fn $($rest)*
+
+        #[cfg(loom)]
+        $(#[$attr])*
+        $vis fn $($rest)*
+    };
+}
+
+/// The cell holding the value of a fallback type.
+#[repr(transparent)]
+pub struct ValueCell<T>(UnsafeCell<T>);
+
+impl<T> ValueCell<T> {
+    loom_const_fn! {
+        pub fn new(v: T) -> Self {
+            Self(UnsafeCell::new(v))
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        #[cfg(not(loom))]
+        let value = self.0.get_mut();
+        // SAFETY: The mutable reference to `self` guarantees that the value
+        // isn't accessed elsewhere for the lifetime of the returned reference.
+        #[cfg(loom)]
+        let value = self.0.with_mut(|p| unsafe { &mut *p });
+        value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+
+    loom_const_fn! {
+        /// Returns a pointer to the value.
+        ///
+        /// With loom, obtaining the pointer counts as a read of the value,
+        /// but accesses through the pointer are not tracked.
+        pub fn as_ptr(&self) -> *mut T {
+            #[cfg(not(loom))]
+            let ptr = self.0.get();
+            // The value is inside an `UnsafeCell`, so writing through the
+            // pointer is allowed, even though loom only exposes `*const T`.
+            #[cfg(loom)]
+            let ptr = self.0.get().with(|p| p as *mut T);
+            ptr
+        }
+    }
+
+    /// Starts an access to the value.
+    ///
+    /// # Safety
+    ///
+    /// While the returned [`Access`] is alive, the value must not be accessed
+    /// by other threads, and references obtained through it must not overlap
+    /// with other accesses.
+    pub unsafe fn access(&self) -> Access<'_, T> {
+        Access {
+            #[cfg(not(loom))]
+            ptr: self.0.get(),
+            #[cfg(loom)]
+            ptr: self.0.get_mut(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Exclusive access to the value in a [`ValueCell`].
+///
+/// With loom, the access lasts until this is dropped, so it must be dropped
+/// before the lock protecting the value is released.
+pub struct Access<'a, T> {
+    #[cfg(not(loom))]
+    ptr: *mut T,
+    #[cfg(loom)]
+    ptr: loom::cell::MutPtr<T>,
+    phantom: PhantomData<&'a mut T>,
+}
+
+impl<T> Deref for Access<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: Guaranteed by the caller of `ValueCell::access`.
+        #[cfg(not(loom))]
+        let value = unsafe { &*self.ptr };
+        // SAFETY: See above.
+        #[cfg(loom)]
+        let value = unsafe { self.ptr.deref() };
+        value
+    }
+}
+
+impl<T> DerefMut for Access<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: See `Self::deref`.
+        #[cfg(not(loom))]
+        let value = unsafe { &mut *self.ptr };
+        // SAFETY: See `Self::deref`.
+        #[cfg(loom)]
+        let value = unsafe { self.ptr.deref() };
+        value
+    }
+}
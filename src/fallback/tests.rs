@@ -0,0 +1,350 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests of fallback types that aren't otherwise reachable from doctests on
+//! targets with the corresponding built-in atomics.
+
+extern crate std;
+
+use core::sync::atomic::Ordering;
+use std::boxed::Box;
+use std::thread;
+
+#[cfg(feature = "primitives")]
+#[test]
+fn ptr_swap_acq_rel() {
+    use super::AtomicPtr;
+
+    const ROUNDS: usize = 1000;
+    let current = AtomicPtr::new(Box::into_raw(Box::new([0_usize; 4])));
+    thread::scope(|s| {
+        for id in 1..=4 {
+            let current = &current;
+            s.spawn(move || {
+                for i in 0..ROUNDS {
+                    let new = Box::into_raw(Box::new([id * ROUNDS + i; 4]));
+                    // `Release` publishes the initialization of `new`, and
+                    // `Acquire` makes the writes to `old` by the thread that
+                    // published it visible before it is read and freed.
+                    let old = current.swap(new, Ordering::AcqRel);
+                    // SAFETY: `old` came from `Box::into_raw`, and the swap
+                    // made it unreachable by the other threads.
+                    let old = unsafe { Box::from_raw(old) };
+                    assert!(old.iter().all(|&n| n == old[0]));
+                }
+            });
+        }
+    });
+    // SAFETY: The last pointer came from `Box::into_raw`.
+    drop(unsafe { Box::from_raw(current.into_inner()) });
+}
+
+/// Checks that the fallback types are aligned like the built-in atomics, and
+/// that when they consist only of their values, they also have the same size.
+#[cfg(feature = "primitives")]
+#[test]
+fn layout_matches_builtin() {
+    use core::mem::{align_of, size_of};
+
+    fn check<A, B>() {
+        assert!(align_of::<A>() >= align_of::<B>());
+        if cfg!(compact_fallback) {
+            assert_eq!(size_of::<A>(), size_of::<B>());
+        }
+    }
+    check::<super::AtomicPtr<u8>, core::sync::atomic::AtomicPtr<u8>>();
+    // `AtomicU128` is a fallback type on most targets. There is no built-in
+    // atomic to compare it with, but like one, it is aligned to its size.
+    check::<crate::AtomicU128, u128>();
+    assert_eq!(align_of::<crate::AtomicU128>(), 16);
+}
+
+/// Checks that the fallback `compare_exchange` and `fetch_update` succeed,
+/// fail, and panic for the same combinations of orderings as the built-in
+/// atomics.
+#[cfg(feature = "primitives")]
+#[test]
+fn cas_orderings_match_builtin() {
+    use crate::AtomicI128;
+    use core::sync::atomic::AtomicI32;
+    use std::panic::{AssertUnwindSafe as Safe, catch_unwind};
+
+    type R<T> = std::thread::Result<Result<T, T>>;
+
+    fn check(a: R<i32>, b: R<i128>) {
+        match (a, b) {
+            (Ok(a), Ok(b)) => {
+                let a = a.map(i128::from).map_err(i128::from);
+                assert_eq!(a, b);
+            }
+            (Err(_), Err(_)) => {}
+            _ => panic!("only one operation panicked"),
+        }
+    }
+
+    use Ordering::*;
+    let orders = [Relaxed, Release, Acquire, AcqRel, SeqCst];
+    for s in orders {
+        for f in orders {
+            // 1 succeeds; 2 fails.
+            for c in [1, 2] {
+                let (a, b) = (AtomicI32::new(1), AtomicI128::new(1));
+                let cas_a = || a.compare_exchange(c, 3, s, f);
+                let cas_b = || b.compare_exchange(c.into(), 3, s, f);
+                check(catch_unwind(Safe(cas_a)), catch_unwind(Safe(cas_b)));
+
+                let up_a = |v: i32| (c == 1).then(|| v + 2);
+                let up_b = |v: i128| (c == 1).then(|| v + 2);
+                check(
+                    catch_unwind(Safe(|| a.fetch_update(s, f, up_a))),
+                    catch_unwind(Safe(|| b.fetch_update(s, f, up_b))),
+                );
+            }
+        }
+
+        for c in [1, 2] {
+            let (a, b) = (AtomicI32::new(1), AtomicI128::new(1));
+            #[allow(deprecated)]
+            let old_a = catch_unwind(Safe(|| a.compare_and_swap(c, 3, s)));
+            let old_b =
+                catch_unwind(Safe(|| b.compare_and_swap(c.into(), 3, s)));
+            check(old_a.map(Ok), old_b.map(Ok));
+        }
+    }
+}
+
+/// A critical section for the unit tests, which excludes other threads with a
+/// global spinlock and records how each thread acquires and releases it.
+#[cfg(feature = "critical-section")]
+mod critical_section_mock {
+    use super::std;
+    use core::cell::{Cell, RefCell};
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use critical_section::RawRestoreState;
+    use std::vec::Vec;
+
+    struct Mock;
+    critical_section::set_impl!(Mock);
+
+    static LOCKED: AtomicBool = AtomicBool::new(false);
+
+    std::thread_local! {
+        /// The number of critical sections currently held.
+        pub static DEPTH: Cell<u32> = const { Cell::new(0) };
+        pub static ACQUIRED: Cell<usize> = const { Cell::new(0) };
+        /// The restore states passed to `release`, in order.
+        pub static RESTORED: RefCell<Vec<u32>> = const {
+            RefCell::new(Vec::new())
+        };
+    }
+
+    // SAFETY: Only one thread at a time holds `LOCKED`, and all of its
+    // critical sections, however nested, are released before `LOCKED` is.
+    unsafe impl critical_section::Impl for Mock {
+        unsafe fn acquire() -> RawRestoreState {
+            let depth = DEPTH.with(Cell::get);
+            if depth == 0 {
+                while LOCKED
+                    .compare_exchange_weak(
+                        false,
+                        true,
+                        Ordering::Acquire,
+                        Ordering::Relaxed,
+                    )
+                    .is_err()
+                {
+                    std::thread::yield_now();
+                }
+            }
+            DEPTH.with(|d| d.set(depth + 1));
+            ACQUIRED.with(|n| n.set(n.get() + 1));
+            depth
+        }
+
+        unsafe fn release(state: RawRestoreState) {
+            let depth = DEPTH.with(Cell::get) - 1;
+            RESTORED.with(|r| r.borrow_mut().push(state));
+            assert_eq!(state, depth, "critical section released out of order");
+            DEPTH.with(|d| d.set(depth));
+            if depth == 0 {
+                LOCKED.store(false, Ordering::Release);
+            }
+        }
+    }
+}
+
+/// Checks that each operation acquires the critical section once, and that
+/// nested critical sections are released in the reverse order, each with the
+/// restore state returned when it was acquired.
+#[cfg(all(feature = "critical-section", feature = "primitives"))]
+#[cfg(fallback_128)]
+#[test]
+fn critical_section_nesting() {
+    use super::AtomicU128;
+    use critical_section_mock::{ACQUIRED, DEPTH, RESTORED};
+
+    let acquired = || ACQUIRED.with(|n| n.get());
+    let depth = || DEPTH.with(|d| d.get());
+    let restored = || RESTORED.with(|r| r.take());
+
+    let start = acquired();
+    restored();
+    let (a, b) = (AtomicU128::new(1), AtomicU128::new(5));
+    a.fetch_add(1, Ordering::Relaxed);
+    assert_eq!(acquired() - start, 1);
+    assert_eq!(restored(), [0]);
+
+    a.with_lock(Ordering::AcqRel, |x| {
+        assert_eq!(depth(), 1);
+        b.with_lock(Ordering::AcqRel, |y| {
+            assert_eq!(depth(), 2);
+            let (vx, vy) = (x.snapshot(), y.snapshot());
+            x.set(vy);
+            y.set(vx);
+        });
+        assert_eq!(depth(), 1);
+    });
+    assert_eq!(depth(), 0);
+    assert_eq!(acquired() - start, 3);
+    assert_eq!(restored(), [1, 0]);
+    assert_eq!(a.load(Ordering::Relaxed), 5);
+    assert_eq!(b.load(Ordering::Relaxed), 2);
+}
+
+/// Checks that installed lock hooks are called once per operation, and not
+/// at all once removed.
+#[cfg(all(feature = "hooks", feature = "primitives"))]
+#[cfg(fallback_128)]
+#[test]
+fn lock_hooks_count_acquisitions() {
+    use super::{AtomicU128, LockHooks, set_lock_hooks};
+    use core::sync::atomic::AtomicUsize;
+
+    // Other tests may run concurrently, so only calls for the atomic below
+    // are counted.
+    static TARGET: AtomicUsize = AtomicUsize::new(0);
+    static BEFORE: AtomicUsize = AtomicUsize::new(0);
+    static AFTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn count(counter: &AtomicUsize, addr: *const ()) {
+        if addr as usize == TARGET.load(Ordering::Relaxed) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    static HOOKS: LockHooks = LockHooks {
+        before_acquire: |addr| count(&BEFORE, addr),
+        after_acquire: |addr| count(&AFTER, addr),
+    };
+
+    const N: usize = 100;
+    let a = AtomicU128::new(0);
+    TARGET.store(&a as *const _ as usize, Ordering::Relaxed);
+    set_lock_hooks(Some(&HOOKS));
+    for _ in 0..N {
+        a.fetch_add(1, Ordering::Relaxed);
+    }
+    assert_eq!(BEFORE.load(Ordering::Relaxed), N);
+    assert_eq!(AFTER.load(Ordering::Relaxed), N);
+
+    set_lock_hooks(None);
+    for _ in 0..N {
+        a.fetch_add(1, Ordering::Relaxed);
+    }
+    assert_eq!(BEFORE.load(Ordering::Relaxed), N);
+    assert_eq!(AFTER.load(Ordering::Relaxed), N);
+    assert_eq!(a.into_inner(), 2 * N as u128);
+}
+
+/// Checks that operations on adjacent atomics, many of which share stripes
+/// with each other, stay atomic under contention.
+#[cfg(all(feature = "striped", feature = "primitives"))]
+#[test]
+fn striped_stress() {
+    use super::AtomicU128;
+
+    const THREADS: u128 = 4;
+    const ROUNDS: u128 = 1000;
+    let atomics: [AtomicU128; 16] = Default::default();
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| {
+                for _ in 0..ROUNDS {
+                    for (i, a) in atomics.iter().enumerate() {
+                        let i = i as u128;
+                        a.fetch_add(1 | i << 64, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+    for (i, a) in atomics.iter().enumerate() {
+        let n = THREADS * ROUNDS;
+        assert_eq!(a.load(Ordering::Relaxed), n | (n * i as u128) << 64);
+    }
+}
+
+/// Checks that with `conservative-ordering`, the lock is acquired and
+/// released with [`SeqCst`](Ordering::SeqCst) for every ordering.
+#[cfg(feature = "conservative-ordering")]
+#[test]
+fn conservative_lock_orderings() {
+    use super::{acquire_order, release_order};
+    use Ordering::*;
+
+    for order in [Relaxed, Release, Acquire, AcqRel, SeqCst] {
+        assert_eq!(acquire_order(order), SeqCst);
+        assert_eq!(release_order(order), SeqCst);
+    }
+}
+
+/// Checks that optimistic loads never return a value torn by a concurrent
+/// store: each stored value has equal halves.
+#[cfg(all(seqlock, feature = "primitives"))]
+#[test]
+fn seqlock_loads_are_not_torn() {
+    use super::AtomicU128;
+    use core::sync::atomic::AtomicBool;
+
+    const ROUNDS: u128 = 100_000;
+    let a = AtomicU128::new(0);
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let v = a.load(Ordering::Acquire);
+                    assert_eq!(v as u64, (v >> 64) as u64);
+                }
+            });
+        }
+        let writers: std::vec::Vec<_> = (0..2)
+            .map(|_| {
+                s.spawn(|| {
+                    for x in 0..ROUNDS {
+                        a.store(x | x << 64, Ordering::Release);
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+}
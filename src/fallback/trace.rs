@@ -0,0 +1,323 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tracing of read-modify-write operations on the fallback atomics.
+//!
+//! Entries are stored in a global ring buffer of [`CAPACITY`] slots. Each
+//! slot is protected by a sequence number, as in a seqlock, and every field
+//! is stored in atomic words, so recording and draining never block each
+//! other and never race.
+//!
+//! Recording is async-signal-safe, so operations performed by signal handlers
+//! (e.g., with the feature `signal`) are traced too: besides atomic
+//! operations, it only reads and writes a `const`-initialized thread-local
+//! without a destructor, which needs no lazy initialization or allocation.
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering, fence};
+use std::vec::Vec;
+
+/// The maximum number of entries held by the trace buffer.
+const CAPACITY: usize = 1024;
+
+/// The number of 32-bit words needed to store a [`TraceEntry`].
+const WORDS: usize = 13;
+
+/// A kind of traced operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TraceOp {
+    /// `swap`.
+    Swap,
+    /// `compare_exchange` or `compare_exchange_weak`.
+    CompareExchange,
+    /// `fetch_update`.
+    FetchUpdate,
+    /// `fetch_add`.
+    FetchAdd,
+    /// `fetch_sub`.
+    FetchSub,
+    /// `fetch_and`.
+    FetchAnd,
+    /// `fetch_nand`.
+    FetchNand,
+    /// `fetch_or`.
+    FetchOr,
+    /// `fetch_xor`.
+    FetchXor,
+    /// `fetch_max`.
+    FetchMax,
+    /// `fetch_min`.
+    FetchMin,
+}
+
+const OPS: [TraceOp; 11] = [
+    TraceOp::Swap,
+    TraceOp::CompareExchange,
+    TraceOp::FetchUpdate,
+    TraceOp::FetchAdd,
+    TraceOp::FetchSub,
+    TraceOp::FetchAnd,
+    TraceOp::FetchNand,
+    TraceOp::FetchOr,
+    TraceOp::FetchXor,
+    TraceOp::FetchMax,
+    TraceOp::FetchMin,
+];
+
+/// A read-modify-write operation recorded by the `trace` feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct TraceEntry {
+    /// The kind of operation.
+    pub op: TraceOp,
+    /// The address of the atomic.
+    pub addr: usize,
+    /// The operation’s argument. For `compare_exchange` and `fetch_update`,
+    /// this is the new value that was (or would have been) stored.
+    pub arg: u128,
+    /// The value of the atomic before the operation.
+    pub prev: u128,
+    /// An identifier for the thread that performed the operation, unique
+    /// among all threads that have recorded entries, or [`usize::MAX`] if the
+    /// thread’s thread-local storage was unavailable (e.g., because the
+    /// operation was performed by a thread-local destructor).
+    pub thread: usize,
+}
+
+/// A value that can be recorded in a [`TraceEntry`].
+pub trait TraceBits: Copy {
+    /// Converts the value to a `u128`. Signed integers are sign-extended.
+    fn to_bits(self) -> u128;
+}
+
+macro_rules! impl_trace_bits {
+    ($($int:ident),*) => {$(
+        impl TraceBits for $int {
+            fn to_bits(self) -> u128 {
+                self as u128
+            }
+        }
+    )*};
+}
+
+impl_trace_bits!(
+    i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize
+);
+
+/// Records the low 128 bits.
+#[cfg(feature = "wide")]
+impl TraceBits for crate::U256 {
+    fn to_bits(self) -> u128 {
+        self.to_words().1
+    }
+}
+
+/// Records the low 128 bits.
+#[cfg(feature = "wide")]
+impl TraceBits for crate::I256 {
+    fn to_bits(self) -> u128 {
+        self.to_words().1
+    }
+}
+
+impl<T> TraceBits for *mut T {
+    fn to_bits(self) -> u128 {
+        self as usize as u128
+    }
+}
+
+struct Slot {
+    /// 0 if the slot has never been written; `2 * index + 1` while entry
+    /// `index` is being written; `2 * index + 2` once it has been written.
+    seq: AtomicUsize,
+    words: [AtomicU32; WORDS],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_WORD: AtomicU32 = AtomicU32::new(0);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: Slot = Slot {
+    seq: AtomicUsize::new(0),
+    words: [EMPTY_WORD; WORDS],
+};
+
+static SLOTS: [Slot; CAPACITY] = [EMPTY_SLOT; CAPACITY];
+
+/// The index of the next entry to be recorded.
+static HEAD: AtomicUsize = AtomicUsize::new(0);
+
+/// The index of the first entry that has not been drained.
+static TAIL: AtomicUsize = AtomicUsize::new(0);
+
+/// The thread identifier recorded when thread-local storage is unavailable.
+const UNKNOWN_THREAD: usize = usize::MAX;
+
+/// Returns the identifier of the current thread, assigning one if needed.
+///
+/// The thread-local is `const`-initialized, so accessing it doesn't run a
+/// lazy initializer, which wouldn't be async-signal-safe. A signal handler
+/// that interrupts the assignment of a thread's identifier may cause entries
+/// from the same thread to be recorded with two different identifiers.
+fn thread_id() -> usize {
+    // Identifiers start at 1; 0 means none has been assigned yet.
+    static NEXT: AtomicUsize = AtomicUsize::new(1);
+    std::thread_local! {
+        static ID: Cell<usize> = const { Cell::new(0) };
+    }
+    ID.try_with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+    .unwrap_or(UNKNOWN_THREAD)
+}
+
+fn encode(entry: &TraceEntry) -> [u32; WORDS] {
+    let addr = entry.addr as u64;
+    let thread = entry.thread as u64;
+    let mut words = [0; WORDS];
+    words[0] = entry.op as u32;
+    words[1] = addr as u32;
+    words[2] = (addr >> 32) as u32;
+    words[3] = thread as u32;
+    words[4] = (thread >> 32) as u32;
+    for i in 0..4 {
+        words[5 + i] = (entry.arg >> (32 * i)) as u32;
+        words[9 + i] = (entry.prev >> (32 * i)) as u32;
+    }
+    words
+}
+
+fn decode(words: &[u32; WORDS]) -> TraceEntry {
+    let join = |words: &[u32]| {
+        words.iter().rev().fold(0_u128, |n, &w| (n << 32) | u128::from(w))
+    };
+    TraceEntry {
+        op: OPS[words[0] as usize],
+        addr: join(&words[1..3]) as usize,
+        thread: join(&words[3..5]) as usize,
+        arg: join(&words[5..9]),
+        prev: join(&words[9..13]),
+    }
+}
+
+/// Records an operation on the atomic at `addr`.
+///
+/// If the buffer has wrapped around and another thread is still writing to
+/// the slot this entry would occupy, the entry is dropped.
+pub fn record<T: TraceBits>(op: TraceOp, addr: *const (), arg: T, prev: T) {
+    let words = encode(&TraceEntry {
+        op,
+        addr: addr as usize,
+        arg: arg.to_bits(),
+        prev: prev.to_bits(),
+        thread: thread_id(),
+    });
+    let index = HEAD.fetch_add(1, Ordering::Relaxed);
+    let slot = &SLOTS[index % CAPACITY];
+    let seq = slot.seq.load(Ordering::Relaxed);
+    if seq % 2 == 1
+        || slot
+            .seq
+            .compare_exchange(
+                seq,
+                index.wrapping_mul(2).wrapping_add(1),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            )
+            .is_err()
+    {
+        return;
+    }
+    fence(Ordering::Release);
+    for (word, value) in slot.words.iter().zip(words) {
+        word.store(value, Ordering::Relaxed);
+    }
+    slot.seq.store(index.wrapping_mul(2).wrapping_add(2), Ordering::Release);
+}
+
+/// Reads entry `index`, if it is still present in the buffer.
+fn read(index: usize) -> Option<TraceEntry> {
+    let slot = &SLOTS[index % CAPACITY];
+    let expected = index.wrapping_mul(2).wrapping_add(2);
+    if slot.seq.load(Ordering::Acquire) != expected {
+        return None;
+    }
+    let mut words = [0; WORDS];
+    for (value, word) in words.iter_mut().zip(&slot.words) {
+        *value = word.load(Ordering::Relaxed);
+    }
+    fence(Ordering::Acquire);
+    if slot.seq.load(Ordering::Relaxed) != expected {
+        return None;
+    }
+    Some(decode(&words))
+}
+
+/// Removes and returns all entries in the trace buffer, in the order in which
+/// they were recorded.
+///
+/// The buffer holds at most the 1024 most recent entries; older entries are
+/// overwritten. Entries that are still being recorded when this function is
+/// called are skipped and will not be returned by later calls.
+#[cfg_attr(fallback_128, doc = "```")]
+#[cfg_attr(not(fallback_128), doc = "```ignore")]
+/// use atomic_int::{AtomicU128, TraceEntry, TraceOp, drain_trace};
+/// use std::sync::atomic::Ordering::{AcqRel, Relaxed};
+/// use std::thread;
+///
+/// // `AtomicU128` is a fallback type on most targets.
+/// let a = AtomicU128::new(1);
+/// a.fetch_add(2, Relaxed);
+/// a.swap(10, Relaxed);
+/// let _ = a.compare_exchange(10, 20, AcqRel, Relaxed);
+/// thread::spawn(|| AtomicU128::new(0).fetch_max(5, Relaxed)).join().unwrap();
+/// a.fetch_max(5, Relaxed);
+///
+/// let addr = &a as *const AtomicU128 as usize;
+/// let entries: Vec<TraceEntry> = drain_trace();
+/// let ops: Vec<_> = entries
+///     .iter()
+///     .filter(|e| e.addr == addr)
+///     .map(|e| (e.op, e.arg, e.prev))
+///     .collect();
+/// assert_eq!(ops, [
+///     (TraceOp::FetchAdd, 2, 1),
+///     (TraceOp::Swap, 10, 3),
+///     (TraceOp::CompareExchange, 20, 10),
+///     (TraceOp::FetchMax, 5, 20),
+/// ]);
+///
+/// // The other thread's entry has a different identifier.
+/// assert_eq!(entries.len(), 5);
+/// assert!(entries[..3].iter().all(|e| e.thread == entries[0].thread));
+/// assert_ne!(entries[3].thread, entries[0].thread);
+/// assert_eq!(entries[4].thread, entries[0].thread);
+///
+/// // Drained entries are not returned again.
+/// assert!(drain_trace().is_empty());
+/// ```
+pub fn drain_trace() -> Vec<TraceEntry> {
+    let head = HEAD.load(Ordering::Relaxed);
+    let tail = TAIL.fetch_max(head, Ordering::Relaxed);
+    let start = tail.max(head.saturating_sub(CAPACITY));
+    (start..head).filter_map(read).collect()
+}
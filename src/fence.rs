@@ -0,0 +1,83 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use core::sync::atomic::{self, Ordering};
+
+/// An atomic fence; forwards to [`core::sync::atomic::fence`].
+///
+/// Fences can be placed around operations on this crate’s atomics exactly as
+/// they would be around the built-in atomics, whether a given type is
+/// built-in or a fallback. Every operation on a fallback type acquires and
+/// releases its lock with at least the orderings that a fence-based pattern
+/// relies on from the operation itself, so, e.g., a [`Release`] fence
+/// followed by a [`Relaxed`] store to a fallback atomic synchronizes with an
+/// [`Acquire`] fence that follows a [`Relaxed`] load of the stored value,
+/// including when the other atomics involved are built-in.
+///
+/// A [`SeqCst`] fence also orders fallback operations with respect to the
+/// single total order of [`SeqCst`] operations: the lock of a fallback type
+/// is itself an atomic, and an operation that reads a value written before a
+/// preceding [`SeqCst`] fence (in that order) must acquire the lock after the
+/// writer released it. However, between two operations on fallback types
+/// alone, a fence adds nothing, as the locks already order those operations.
+///
+/// ```
+/// use atomic_int::{AtomicU128, AtomicUsize, fence};
+/// use std::sync::atomic::Ordering;
+///
+/// static DATA: AtomicUsize = AtomicUsize::new(0);
+/// // Likely a fallback type.
+/// static READY: AtomicU128 = AtomicU128::new(0);
+///
+/// std::thread::scope(|s| {
+///     s.spawn(|| {
+///         DATA.store(42, Ordering::Relaxed);
+///         fence(Ordering::Release);
+///         READY.store(1, Ordering::Relaxed);
+///     });
+///     s.spawn(|| {
+///         if READY.load(Ordering::Relaxed) == 1 {
+///             fence(Ordering::Acquire);
+///             assert_eq!(DATA.load(Ordering::Relaxed), 42);
+///         }
+///     });
+/// });
+/// ```
+///
+/// [`Relaxed`]: Ordering::Relaxed
+/// [`Release`]: Ordering::Release
+/// [`Acquire`]: Ordering::Acquire
+/// [`SeqCst`]: Ordering::SeqCst
+#[inline]
+pub fn fence(order: Ordering) {
+    atomic::fence(order);
+}
+
+/// A compiler memory fence; forwards to
+/// [`core::sync::atomic::compiler_fence`].
+///
+/// This prevents only the compiler from reordering memory accesses across
+/// it, which is useful for synchronizing with signal or interrupt handlers
+/// running on the same thread. Operations on fallback types already act as
+/// compiler fences, as they call into the lock, but a fallback type
+/// shouldn’t be used from a signal handler unless the feature `signal` (or
+/// `critical-section`) is enabled.
+#[inline]
+pub fn compiler_fence(order: Ordering) {
+    atomic::compiler_fence(order);
+}
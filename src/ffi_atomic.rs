@@ -0,0 +1,121 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::AtomicIntExt;
+use super::detail::HasNativeAtomic;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+/// An atomic integer with a layout suitable for use in FFI structs.
+///
+/// `FfiAtomic<T>` is `#[repr(transparent)]` over the built-in atomic for the
+/// integer `T`, and thus has the same size as `T` and the alignment of the
+/// built-in atomic. This makes it suitable for fields that C code declares as,
+/// e.g., `_Atomic(int)`.
+///
+/// Because this layout cannot be provided by the spinlock-based fallback, this
+/// type is available only for integers that have a built-in atomic on the
+/// current target; using it with any other integer type results in a compile
+/// error. All atomic operations are available through [`Deref`].
+///
+/// ```
+/// use atomic_int::FfiAtomic;
+/// use std::mem::{align_of, size_of};
+/// use std::os::raw::c_int;
+/// use std::sync::atomic::Ordering;
+///
+/// // Corresponds to `struct shared { _Atomic(int) flags; unsigned len; }`.
+/// #[repr(C)]
+/// struct Shared {
+///     flags: FfiAtomic<c_int>,
+///     len: u32,
+/// }
+///
+/// assert_eq!(size_of::<FfiAtomic<c_int>>(), size_of::<c_int>());
+/// assert!(align_of::<FfiAtomic<c_int>>() >= align_of::<c_int>());
+/// let shared = Shared {
+///     flags: FfiAtomic::new(0),
+///     len: 0,
+/// };
+/// shared.flags.fetch_or(0b10, Ordering::Release);
+/// assert_eq!(shared.flags.load(Ordering::Acquire), 0b10);
+/// # let _ = shared.len;
+/// ```
+#[repr(transparent)]
+pub struct FfiAtomic<T: HasNativeAtomic>(T::Atomic);
+
+impl<T: HasNativeAtomic> FfiAtomic<T> {
+    const LAYOUT_CHECK: () = assert!(
+        core::mem::size_of::<T::Atomic>() == core::mem::size_of::<T>()
+    );
+
+    /// Creates a new atomic.
+    ///
+    /// ```
+    /// use atomic_int::FfiAtomic;
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let a = FfiAtomic::new(7_u32);
+    /// assert_eq!(a.swap(8, Ordering::Relaxed), 7);
+    /// ```
+    pub fn new(v: T) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::LAYOUT_CHECK;
+        Self(T::Atomic::new(v))
+    }
+
+    /// Consumes the atomic and returns the contained value.
+    ///
+    /// ```
+    /// use atomic_int::FfiAtomic;
+    /// use std::sync::atomic::Ordering;
+    ///
+    /// let a = FfiAtomic::new(1_u16);
+    /// a.fetch_add(2, Ordering::Relaxed);
+    /// assert_eq!(a.into_inner(), 3);
+    /// ```
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T: HasNativeAtomic> Deref for FfiAtomic<T> {
+    type Target = T::Atomic;
+
+    fn deref(&self) -> &T::Atomic {
+        &self.0
+    }
+}
+
+impl<T: HasNativeAtomic> DerefMut for FfiAtomic<T> {
+    fn deref_mut(&mut self) -> &mut T::Atomic {
+        &mut self.0
+    }
+}
+
+impl<T: HasNativeAtomic> fmt::Debug for FfiAtomic<T>
+where
+    T::Atomic: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// SAFETY: The type contains only a built-in integer atomic.
+impl_zeroable!([T: HasNativeAtomic] FfiAtomic<T>);
@@ -0,0 +1,109 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A boolean flag that is always lock-free, like C++’s `std::atomic_flag`.
+///
+/// This type is a thin wrapper around the built-in
+/// [`AtomicBool`](core::sync::atomic::AtomicBool), so unlike the other types
+/// in this crate, it never uses the fallback implementation. It is therefore
+/// unavailable on targets without atomic read-modify-write operations on
+/// `AtomicBool`, like `thumbv6m` and `riscv32i`. It supports only the
+/// operations of `std::atomic_flag`, which makes it convenient when porting
+/// C++ code.
+///
+/// ```
+/// use atomic_int::AtomicFlag;
+/// use std::sync::atomic::Ordering;
+///
+/// // A minimal spinlock.
+/// static LOCKED: AtomicFlag = AtomicFlag::new();
+///
+/// while LOCKED.test_and_set(Ordering::Acquire) {
+///     std::hint::spin_loop();
+/// }
+/// assert!(LOCKED.test(Ordering::Relaxed));
+/// LOCKED.clear(Ordering::Release);
+/// assert!(!LOCKED.test(Ordering::Relaxed));
+/// ```
+#[repr(transparent)]
+pub struct AtomicFlag {
+    flag: AtomicBool,
+}
+
+impl AtomicFlag {
+    /// Whether this type is always lock-free, which is always the case.
+    pub const IS_ALWAYS_LOCK_FREE: bool = true;
+
+    /// Creates a new flag, which is initially clear.
+    pub const fn new() -> Self {
+        Self {
+            flag: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets the flag, returning whether it was already set.
+    ///
+    /// Corresponds to `std::atomic_flag::test_and_set`.
+    pub fn test_and_set(&self, order: Ordering) -> bool {
+        self.flag.swap(true, order)
+    }
+
+    /// Clears the flag.
+    ///
+    /// Corresponds to `std::atomic_flag::clear`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is [`Acquire`](Ordering::Acquire) or
+    /// [`AcqRel`](Ordering::AcqRel), like [`AtomicBool::store`].
+    pub fn clear(&self, order: Ordering) {
+        self.flag.store(false, order);
+    }
+
+    /// Returns whether the flag is set, without modifying it.
+    ///
+    /// Corresponds to `std::atomic_flag::test`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is [`Release`](Ordering::Release) or
+    /// [`AcqRel`](Ordering::AcqRel), like [`AtomicBool::load`].
+    pub fn test(&self, order: Ordering) -> bool {
+        self.flag.load(order)
+    }
+}
+
+impl Default for AtomicFlag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AtomicFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicFlag")
+            .field(&self.test(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+// SAFETY: Zero is `false`.
+impl_zeroable!([] AtomicFlag);
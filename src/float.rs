@@ -0,0 +1,309 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::{AtomicU32, AtomicU64};
+use crate::ext::load_order;
+use core::fmt;
+use core::mem;
+use core::sync::atomic::Ordering;
+
+macro_rules! define_float {
+    ($atomic:ident, $float:ident, $bits:ident, $int:ident) => {
+        /// An atomic
+        #[doc = concat!("[`", stringify!($float), "`],")]
+        /// stored as its bit representation in an
+        #[doc = concat!("[`", stringify!($bits), "`].")]
+        ///
+        /// # Comparisons
+        ///
+        /// [`compare_exchange`](Self::compare_exchange) and
+        /// [`compare_exchange_weak`](Self::compare_exchange_weak) compare
+        /// bit patterns, not values. This means that `0.0` and `-0.0` do
+        /// not match each other, even though they are equal according to
+        /// `==`, and that a NaN matches only a NaN with exactly the same
+        /// encoding, even though no NaN is equal to anything according to
+        /// `==`:
+        ///
+        /// ```
+        #[doc = concat!("use atomic_int::", stringify!($atomic), ";")]
+        /// use std::sync::atomic::Ordering::SeqCst;
+        ///
+        #[doc = concat!("let a = ", stringify!($atomic), "::new(-0.0);")]
+        /// assert!(a.compare_exchange(0.0, 1.0, SeqCst, SeqCst).is_err());
+        ///
+        #[doc = concat!("let nan = ", stringify!($float), "::NAN;")]
+        /// a.store(nan, SeqCst);
+        /// let prev = a.compare_exchange(nan, 1.0, SeqCst, SeqCst);
+        /// assert_eq!(prev.unwrap().to_bits(), nan.to_bits());
+        /// ```
+        ///
+        /// To compare with `==` instead, use
+        /// [`compare_exchange_value`](Self::compare_exchange_value).
+        #[repr(transparent)]
+        pub struct $atomic {
+            bits: $bits,
+        }
+
+        impl $atomic {
+            /// Creates a new atomic float.
+            // `to_bits` is not `const` in this crate's minimum supported Rust
+            // version.
+            #[allow(unknown_lints, unnecessary_transmutes)]
+            pub const fn new(v: $float) -> Self {
+                // SAFETY: Every float is a valid integer of the same size.
+                let bits = unsafe { mem::transmute::<$float, $int>(v) };
+                Self {
+                    bits: $bits::new(bits),
+                }
+            }
+
+            /// Returns a mutable reference to the underlying value.
+            pub fn get_mut(&mut self) -> &mut $float {
+                let bits: *mut $int = self.bits.get_mut();
+                // SAFETY: The float and integer types have the same size and
+                // alignment, and every integer is a valid float.
+                unsafe { &mut *bits.cast() }
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            pub fn into_inner(self) -> $float {
+                $float::from_bits(self.bits.into_inner())
+            }
+
+            /// Loads the value.
+            pub fn load(&self, order: Ordering) -> $float {
+                $float::from_bits(self.bits.load(order))
+            }
+
+            /// Stores a value.
+            pub fn store(&self, val: $float, order: Ordering) {
+                self.bits.store(val.to_bits(), order);
+            }
+
+            /// Stores a value, returning the previous value.
+            pub fn swap(&self, val: $float, order: Ordering) -> $float {
+                $float::from_bits(self.bits.swap(val.to_bits(), order))
+            }
+
+            /// Stores `new` if the current value has the same bit pattern as
+            /// `current`.
+            ///
+            /// See the [type-level documentation](Self#comparisons) for how
+            /// this differs from comparing with `==`.
+            pub fn compare_exchange(
+                &self,
+                current: $float,
+                new: $float,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$float, $float> {
+                self.bits
+                    .compare_exchange(
+                        current.to_bits(),
+                        new.to_bits(),
+                        success,
+                        failure,
+                    )
+                    .map($float::from_bits)
+                    .map_err($float::from_bits)
+            }
+
+            /// Like [`Self::compare_exchange`], but may fail spuriously.
+            pub fn compare_exchange_weak(
+                &self,
+                current: $float,
+                new: $float,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$float, $float> {
+                self.bits
+                    .compare_exchange_weak(
+                        current.to_bits(),
+                        new.to_bits(),
+                        success,
+                        failure,
+                    )
+                    .map($float::from_bits)
+                    .map_err($float::from_bits)
+            }
+
+            /// Stores `new` if the current value is equal to `current`
+            /// according to `==`.
+            ///
+            /// Unlike [`Self::compare_exchange`], `0.0` and `-0.0` match
+            /// each other, and a NaN `current` never matches, as NaN is not
+            /// equal to any value. The previous value is returned in either
+            /// case; if it is `Ok`, it may differ in bit pattern from
+            /// `current`.
+            ///
+            /// ```
+            #[doc = concat!("use atomic_int::", stringify!($atomic), ";")]
+            /// use std::sync::atomic::Ordering::SeqCst;
+            ///
+            #[doc = concat!("let a = ", stringify!($atomic), "::new(-0.0);")]
+            /// let prev = a.compare_exchange_value(0.0, 1.0, SeqCst, SeqCst);
+            /// assert!(prev.unwrap().is_sign_negative());
+            /// assert_eq!(a.load(SeqCst), 1.0);
+            /// ```
+            pub fn compare_exchange_value(
+                &self,
+                current: $float,
+                new: $float,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<$float, $float> {
+                let new = new.to_bits();
+                let mut bits = self.bits.load(failure);
+                loop {
+                    if $float::from_bits(bits) != current {
+                        return Err($float::from_bits(bits));
+                    }
+                    match self
+                        .bits
+                        .compare_exchange_weak(bits, new, success, failure)
+                    {
+                        Ok(prev) => return Ok($float::from_bits(prev)),
+                        Err(prev) => bits = prev,
+                    }
+                }
+            }
+
+            /// Fetches the value, and applies a function to it that returns
+            /// an optional new value.
+            #[doc = concat!(
+                "\n\nSee [`",
+                stringify!($bits),
+                "::fetch_update`].",
+            )]
+            pub fn fetch_update<F>(
+                &self,
+                set_order: Ordering,
+                fetch_order: Ordering,
+                mut f: F,
+            ) -> Result<$float, $float>
+            where
+                F: FnMut($float) -> Option<$float>,
+            {
+                self.bits
+                    .fetch_update(set_order, fetch_order, |bits| {
+                        f($float::from_bits(bits)).map($float::to_bits)
+                    })
+                    .map($float::from_bits)
+                    .map_err($float::from_bits)
+            }
+
+            fn fetch_map<F>(&self, order: Ordering, mut f: F) -> $float
+            where
+                F: FnMut($float) -> $float,
+            {
+                let result = self.fetch_update(order, load_order(order), |v| {
+                    Some(f(v))
+                });
+                result.unwrap_or_else(|v| v)
+            }
+
+            /// Adds to the current value, returning the previous value.
+            ///
+            /// This is performed with a compare-exchange loop, and, like
+            /// [`Self::fetch_update`], uses the load ordering corresponding
+            /// to `order` for the loads in that loop.
+            ///
+            /// ```
+            #[doc = concat!("use atomic_int::", stringify!($atomic), ";")]
+            /// use std::sync::atomic::Ordering::Relaxed;
+            ///
+            #[doc = concat!("let a = ", stringify!($atomic), "::new(1.5);")]
+            /// assert_eq!(a.fetch_add(2.0, Relaxed), 1.5);
+            /// assert_eq!(a.fetch_sub(0.5, Relaxed), 3.5);
+            /// assert_eq!(a.fetch_max(4.0, Relaxed), 3.0);
+            /// assert_eq!(a.fetch_min(-1.0, Relaxed), 4.0);
+            /// assert_eq!(a.load(Relaxed), -1.0);
+            /// ```
+            pub fn fetch_add(&self, val: $float, order: Ordering) -> $float {
+                self.fetch_map(order, |v| v + val)
+            }
+
+            /// Subtracts from the current value, returning the previous
+            /// value.
+            ///
+            /// See [`Self::fetch_add`].
+            pub fn fetch_sub(&self, val: $float, order: Ordering) -> $float {
+                self.fetch_map(order, |v| v - val)
+            }
+
+            /// Stores the maximum of the current value and `val`, returning
+            /// the previous value.
+            ///
+            #[doc = concat!(
+                "The maximum is computed with [`",
+                stringify!($float),
+                "::max`], so if one of the values is NaN, the other is stored."
+            )]
+            /// See also [`Self::fetch_add`].
+            pub fn fetch_max(&self, val: $float, order: Ordering) -> $float {
+                self.fetch_map(order, |v| v.max(val))
+            }
+
+            /// Stores the minimum of the current value and `val`, returning
+            /// the previous value.
+            ///
+            #[doc = concat!(
+                "The minimum is computed with [`",
+                stringify!($float),
+                "::min`], so if one of the values is NaN, the other is stored."
+            )]
+            /// See also [`Self::fetch_add`].
+            pub fn fetch_min(&self, val: $float, order: Ordering) -> $float {
+                self.fetch_map(order, |v| v.min(val))
+            }
+        }
+
+        impl Default for $atomic {
+            fn default() -> Self {
+                Self::new(0.0)
+            }
+        }
+
+        impl From<$float> for $atomic {
+            fn from(v: $float) -> Self {
+                Self::new(v)
+            }
+        }
+
+        impl crate::ext::sealed::Sealed for $atomic {}
+
+        impl_any_atomic!(
+            [] $atomic,
+            $float,
+            <$bits as crate::AnyAtomic>::IS_ALWAYS_LOCK_FREE
+        );
+
+        impl_serde!([] $atomic, $float);
+        // SAFETY: Zero is `0.0`.
+        impl_zeroable!([] $atomic);
+
+        impl fmt::Debug for $atomic {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.load(Ordering::SeqCst).fmt(f)
+            }
+        }
+    };
+}
+
+define_float!(AtomicF32, f32, AtomicU32, u32);
+define_float!(AtomicF64, f64, AtomicU64, u64);
@@ -0,0 +1,389 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::fallback::{cas_order, with_stripe};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem;
+use core::ptr;
+#[allow(unused_imports)]
+use core::sync::atomic::{self, Ordering};
+
+/// A [`Copy`] type with no padding or otherwise uninitialized bytes, which
+/// can therefore be stored in an [`Atomic`].
+///
+/// This is implemented for the primitive integers, floats, [`bool`],
+/// [`char`], raw pointers, and arrays of [`Plain`] types.
+///
+/// # Safety
+///
+/// Every byte of every value of the type must be initialized. For example,
+/// a fieldless enum with a primitive representation (like `#[repr(u8)]`)
+/// satisfies this, but a struct whose fields leave padding between them
+/// does not.
+///
+/// ```
+/// use atomic_int::{Atomic, Plain};
+/// use std::sync::atomic::Ordering;
+///
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// #[repr(u8)]
+/// enum State {
+///     Idle,
+///     Running,
+/// }
+///
+/// // SAFETY: `State` is a fieldless `#[repr(u8)]` enum.
+/// unsafe impl Plain for State {}
+///
+/// let state = Atomic::new(State::Idle);
+/// assert!(Atomic::<State>::is_lock_free());
+/// let prev = state.compare_exchange(
+///     State::Idle,
+///     State::Running,
+///     Ordering::AcqRel,
+///     Ordering::Acquire,
+/// );
+/// assert_eq!(prev, Ok(State::Idle));
+/// assert_eq!(state.load(Ordering::Acquire), State::Running);
+/// ```
+pub unsafe trait Plain: Copy + 'static {}
+
+macro_rules! impl_plain {
+    ($($type:ty),*) => {
+        $(unsafe impl Plain for $type {})*
+    };
+}
+
+impl_plain!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize);
+impl_plain!(f32, f64, bool, char);
+
+unsafe impl<T: 'static> Plain for *const T {}
+unsafe impl<T: 'static> Plain for *mut T {}
+unsafe impl<T: Plain, const N: usize> Plain for [T; N] {}
+
+/// How an [`Atomic`] accesses its value.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Width {
+    W8,
+    W16,
+    W32,
+    W64,
+    Locked,
+}
+
+impl Width {
+    /// Returns the built-in atomic that can hold a `T`, if any. This needs
+    /// the same size as `T`, and an alignment no greater than `T`’s.
+    const fn of<T>() -> Self {
+        const fn fits<T>(size: usize) -> bool {
+            mem::size_of::<T>() == size && mem::align_of::<T>() >= size
+        }
+        if cfg!(target_has_atomic = "8") && fits::<T>(1) {
+            Self::W8
+        } else if cfg!(target_has_atomic = "16") && fits::<T>(2) {
+            Self::W16
+        } else if cfg!(target_has_atomic = "32") && fits::<T>(4) {
+            Self::W32
+        } else if cfg!(target_has_atomic = "64") && fits::<T>(8) {
+            Self::W64
+        } else {
+            Self::Locked
+        }
+    }
+}
+
+/// An atomic version of any [`Plain`] type, like `AtomicCell` in crossbeam.
+///
+/// When a built-in atomic integer has the same size as `T` and an alignment
+/// that `T` satisfies, the value is accessed through that atomic. Otherwise,
+/// each operation locks a spinlock chosen by hashing the atomic’s address
+/// from a global table (the same one used with the feature `striped`), so
+/// this type never contains a lock of its own and is `repr(transparent)`
+/// over `T`.
+///
+/// Like the built-in atomics, [`Self::compare_exchange`] compares bit
+/// patterns, not values according to [`PartialEq`].
+///
+/// ```
+/// use atomic_int::Atomic;
+/// use std::sync::atomic::Ordering;
+///
+/// // Too large for a built-in atomic, so this uses a lock.
+/// let pair = Atomic::new([1_u64, 2, 3]);
+/// assert!(!Atomic::<[u64; 3]>::is_lock_free());
+/// pair.fetch_update(Ordering::AcqRel, Ordering::Acquire, |[a, b, c]| {
+///     Some([b, c, a])
+/// })
+/// .unwrap();
+/// assert_eq!(pair.load(Ordering::Acquire), [2, 3, 1]);
+/// ```
+#[repr(transparent)]
+pub struct Atomic<T> {
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: Every access to the value is atomic, either through a built-in
+// atomic or while holding a lock.
+unsafe impl<T: Send> Sync for Atomic<T> {}
+
+/// Calls `$native` with `$atomic` bound to a reference to the value as a
+/// built-in atomic, and `$int` to its integer type, or evaluates `$locked`
+/// if there is no such atomic.
+macro_rules! dispatch {
+    (
+        $self:ident,
+        |$atomic:ident: $int:ident| $native:expr,
+        $locked:expr $(,)?
+    ) => {
+        match Width::of::<T>() {
+            #[cfg(target_has_atomic = "8")]
+            Width::W8 => dispatch!(
+                @native $self, AtomicU8, u8, $atomic, $int, $native
+            ),
+            #[cfg(target_has_atomic = "16")]
+            Width::W16 => dispatch!(
+                @native $self, AtomicU16, u16, $atomic, $int, $native
+            ),
+            #[cfg(target_has_atomic = "32")]
+            Width::W32 => dispatch!(
+                @native $self, AtomicU32, u32, $atomic, $int, $native
+            ),
+            #[cfg(target_has_atomic = "64")]
+            Width::W64 => dispatch!(
+                @native $self, AtomicU64, u64, $atomic, $int, $native
+            ),
+            _ => $locked,
+        }
+    };
+    (
+        @native $self:ident,
+        $ty:ident,
+        $prim:ident,
+        $atomic:ident,
+        $int:ident,
+        $native:expr
+    ) => {{
+        #[allow(dead_code)]
+        type $int = $prim;
+        // SAFETY: `Width::of` checked that the atomic has the same size as
+        // `T`, and an alignment that `T` satisfies.
+        let $atomic = unsafe { &*($self.value.get() as *const atomic::$ty) };
+        $native
+    }};
+}
+
+/// Reinterprets a [`Plain`] value as another type of the same size.
+#[cfg_attr(
+    not(any(
+        target_has_atomic = "8",
+        target_has_atomic = "16",
+        target_has_atomic = "32",
+        target_has_atomic = "64",
+    )),
+    allow(dead_code)
+)]
+fn cast<T: Plain, U: Copy>(v: T) -> U {
+    debug_assert_eq!(mem::size_of::<T>(), mem::size_of::<U>());
+    // SAFETY: Callers only convert between a `Plain` type and an integer of
+    // the same size, or back to the `Plain` type from an integer holding a
+    // value originally converted from it. `Plain` types have no
+    // uninitialized bytes.
+    unsafe { mem::transmute_copy(&v) }
+}
+
+/// Returns whether `a` and `b` have the same bytes.
+pub(crate) fn same_bits<T: Plain>(a: &T, b: &T) -> bool {
+    let len = mem::size_of::<T>();
+    // SAFETY: `Plain` types have no uninitialized bytes.
+    unsafe {
+        let a = core::slice::from_raw_parts(a as *const T as *const u8, len);
+        let b = core::slice::from_raw_parts(b as *const T as *const u8, len);
+        a == b
+    }
+}
+
+impl<T> Atomic<T> {
+    /// Creates a new atomic.
+    pub const fn new(v: T) -> Self {
+        Self {
+            value: UnsafeCell::new(v),
+        }
+    }
+
+    /// Returns a mutable reference to the underlying value.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Consumes the atomic and returns the contained value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    /// Returns whether operations on this type use a built-in atomic rather
+    /// than a lock.
+    pub const fn is_lock_free() -> bool {
+        !matches!(Width::of::<T>(), Width::Locked)
+    }
+
+    fn locked<R>(&self, order: Ordering, f: impl FnOnce(*mut T) -> R) -> R {
+        let ptr = self.value.get();
+        with_stripe(ptr as usize, order, || f(ptr))
+    }
+}
+
+impl<T: Plain> Atomic<T> {
+    /// Loads the value.
+    pub fn load(&self, order: Ordering) -> T {
+        dispatch!(
+            self,
+            |a: Int| cast(a.load(order)),
+            // SAFETY: The lock is held.
+            self.locked(order, |p| unsafe { ptr::read(p) }),
+        )
+    }
+
+    /// Stores a value.
+    pub fn store(&self, val: T, order: Ordering) {
+        dispatch!(
+            self,
+            |a: Int| a.store(cast(val), order),
+            // SAFETY: The lock is held.
+            self.locked(order, |p| unsafe { ptr::write(p, val) }),
+        )
+    }
+
+    /// Stores a value, returning the previous value.
+    pub fn swap(&self, val: T, order: Ordering) -> T {
+        dispatch!(
+            self,
+            |a: Int| cast(a.swap(cast(val), order)),
+            // SAFETY: The lock is held.
+            self.locked(order, |p| unsafe { ptr::replace(p, val) }),
+        )
+    }
+
+    /// Stores `new` if the current value has the same bit pattern as
+    /// `current`, returning the previous value.
+    pub fn compare_exchange(
+        &self,
+        current: T,
+        new: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        dispatch!(
+            self,
+            |a: Int| a
+                .compare_exchange(cast(current), cast(new), success, failure)
+                .map(cast)
+                .map_err(cast),
+            self.locked(cas_order(success, failure), |p| {
+                // SAFETY: The lock is held.
+                let prev = unsafe { ptr::read(p) };
+                if same_bits(&prev, &current) {
+                    // SAFETY: The lock is held.
+                    unsafe { ptr::write(p, new) };
+                    Ok(prev)
+                } else {
+                    Err(prev)
+                }
+            }),
+        )
+    }
+
+    /// Like [`Self::compare_exchange`], but may fail spuriously.
+    pub fn compare_exchange_weak(
+        &self,
+        current: T,
+        new: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        dispatch!(
+            self,
+            |a: Int| a
+                .compare_exchange_weak(
+                    cast(current),
+                    cast(new),
+                    success,
+                    failure,
+                )
+                .map(cast)
+                .map_err(cast),
+            self.compare_exchange(current, new, success, failure),
+        )
+    }
+
+    /// Fetches the value, and applies a function to it that returns an
+    /// optional new value.
+    ///
+    /// Like the built-in atomics (and unlike the fallback types), this is a
+    /// compare-exchange loop, so `f` may be called more than once, and it
+    /// never runs while a lock is held.
+    pub fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: F,
+    ) -> Result<T, T>
+    where
+        F: FnMut(T) -> Option<T>,
+    {
+        let mut prev = self.load(fetch_order);
+        while let Some(next) = f(prev) {
+            match self.compare_exchange_weak(
+                prev,
+                next,
+                set_order,
+                fetch_order,
+            ) {
+                Ok(v) => return Ok(v),
+                Err(v) => prev = v,
+            }
+        }
+        Err(prev)
+    }
+}
+
+impl<T: Plain> crate::ext::sealed::Sealed for Atomic<T> {}
+
+impl_any_atomic!([T: Plain] Atomic<T>, T, Self::is_lock_free());
+
+impl_serde!([T: Plain] Atomic<T>, T);
+// SAFETY: The type contains only the value.
+impl_zeroable!([T: bytemuck::Zeroable] Atomic<T>);
+
+impl<T: Default> Default for Atomic<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for Atomic<T> {
+    fn from(v: T) -> Self {
+        Self::new(v)
+    }
+}
+
+impl<T: Plain + fmt::Debug> fmt::Debug for Atomic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.load(Ordering::SeqCst).fmt(f)
+    }
+}
@@ -16,7 +16,7 @@
  * limitations under the License.
  */
 
-#![cfg_attr(not(feature = "libc"), no_std)]
+#![cfg_attr(not(any(feature = "std", feature = "libc")), no_std)]
 #![cfg_attr(feature = "doc_cfg", feature(doc_cfg))]
 #![deny(unsafe_op_in_unsafe_fn)]
 
@@ -35,21 +35,92 @@
 //! available on all platforms[^1] in some form—either the built-in or fallback
 //! implementation.
 //!
+//! In addition, [`Atomic<T>`] makes any `Copy` type without padding atomic,
+//! such as a fieldless enum, using a built-in atomic of the same size when one
+//! is available and a global table of spinlocks otherwise.
+//!
+//! [`AtomicFlag`] is a flag that is always lock-free, like C++’s
+//! `std::atomic_flag`, as it needs only `AtomicBool`.
+//!
+//! [`AtomicOnce<T>`] is a cell that is initialized at most once, like
+//! `std::sync::OnceLock`, but available in `no_std` code.
+//!
+//! [`LockedAtomic<T, S>`] is protected by a lock chosen with its type
+//! parameter `S` rather than by the crate features, so that different atomics
+//! in one program can use different locks; e.g., only the atomics used by
+//! signal handlers might block signals. The available [`LockStrategy`]
+//! implementations are `SpinLock`, `SignalSafeSpinLock`, `CriticalSection`,
+//! and `OsMutex`.
+//!
+//! To check which implementation a type uses, [`AnyAtomic`] provides
+//! `IS_ALWAYS_LOCK_FREE` and `is_lock_free`, which are true only for built-in
+//! atomics. The fallback types also provide these as inherent items.
+//!
+//! When an alias points to a built-in atomic, it has the same size and bit
+//! validity as its integer and at least the same alignment, like the standard
+//! atomics, which is checked at compile time. Memory owned by C code, such as
+//! an `_Atomic int`, can therefore be viewed through an alias like
+//! `AtomicCInt` with `AtomicIntExt::from_ptr` (or the inherent `from_ptr`
+//! provided by the fallback types and, since Rust 1.75, the built-in atomics).
+//! A fallback type has this layout only when it consists solely of its value
+//! (see the feature `striped`), and even then, its accesses are not
+//! synchronized with those made by C code.
+//!
 //! Crate features
 //! --------------
 //!
 //! Types that directly correspond with Rust’s standard atomics like
 //! [`AtomicU64`] are available with the feature `primitives` (enabled by
 //! default). This includes [`AtomicPtr`], even though it isn’t exactly an
-//! integer.
+//! integer, and `AtomicChar`, which stores a `char` in an `AtomicU32`.
 //!
 //! Atomic C integer types like [`AtomicCInt`] and [`AtomicCUlong`] are
 //! available with the feature `c` (enabled by default). For more granularity,
 //! a separate feature exists for each C integer (e.g., `c_int` and `c_ulong`).
+//! This includes [`AtomicSizeT`] and [`AtomicSsizeT`] (features `size_t` and
+//! `ssize_t`), and `AtomicIntptrT`, `AtomicUintptrT`, and `AtomicPtrdiffT`
+//! (features `intptr_t`, `uintptr_t`, and `ptrdiff_t`), which are often used
+//! to pass integers through `void *` parameters of C callbacks, and
+//! `AtomicChar16T` and `AtomicChar32T` (features `char16_t` and `char32_t`),
+//! for the C11 character types, which are always `u16` and `u32`, and
+//! `AtomicSigAtomicT` (feature `sig_atomic_t`), for flags shared with signal
+//! handlers, which is an `int` or a `long` depending on the target. The
+//! features `wchar_t`, `time_t`, `off_t`, and `clock_t` provide `AtomicWcharT`
+//! (e.g., for sharing UTF-16 state with Windows APIs), `AtomicTimeT`,
+//! `AtomicOffT`, and `AtomicClockT`; they are not part of `c`, and they enable
+//! `libc`, as these types are not in [`core::ffi`]. Likewise, the Unix-only
+//! features `pid_t`, `uid_t`, and `gid_t` provide `AtomicPidT`, `AtomicUidT`,
+//! and `AtomicGidT`, and, for network code (e.g., to publish the address and
+//! port a socket was bound to), the features `socklen_t`, `in_addr_t`, and
+//! `in_port_t` provide `AtomicSocklenT`, `AtomicInAddrT`, and `AtomicInPortT`.
 //!
 //! The spinlock-based fallback implementation can cause deadlocks with signal
 //! handlers. To avoid this, enable the feature `signal`, which blocks incoming
-//! signals while the lock is held. This feature is Unix-specific.
+//! signals while the lock is held. On Windows, this feature has no effect, as
+//! no signal can interrupt a thread while it holds the lock (except
+//! synchronously, when the thread raises one itself). Note that the closure
+//! passed to `fetch_update` also runs with signals blocked. `signal::prewarm`
+//! performs this feature’s one-time initialization ahead of time, and, on
+//! Unix, `signal::set_blocked_signals` restricts which signals are blocked. If
+//! a system call that blocks or unblocks signals fails, the process aborts,
+//! unless a hook installed with `signal::set_failure_hook` handles the failure
+//! instead. The feature `signal-mask`, which `signal` enables, provides the
+//! `signal` module and `SignalSafeSpinLock` without making the fallback types
+//! block signals. `signal::SignalGuard` and `signal::with_signals_blocked`
+//! block the same signals for custom critical sections. Signals are blocked
+//! before a lock is acquired even if it is uncontended, as a handler could
+//! otherwise interrupt the thread while it holds the lock; to amortize the
+//! cost of the system calls this requires, a batch of operations can be
+//! performed within `signal::with_signals_blocked`, inside which the fallback
+//! types don't block signals again.
+//!
+//! On Linux, the feature `signal-nostd` makes the fallback types block signals
+//! like `signal` does, but by invoking the `rt_sigprocmask` system call
+//! directly instead of through `libc`, so that it is available in `no_std`
+//! code (such as early-boot or initramfs tools). It blocks every signal and
+//! doesn't provide the `signal` module, and it is supported on x86-64,
+//! AArch64, and 64-bit RISC-V; enabling it on any other target is a
+//! compile-time error. If `signal` is also enabled, `signal` takes precedence.
 //!
 //! atomic-int can optionally depend on [`libc`]. If this dependency is
 //! enabled, atomic-int will use the C integer types from [`libc`] instead of
@@ -58,7 +129,230 @@
 //! to [`core::ffi`] only in version 1.64. The feature `signal` always enables
 //! `libc`.
 //!
-//! This crate is `no_std` when `libc` is not enabled.
+//! With the feature `adaptive`, a thread that waits for a contended fallback
+//! spinlock for too long sleeps until the lock is released (on Linux and
+//! Android, using a futex) rather than spinning indefinitely. In exchange,
+//! every release of a lock must check for sleeping threads, which needs a
+//! `SeqCst` store in place of a release store; on x86-64, this makes
+//! uncontended operations on the fallback types nearly twice as slow. This
+//! feature also provides `contention_stats`, which reports how often the locks
+//! are contended. `adaptive` always enables `libc`.
+//!
+//! For debugging, the feature `trace` records every read-modify-write
+//! operation on the fallback types (such as `swap` and `fetch_add`) in a
+//! global ring buffer of the 1024 most recent entries, which can be read with
+//! `drain_trace`. Recording an entry costs several atomic operations and a
+//! thread-local access, so this feature should not be enabled in production
+//! builds. Recording is async-signal-safe, so operations performed by signal
+//! handlers are traced too. Operations on built-in atomics are not traced.
+//! `trace` always enables `std`.
+//!
+//! For strictly single-threaded code, the feature `cell` adds the module
+//! `cell`, which provides an unsynchronized counterpart to each integer and
+//! pointer atomic type in this crate, with the same name and API (including
+//! `AtomicIntExt` and `AtomicPtrExt`). These types store their values in a
+//! `Cell` and ignore the requested orderings, so they have no locking
+//! overhead, but they are `!Sync` and can’t be shared between threads. Code
+//! that is generic over these traits can use either kind of atomic. The
+//! feature only adds this module; it does not change any other type.
+//!
+//! The feature `hooks` provides `set_lock_hooks`, which installs callbacks
+//! that are invoked whenever a fallback type acquires its spinlock. This is
+//! intended for integration with tracing and profiling tools. When no hooks
+//! are installed, the feature adds only a single relaxed load to each lock
+//! acquisition.
+//!
+//! For debugging, the feature `deadlock-detection` makes each thread record
+//! the fallback spinlocks it holds, and reports an attempt to acquire one of
+//! them again (e.g., from a callback passed to `fetch_update` or from a signal
+//! handler) instead of spinning forever. With `libc` on Unix, the report is an
+//! async-signal-safe message followed by an abort; otherwise, it is a panic.
+//! This detects only deadlocks within a single thread, and it has no effect
+//! under loom. `deadlock-detection` always enables `std`.
+//!
+//! The feature `asm-atomics` makes `AtomicI64` and `AtomicU64` lock-free on
+//! ARM targets that lack built-in 64-bit atomics but have the exclusive
+//! doubleword instructions `ldrexd` and `strexd` (ARMv6K and later, except for
+//! the M profile), using those instructions in place of the spinlock. Every
+//! built-in ARMv7 target already has 64-bit atomics, so this mainly benefits
+//! `armv6k-nintendo-3ds` and custom targets. It similarly makes `AtomicI128`
+//! and `AtomicU128` lock-free on x86-64 and little-endian AArch64 targets,
+//! using `cmpxchg16b` or `casp` (or `ldxp` and `stxp` without LSE); on x86-64,
+//! unless the `cmpxchg16b` target feature is enabled, support for it is
+//! detected at run time. All operations on these types are sequentially
+//! consistent regardless of the ordering requested. The feature has no effect
+//! on other targets, and the C type aliases always use the spinlock.
+//!
+//! The feature `striped` removes the spinlock from each fallback type and
+//! instead locks a global table of cache-padded spinlocks (stripes), chosen by
+//! hashing the atomic’s address. This makes the fallback types consist only of
+//! their values, so they have the same size as the plain integers and the same
+//! alignment as the built-in atomics (which no other feature changes:
+//! `seqlock` keeps its counter in the stripes instead, and `poisoning` can't
+//! be enabled together with `striped`), at the cost of occasional contention
+//! between unrelated atomics that share a stripe. The number of stripes is
+//! read from the environment variable `ATOMIC_INT_LOCK_STRIPES` at build time
+//! (default 64); more stripes reduce such contention but use more static
+//! memory, as each stripe occupies its own cache line. With this feature, the
+//! closure passed to `fetch_update` on a fallback type must not access other
+//! fallback atomics, as they may share its stripe.
+//!
+//! The feature `seqlock` makes `load` on the fallback integer types read the
+//! value without taking the spinlock, like a sequence lock: each fallback type
+//! (or, with `striped`, each stripe) gets a sequence counter that operations
+//! update whenever they store a value while holding the lock, and `load` reads
+//! the value with word- or byte-wise relaxed atomic operations, retrying if
+//! the counter shows that a store overlapped the read. Readers therefore never
+//! contend with each other or delay writers, though they retry while a store
+//! is in progress. `SeqCst` loads (and, with `conservative-ordering`, every
+//! load) and loads of `AtomicPtr` still take the lock. This feature has no
+//! effect with `critical-section`, or with `--cfg loom`.
+//!
+//! The feature `float` provides `AtomicF32` and `AtomicF64`, which store
+//! floats as their bit representations in `AtomicU32` and `AtomicU64`. Their
+//! `compare_exchange` compares bit patterns rather than using `==`;
+//! `compare_exchange_value` compares with `==` instead. They also provide
+//! `fetch_add`, `fetch_sub`, `fetch_min`, and `fetch_max`.
+//!
+//! The feature `critical-section` is the bare-metal counterpart of `signal`:
+//! every operation on a fallback type runs inside a critical section from the
+//! [`critical-section`] crate instead of taking a spinlock, so the fallback
+//! types contain no lock and can be used from interrupt handlers on embedded
+//! targets. Unless `poisoning` is enabled too, they then consist only of their
+//! values, as with `striped`, and so have the same size as the plain integers
+//! and the same alignment as the built-in atomics. An implementation of
+//! `critical-section` must be provided, as described in that crate’s
+//! documentation. If both `signal` and `critical-section` are enabled,
+//! `critical-section` is used. On targets without atomic read-modify-write
+//! operations on `AtomicBool`, like `thumbv6m` and `riscv32i`, the crate
+//! builds only with `critical-section` or `portable-atomic` (which must then
+//! be set up to provide these operations, e.g., with its own feature
+//! `critical-section`), and `AtomicFlag`, `AtomicOnce`, `SpinLock`, and
+//! `SignalSafeSpinLock` are unavailable.
+//!
+//! The feature `alloc` provides `dump_slice`, which formats the values in a
+//! slice of atomics as a `String`. It is enabled by `std`.
+//!
+//! The feature `atomic-traits` implements the traits of [`atomic-traits`]
+//! (`Atomic`, `NumOps`, `Bitwise`, and the `fetch::*` traits they require) for
+//! the fallback types (including those in the module `cell`), so that code
+//! generic over those traits works with them. The aliases of built-in atomics
+//! already implement them through `atomic-traits` itself. The types from
+//! `portable-atomic` used by the features `portable-atomic` and
+//! `portable-atomic-all` do not, as they are foreign to both crates. `AsPtr`
+//! and `FromPtr` are not implemented, as `atomic-traits` declares them only on
+//! some compiler versions.
+//!
+//! The feature `backoff` provides `set_backoff_policy`, which selects at
+//! runtime how threads wait for a fallback type’s spinlock when it is held by
+//! another thread: by spinning, by spinning and then yielding to the OS
+//! scheduler, or (with `adaptive`) by sleeping. The policy can be changed at
+//! any time from any thread. This feature enables `std`, and has no effect
+//! when `critical-section` is enabled.
+//!
+//! The feature `exponential-backoff` makes threads that find a fallback type’s
+//! spinlock held wait with exponential backoff: between checks of the lock,
+//! they issue exponentially more spin-loop hints (up to 64), and, if `std` is
+//! enabled, they yield to the OS scheduler once the lock has been held for a
+//! while. This improves throughput under contention, especially when threads
+//! outnumber CPUs. With `backoff`, it is available as
+//! `BackoffPolicy::Exponential` (the default with this feature); `adaptive`
+//! takes precedence over it.
+//!
+//! The feature `conservative-ordering` makes every operation on a fallback
+//! type acquire and release its spinlock with `SeqCst` ordering, regardless of
+//! the ordering requested, so that code that accidentally uses too weak an
+//! ordering still behaves as if it had used `SeqCst` on the fallback types.
+//! This trades performance for safety, and has no effect on built-in atomics.
+//!
+//! The feature `poisoning` makes the fallback types track whether an operation
+//! panicked while holding the spinlock (e.g., because the closure passed to
+//! `fetch_update` panicked), like `Mutex`. Poisoning can be observed with
+//! `is_poisoned` and `fetch_update_poisonable`, and cleared with
+//! `clear_poison`; the other methods are unaffected, as built-in atomics have
+//! no notion of poisoning. It can't be enabled together with `striped`, as a
+//! flag shared by a stripe would let a panic poison unrelated atomics. This
+//! feature enables `std`.
+//!
+//! The feature `portable-atomic` makes the aliases that would otherwise point
+//! to a fallback type point to the corresponding type in [`portable-atomic`]
+//! instead, which may use a lock-free implementation specific to the target
+//! (e.g., disabling interrupts on single-core embedded targets) where this
+//! crate would use a spinlock. Aliases of built-in atomics, including the C
+//! integer types, are unaffected, so the types and their feature detection
+//! work as usual. `portable-atomic-all`, which goes further, enables this
+//! feature, and `force-fallback` overrides it. As with `portable-atomic-all`,
+//! `asm-atomics` then has no effect, and bytemuck’s `Zeroable` is not
+//! implemented for the replaced types.
+//!
+//! The feature `portable-atomic-all` makes every integer and pointer type
+//! alias in this crate (except `AtomicBool`) point to the corresponding type
+//! in [`portable-atomic`], even when a built-in atomic is available, so that a
+//! project already using `portable-atomic` gets the same implementation
+//! everywhere. No fallback type from this crate is used with this feature.
+//!
+//! The feature `force-fallback` makes every integer and pointer type alias
+//! (except `AtomicBool`) point to a lock-based fallback type, even when a
+//! built-in atomic is available, so that code using this crate can be tested
+//! against the fallback implementation on any machine. It overrides
+//! `portable-atomic-all` and `asm-atomics`, and can also be enabled without
+//! changing a manifest by setting the environment variable
+//! `ATOMIC_INT_FORCE_FALLBACK=1` when building. It has no effect with `--cfg
+//! loom`. `Atomic<T>` and `FfiAtomic<T>` still use built-in atomics where
+//! available.
+//!
+//! The feature `wait` adds `wait`, `notify_one`, and `notify_all` to
+//! `AtomicIntExt` and `AtomicPtrExt`, for blocking until an atomic (built-in
+//! or fallback) changes, like `std::atomic<T>::wait` in C++. This uses `futex`
+//! on Linux, `__ulock_wait` on macOS, and `WaitOnAddress` on Windows. This
+//! feature enables `libc`.
+//!
+//! The feature `serde` implements [`serde`]’s `Serialize` and `Deserialize`
+//! for the fallback types, the atomic floats, and `Atomic<T>`, serializing the
+//! value loaded with `SeqCst` ordering. The aliases of built-in atomics use
+//! serde’s own implementations instead (which load with `Relaxed` ordering),
+//! or those of `portable-atomic` with `portable-atomic-all`. serde implements
+//! them for the built-in atomics only with its `std` feature, which the
+//! feature `std` enables when `serde` is enabled, so `serde` alone does not
+//! require `std`.
+//!
+//! The feature `bytemuck` implements [`bytemuck`]’s `Zeroable` for the
+//! fallback types and most other types in this crate (for wrappers, if the
+//! wrapped type implements it), so that they can be zero-initialized, e.g., in
+//! shared memory. It also enables bytemuck’s `zeroable_atomics`, so the
+//! aliases of built-in atomics implement `Zeroable` too, except with
+//! `portable-atomic-all`. `NoUninit` and `Pod` are not implemented, as
+//! bytemuck forbids them for types with interior mutability.
+//!
+//! The feature `wide` adds `AtomicU256` and `AtomicI256`, 256-bit atomic
+//! integers that always use the fallback implementation, with the same API as
+//! the other fallback types (including every `fetch_*` method) and an
+//! implementation of `AtomicIntExt`. Their values are the types `U256` and
+//! `I256`, which support wrapping, checked, and saturating arithmetic, bitwise
+//! operations, shifts, comparison, and formatting, and convert to and from
+//! pairs of 128-bit words with `from_words` and `to_words`.
+//!
+//! The feature `time` (which enables `std` and `primitives`) adds
+//! `AtomicDuration` and `AtomicSystemTime`, which store a `Duration` or a
+//! `SystemTime` as a number of nanoseconds (since the Unix epoch, for the
+//! latter) in an `AtomicU64`, and provide `load`, `store`, `swap`,
+//! `compare_exchange`, and saturating `fetch_add`, as well as `fetch_max` and
+//! `fetch_min`, e.g., to track the latest heartbeat from several threads.
+//! Values outside the representable range (about 584 years, or the years 1970
+//! to 2554) are clamped to it.
+//!
+//! When the crate is built with `RUSTFLAGS="--cfg loom"`, the fallback types
+//! use the atomics and cells of [`loom`] instead of those in `core`, so that
+//! code using them (including this crate’s own tests) can be model-checked:
+//! loom reports a data race if an access to a fallback type’s value isn’t
+//! properly synchronized by its lock. The fallback types can then be used only
+//! within a loom model, and their `new` and `as_ptr` are not `const`. With
+//! `--cfg loom`, `asm-atomics` has no effect, and threads always wait for a
+//! lock by spinning, regardless of `backoff`, `adaptive`, and
+//! `exponential-backoff`. The lock stripes used by `Atomic<T>` are also
+//! modeled, but accesses to its value are not tracked.
+//!
+//! This crate is `no_std` when neither `std` nor `libc` is enabled.
 //!
 //! [^1]: As long as the platform supports [`AtomicBool`], which is required
 //!       for the fallback implementation.
@@ -66,25 +360,126 @@
 //! [`libc`]: https://docs.rs/libc/0.2
 //! [`c_int`]: ffi::c_int
 //! [`AtomicBool`]: atomic::AtomicBool
+//! [`LockStrategy`]: LockStrategy
+//! [`LockedAtomic<T, S>`]: LockedAtomic
+//! [`AtomicFlag`]: AtomicFlag
+//! [`AtomicOnce<T>`]: AtomicOnce
+//! [`loom`]: https://docs.rs/loom/0.7
+//! [`AnyAtomic`]: AnyAtomic
+//! [`Atomic<T>`]: Atomic
+//! [`portable-atomic`]: https://docs.rs/portable-atomic/1
+//! [`critical-section`]: https://docs.rs/critical-section/1
+//! [`serde`]: https://docs.rs/serde/1
+//! [`bytemuck`]: https://docs.rs/bytemuck/1
+//! [`atomic-traits`]: https://docs.rs/atomic-traits/0.4
+
+// The spinlocks are built on `AtomicBool`, so report its absence clearly
+// rather than with errors from deep inside the macros. `critical-section`
+// replaces the spinlocks, and `portable-atomic` provides both the atomics
+// and the `AtomicBool` that the spinlocks are then built on.
+#[cfg(all(
+    no_atomic_bool,
+    not(any(feature = "critical-section", feature = "portable-atomic")),
+))]
+compile_error!(
+    "atomic-int requires atomic read-modify-write operations on \
+     `AtomicBool`, which this target lacks; enable the feature \
+     `critical-section` or `portable-atomic`"
+);
+
+// With `striped`, a poison flag could only be kept per stripe, where a panic
+// would poison unrelated atomics.
+#[cfg(all(feature = "striped", feature = "poisoning"))]
+compile_error!("the features `striped` and `poisoning` are incompatible");
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 #[allow(unused_imports)]
 use core::sync::atomic;
 
+// The source of the types that the aliases point to when available.
+#[allow(unused_imports)]
+#[cfg(not(feature = "portable-atomic-all"))]
+use core::sync::atomic as native;
+
+#[allow(unused_imports)]
+#[cfg(feature = "portable-atomic-all")]
+use portable_atomic as native;
+
+// With `portable-atomic`, the source of the types that replace the fallback
+// types.
+#[allow(unused_imports)]
+#[cfg(feature = "portable-atomic")]
+use portable_atomic as delegate;
+
 #[allow(unused_imports)]
 #[cfg(not(feature = "libc"))]
-use core::ffi;
+mod ffi {
+    pub use core::ffi::*;
+    // Not in `core::ffi`, but Rust assumes that `size_t` is `usize`, and
+    // that `intptr_t` and `uintptr_t` (and thus `ptrdiff_t`) are the size of
+    // a pointer.
+    pub use core::primitive::isize as ptrdiff_t;
+    pub use core::primitive::{isize as ssize_t, usize as size_t};
+    pub use core::primitive::{isize as intptr_t, usize as uintptr_t};
+    // C11’s `char16_t` and `char32_t` are `uint_least16_t` and
+    // `uint_least32_t`, which are exactly 16 and 32 bits wherever Rust runs.
+    pub use core::primitive::{u16 as char16_t, u32 as char32_t};
+    // Not in `libc` either; see `sig_atomic_t` in build.rs.
+    #[cfg(not(sig_atomic_t_long))]
+    pub use core::ffi::c_int as sig_atomic_t;
+    #[cfg(sig_atomic_t_long)]
+    pub use core::ffi::c_long as sig_atomic_t;
+}
 
 #[allow(unused_imports)]
 #[cfg(feature = "libc")]
-use libc as ffi;
+mod ffi {
+    pub use core::primitive::{u16 as char16_t, u32 as char32_t};
+    #[cfg(not(sig_atomic_t_long))]
+    pub use libc::c_int as sig_atomic_t;
+    #[cfg(sig_atomic_t_long)]
+    pub use libc::c_long as sig_atomic_t;
+    pub use libc::*;
+}
 
 mod detail {
-    pub trait HasAtomic {
-        type Atomic;
+    /// Maps an integer to its built-in atomic (or, with `portable-atomic`,
+    /// the type that replaces the fallback type), if there is one.
+    pub trait HasNativeAtomic: Sized {
+        type Atomic: crate::AtomicIntExt<Int = Self>;
     }
 }
 
-use detail::HasAtomic;
+/// An integer type with an atomic counterpart in this crate.
+///
+/// [`Self::Atomic`] is the atomic type alias for the integer (e.g.,
+/// `AtomicU8` for [`u8`]), which is either a built-in atomic or a fallback
+/// type. This trait is implemented for the primitive integers with the
+/// feature `primitives`, and for `U256` and `I256` with the feature `wide`.
+/// As the C integer types are aliases of primitive integers, they are
+/// covered as well; for example, `<c_int as HasAtomic>::Atomic` is
+/// `AtomicCInt`.
+///
+/// This allows generic code to create atomics for any integer type:
+///
+/// ```
+/// use atomic_int::{AtomicIntExt, HasAtomic, PrimInt};
+/// use std::sync::atomic::Ordering;
+///
+/// fn make_counter<T: HasAtomic>() -> T::Atomic {
+///     T::Atomic::new(T::ZERO)
+/// }
+///
+/// let counter = make_counter::<u16>();
+/// counter.fetch_add(3, Ordering::Relaxed);
+/// assert_eq!(counter.load(Ordering::Relaxed), 3);
+/// ```
+pub trait HasAtomic: PrimInt {
+    /// The atomic type for this integer.
+    type Atomic: AtomicIntExt<Int = Self>;
+}
 
 macro_rules! with_primitive_atomics {
     ($macro:path) => {
@@ -105,9 +500,17 @@ macro_rules! with_primitive_atomics {
 
 macro_rules! impl_has_atomic {
     ($atomic:ident, $int:ident, $($cfg:tt)*) => {
-        #[cfg($($cfg)*)]
-        impl HasAtomic for $int {
-            type Atomic = atomic::$atomic;
+        #[cfg(any(feature = "portable-atomic-all", $($cfg)*))]
+        impl detail::HasNativeAtomic for $int {
+            type Atomic = native::$atomic;
+        }
+
+        #[cfg(all(
+            feature = "portable-atomic",
+            not(any(feature = "portable-atomic-all", $($cfg)*)),
+        ))]
+        impl detail::HasNativeAtomic for $int {
+            type Atomic = delegate::$atomic;
         }
     };
 }
@@ -117,16 +520,37 @@ with_primitive_atomics!(impl_has_atomic);
 #[allow(unused_macros)]
 macro_rules! define_primitive_atomic {
     ($atomic:ident$(<$generic:ident>)?, $type:ty, $($cfg:tt)*) => {
-        #[cfg(all(not(doc), $($cfg)*))]
-        pub type $atomic$(<$generic>)? = atomic::$atomic$(<$generic>)?;
+        #[cfg(all(
+            not(doc),
+            not(force_fallback),
+            any(feature = "portable-atomic-all", $($cfg)*),
+        ))]
+        pub type $atomic$(<$generic>)? = native::$atomic$(<$generic>)?;
+
+        #[cfg(all(
+            not(doc),
+            not(force_fallback),
+            feature = "portable-atomic",
+            not(any(feature = "portable-atomic-all", $($cfg)*)),
+        ))]
+        pub type $atomic$(<$generic>)? = delegate::$atomic$(<$generic>)?;
 
-        #[cfg(any(doc, not($($cfg)*)))]
+        #[cfg(any(
+            doc,
+            force_fallback,
+            not(any(feature = "portable-atomic", $($cfg)*)),
+        ))]
         #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
         /// An atomic
         #[doc = concat!("[`", stringify!($type), "`].")]
         ///
         /// This is either an alias to the type in [`core::sync::atomic`], or,
-        /// if not available, a spinlock-based fallback type.
+        /// if not available, a spinlock-based fallback type (or, with the
+        /// feature `portable-atomic`, the type in [`portable-atomic`]). With
+        /// the feature `portable-atomic-all`, it is always an alias to the
+        /// type in [`portable-atomic`].
+        ///
+        /// [`portable-atomic`]: https://docs.rs/portable-atomic/1
         ///
         /// [`*mut T`]: pointer
         pub type $atomic$(<$generic>)? = fallback::$atomic$(<$generic>)?;
@@ -136,6 +560,24 @@ macro_rules! define_primitive_atomic {
 #[cfg(feature = "primitives")]
 with_primitive_atomics!(define_primitive_atomic);
 
+#[allow(unused_macros)]
+macro_rules! impl_has_atomic_alias {
+    ($atomic:ident, $int:ident, $($cfg:tt)*) => {
+        impl HasAtomic for $int {
+            type Atomic = $atomic;
+        }
+    };
+}
+
+#[cfg(feature = "primitives")]
+with_primitive_atomics!(impl_has_atomic_alias);
+
+#[cfg(feature = "wide")]
+impl_has_atomic_alias!(AtomicU256, U256,);
+
+#[cfg(feature = "wide")]
+impl_has_atomic_alias!(AtomicI256, I256,);
+
 #[cfg(feature = "primitives")]
 define_primitive_atomic!(AtomicPtr<T>, *mut T, target_has_atomic = "ptr");
 
@@ -144,8 +586,9 @@ define_primitive_atomic!(AtomicPtr<T>, *mut T, target_has_atomic = "ptr");
 /// An atomic [`bool`].
 ///
 /// This type alias is provided for completeness, but it always points to the
-/// real [`AtomicBool`][real] in [`core::sync::atomic`], as even the fallback
-/// atomic implementation in this crate requires [`AtomicBool`][real].
+/// real [`AtomicBool`][real] in [`core::sync::atomic`]. On targets without
+/// atomic read-modify-write operations on it (like `thumbv6m`), it therefore
+/// supports only loads and stores.
 ///
 /// [real]: atomic::AtomicBool
 pub type AtomicBool = atomic::AtomicBool;
@@ -184,22 +627,76 @@ macro_rules! with_c_atomics {
             "c_ulonglong",
             has_c_ulonglong_atomic
         );
+        #[cfg(feature = "size_t")]
+        $macro!(AtomicSizeT, size_t, "size_t", has_size_t_atomic);
+        #[cfg(feature = "ssize_t")]
+        $macro!(AtomicSsizeT, ssize_t, "ssize_t", has_ssize_t_atomic);
+        #[cfg(feature = "intptr_t")]
+        $macro!(AtomicIntptrT, intptr_t, "intptr_t", has_intptr_t_atomic);
+        #[cfg(feature = "uintptr_t")]
+        $macro!(AtomicUintptrT, uintptr_t, "uintptr_t", has_uintptr_t_atomic);
+        #[cfg(feature = "ptrdiff_t")]
+        $macro!(AtomicPtrdiffT, ptrdiff_t, "ptrdiff_t", has_ptrdiff_t_atomic);
+        #[cfg(feature = "char16_t")]
+        $macro!(AtomicChar16T, char16_t, "char16_t", has_char16_t_atomic);
+        #[cfg(feature = "char32_t")]
+        $macro!(AtomicChar32T, char32_t, "char32_t", has_char32_t_atomic);
+        #[cfg(feature = "sig_atomic_t")]
+        $macro!(
+            AtomicSigAtomicT,
+            sig_atomic_t,
+            "sig_atomic_t",
+            has_sig_atomic_t_atomic
+        );
+        #[cfg(feature = "wchar_t")]
+        $macro!(AtomicWcharT, wchar_t, "wchar_t", has_wchar_t_atomic);
+        #[cfg(feature = "time_t")]
+        $macro!(AtomicTimeT, time_t, "time_t", has_time_t_atomic);
+        #[cfg(feature = "off_t")]
+        $macro!(AtomicOffT, off_t, "off_t", has_off_t_atomic);
+        #[cfg(feature = "clock_t")]
+        $macro!(AtomicClockT, clock_t, "clock_t", has_clock_t_atomic);
+        #[cfg(all(feature = "pid_t", unix))]
+        $macro!(AtomicPidT, pid_t, "pid_t", has_pid_t_atomic);
+        #[cfg(all(feature = "uid_t", unix))]
+        $macro!(AtomicUidT, uid_t, "uid_t", has_uid_t_atomic);
+        #[cfg(all(feature = "gid_t", unix))]
+        $macro!(AtomicGidT, gid_t, "gid_t", has_gid_t_atomic);
+        #[cfg(all(feature = "socklen_t", unix))]
+        $macro!(AtomicSocklenT, socklen_t, "socklen_t", has_socklen_t_atomic);
+        #[cfg(all(feature = "in_addr_t", unix))]
+        $macro!(AtomicInAddrT, in_addr_t, "in_addr_t", has_in_addr_t_atomic);
+        #[cfg(all(feature = "in_port_t", unix))]
+        $macro!(AtomicInPortT, in_port_t, "in_port_t", has_in_port_t_atomic);
     };
 }
 
 #[allow(unused_macros)]
 macro_rules! define_c_atomic {
     ($atomic:ident, $int:ident, $feature:literal, $cfg:ident) => {
-        #[cfg(all(not(doc), $cfg))]
-        pub type $atomic = <ffi::$int as HasAtomic>::Atomic;
+        #[cfg(all(
+            not(doc),
+            not(force_fallback),
+            any(feature = "portable-atomic", $cfg),
+        ))]
+        pub type $atomic = <ffi::$int as detail::HasNativeAtomic>::Atomic;
 
-        #[cfg(any(doc, not($cfg)))]
+        #[cfg(any(
+            doc,
+            force_fallback,
+            not(any(feature = "portable-atomic", $cfg)),
+        ))]
         #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = $feature)))]
         /// An atomic
         #[doc = concat!("[`", stringify!($int), "`][1].")]
         ///
         /// This is either an alias to the appropriate atomic integer type in
-        /// [`core::sync::atomic`], or a spinlock-based fallback type.
+        /// [`core::sync::atomic`] (or, with the feature
+        /// `portable-atomic-all`, [`portable-atomic`]), or a spinlock-based
+        /// fallback type (or, with the feature `portable-atomic`, the type in
+        /// [`portable-atomic`]).
+        ///
+        /// [`portable-atomic`]: https://docs.rs/portable-atomic/1
         #[doc = concat!("\n\n[1]: ffi::", stringify!($int))]
         pub type $atomic = fallback::$atomic;
     };
@@ -207,7 +704,299 @@ macro_rules! define_c_atomic {
 
 with_c_atomics!(define_c_atomic);
 
+#[macro_use]
+mod ext;
+#[cfg(feature = "primitives")]
+mod array;
+mod atomic_enum;
+#[cfg(feature = "primitives")]
+mod bitset;
+// Without any integer types enabled, the module is empty.
+#[cfg(feature = "cell")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "cell")))]
+#[cfg_attr(not(any(feature = "primitives", feature = "c")), allow(unused))]
+pub mod cell;
+#[cfg(feature = "primitives")]
+mod char;
+mod coalesced;
+// Without `fallback_128`, there may be no fallback types outside of the docs
+// (and the tests, which define the fallback `AtomicPtr` on every target).
+#[cfg_attr(any(test, not(fallback_128)), allow(dead_code))]
+#[macro_use]
 mod fallback;
+mod fence;
+mod ffi_atomic;
+#[cfg(not(no_atomic_bool))]
+mod flag;
+#[cfg(feature = "float")]
+mod float;
+mod generic;
+mod locked;
+#[cfg(feature = "primitives")]
+mod minmax;
+#[cfg(not(no_atomic_bool))]
+mod once;
+#[cfg(feature = "primitives")]
+mod option;
+mod ordered;
+mod packed;
+mod padded;
+#[cfg(feature = "primitives")]
+mod refcount;
+mod sharded;
+#[cfg(feature = "signal-mask")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "signal-mask")))]
+pub mod signal;
+#[cfg(feature = "primitives")]
+mod tagged;
+#[cfg(feature = "time")]
+mod time;
+#[cfg(feature = "wait")]
+mod wait;
+#[cfg(feature = "wide")]
+mod wide;
+
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use array::AtomicArray;
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use bitset::{AtomicBitSet, BitSetIter};
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use char::AtomicChar;
+pub use coalesced::Coalesced;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use ext::dump_slice;
+pub use ext::{AnyAtomic, AtomicIntExt, AtomicPtrExt, Overflow, PrimInt};
+pub use ext::{ApplyOp, RmwResult};
+pub use ext::{fmt_slice, max_of, min_of};
+pub use fallback::LockScope;
+pub use fence::{compiler_fence, fence};
+pub use ffi_atomic::FfiAtomic;
+#[cfg(not(no_atomic_bool))]
+pub use flag::AtomicFlag;
+#[cfg(feature = "float")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "float")))]
+pub use float::{AtomicF32, AtomicF64};
+pub use generic::{Atomic, Plain};
+#[cfg(feature = "critical-section")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "critical-section")))]
+pub use locked::CriticalSection;
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+pub use locked::OsMutex;
+#[cfg(all(feature = "signal-mask", not(no_atomic_bool)))]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "signal-mask")))]
+pub use locked::SignalSafeSpinLock;
+#[cfg(not(no_atomic_bool))]
+pub use locked::SpinLock;
+pub use locked::{LockStrategy, LockedAtomic};
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use minmax::AtomicMinMax;
+#[cfg(not(no_atomic_bool))]
+pub use once::AtomicOnce;
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use option::AtomicOption;
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use option::{AtomicOptionNonZeroI8, AtomicOptionNonZeroU8};
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use option::{AtomicOptionNonZeroI16, AtomicOptionNonZeroU16};
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use option::{AtomicOptionNonZeroI32, AtomicOptionNonZeroU32};
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use option::{AtomicOptionNonZeroI64, AtomicOptionNonZeroU64};
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use option::{AtomicOptionNonZeroI128, AtomicOptionNonZeroU128};
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use option::{AtomicOptionNonZeroIsize, AtomicOptionNonZeroUsize};
+pub use ordered::{AcquireReleaseAtomic, RelaxedAtomic, SeqCstAtomic};
+pub use packed::{Packed, PackedAtomic, PackedField};
+pub use padded::CachePadded;
+#[cfg(feature = "primitives")]
+pub use padded::{AtomicBoolPadded, AtomicPtrPadded};
+#[cfg(feature = "primitives")]
+pub use padded::{AtomicI8Padded, AtomicU8Padded};
+#[cfg(feature = "primitives")]
+pub use padded::{AtomicI16Padded, AtomicU16Padded};
+#[cfg(feature = "primitives")]
+pub use padded::{AtomicI32Padded, AtomicU32Padded};
+#[cfg(feature = "primitives")]
+pub use padded::{AtomicI64Padded, AtomicU64Padded};
+#[cfg(feature = "primitives")]
+pub use padded::{AtomicI128Padded, AtomicU128Padded};
+#[cfg(feature = "primitives")]
+pub use padded::{AtomicIsizePadded, AtomicUsizePadded};
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use refcount::{AbortOnOverflow, SaturateOnOverflow};
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use refcount::{AtomicRefCount, OverflowPolicy};
+pub use sharded::ShardedCounter;
+#[cfg(feature = "primitives")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+pub use tagged::AtomicTaggedPtr;
+#[cfg(feature = "time")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "time")))]
+pub use time::{AtomicDuration, AtomicSystemTime};
+#[cfg(feature = "wide")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "wide")))]
+pub use wide::{I256, NonZeroI256, NonZeroU256, U256};
+
+#[cfg(feature = "adaptive")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "adaptive")))]
+pub use fallback::{ContentionStats, contention_stats};
+
+#[cfg(all(feature = "backoff", spinlock))]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "backoff")))]
+pub use fallback::{BackoffPolicy, backoff_policy, set_backoff_policy};
+
+#[cfg(feature = "hooks")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "hooks")))]
+pub use fallback::{LockHooks, set_lock_hooks};
+
+#[cfg(feature = "poisoning")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "poisoning")))]
+pub use fallback::Poisoned;
+
+#[cfg(feature = "trace")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "trace")))]
+pub use fallback::{TraceEntry, TraceOp, drain_trace};
+
+// Ensures that a refactor can't make any atomic type `!Send` or `!Sync`.
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[allow(unused_macros)]
+    macro_rules! check {
+        ($atomic:ty, $($rest:tt)*) => {
+            assert_send::<$atomic>();
+            assert_sync::<$atomic>();
+        };
+    }
+
+    #[cfg(feature = "primitives")]
+    with_primitive_atomics!(check);
+    #[cfg(feature = "primitives")]
+    check!(AtomicPtr<core::cell::Cell<u8>>,);
+    with_c_atomics!(check);
+    #[cfg(feature = "wide")]
+    check!(AtomicU256,);
+    #[cfg(feature = "wide")]
+    check!(AtomicI256,);
+    #[cfg(feature = "primitives")]
+    check!(AtomicArray<u64, 4>,);
+    #[cfg(feature = "primitives")]
+    check!(AtomicMinMax<i64>,);
+    #[cfg(feature = "primitives")]
+    check!(AtomicRefCount,);
+    #[cfg(feature = "primitives")]
+    check!(AtomicTaggedPtr<core::cell::Cell<u64>, 3>,);
+    #[cfg(feature = "time")]
+    check!(AtomicDuration,);
+    #[cfg(feature = "time")]
+    check!(AtomicSystemTime,);
+};
+
+// Ensures that `portable-atomic-all` routes every alias through
+// `portable-atomic`.
+#[cfg(all(feature = "portable-atomic-all", not(force_fallback), not(doc)))]
+const _: fn() = || {
+    macro_rules! check {
+        ($atomic:ident, $($rest:tt)*) => {
+            let _: fn($atomic) -> portable_atomic::$atomic = |a| a;
+        };
+    }
+
+    #[cfg(feature = "primitives")]
+    with_primitive_atomics!(check);
+    #[cfg(feature = "primitives")]
+    let _: fn(AtomicPtr<u8>) -> portable_atomic::AtomicPtr<u8> = |a| a;
+};
+
+// Ensures that every alias of a built-in atomic, and every fallback type that
+// consists only of its value, has the same size as its value and at least its
+// alignment, so that `from_ptr` can view memory owned by C code (e.g., an
+// `_Atomic int`) through the alias.
+#[cfg(not(doc))]
+const _: () = {
+    #[allow(dead_code)]
+    const fn same_layout<A, T>() -> bool {
+        use core::mem::{align_of, size_of};
+        size_of::<A>() == size_of::<T>() && align_of::<A>() >= align_of::<T>()
+    }
+
+    #[allow(unused_macros)]
+    macro_rules! check {
+        ($atomic:ty, $value:ty) => {
+            assert!(
+                !(<$atomic as AnyAtomic>::IS_ALWAYS_LOCK_FREE
+                    || cfg!(compact_fallback))
+                    || same_layout::<$atomic, $value>()
+            );
+        };
+    }
+
+    #[allow(unused_macros)]
+    macro_rules! check_primitive {
+        ($atomic:ident, $int:ident, $($rest:tt)*) => {
+            check!($atomic, $int);
+        };
+    }
+
+    #[allow(unused_macros)]
+    macro_rules! check_c {
+        ($atomic:ident, $int:ident, $($rest:tt)*) => {
+            check!($atomic, ffi::$int);
+        };
+    }
+
+    #[cfg(feature = "primitives")]
+    with_primitive_atomics!(check_primitive);
+    #[cfg(feature = "primitives")]
+    check!(AtomicPtr<u8>, *mut u8);
+    with_c_atomics!(check_c);
+};
+
+// Ensures that `portable-atomic` replaces every fallback type with the
+// corresponding type in `portable-atomic`.
+#[cfg(all(feature = "portable-atomic", not(force_fallback), not(doc)))]
+const _: fn() = || {
+    #[allow(unused_macros)]
+    macro_rules! check {
+        ($atomic:ident, $int:ident, $($cfg:tt)*) => {
+            #[cfg(not(any(feature = "portable-atomic-all", $($cfg)*)))]
+            let _: fn($atomic) -> portable_atomic::$atomic = |a| a;
+        };
+    }
+
+    #[cfg(feature = "primitives")]
+    with_primitive_atomics!(check);
+};
+
+// Ensures that `force-fallback` routes every alias to a fallback type.
+#[cfg(all(force_fallback, feature = "primitives", not(doc)))]
+const _: () = {
+    macro_rules! check {
+        ($atomic:ident, $($rest:tt)*) => {
+            assert!(!<$atomic as AnyAtomic>::IS_ALWAYS_LOCK_FREE);
+        };
+    }
+
+    with_primitive_atomics!(check);
+    assert!(!<AtomicPtr<u8> as AnyAtomic>::IS_ALWAYS_LOCK_FREE);
+};
 
 #[rustfmt::skip]
 #[cfg(doc)]
@@ -223,9 +1012,43 @@ mod fallback;
 /// signals while the spinlock is held.
 ///
 /// The API of this type is designed to be compatible with the atomic integer
-/// types in [`core::sync::atomic`].
+/// types in [`core::sync::atomic`]. Like those types, this type is aligned to
+/// (at least) the size of its integer, so code that relies on the alignment
+/// of atomics continues to work when the fallback is used. With the feature
+/// `striped`, this type consists only of its integer, so it also has the
+/// same size. This is guaranteed whatever other features are enabled, as
+/// `seqlock` then keeps its counter in the stripes too, and `poisoning`
+/// can't be enabled together with `striped`. The same is true with the
+/// feature `critical-section`, unless `poisoning` is enabled:
+///
+#[cfg_attr(all(compact_fallback, fallback_128), doc = "```")]
+#[cfg_attr(not(all(compact_fallback, fallback_128)), doc = "```ignore")]
+/// use atomic_int::AtomicU128;
+/// use std::mem::{align_of, size_of};
+///
+/// // `AtomicU128` is a fallback type on most targets.
+/// assert_eq!(size_of::<AtomicU128>(), size_of::<u128>());
+/// assert_eq!(align_of::<AtomicU128>(), size_of::<u128>());
+/// ```
 ///
 /// This type is exposed only in the documentation for illustrative purposes.
+///
+/// # Auto traits
+///
+/// Like the built-in atomics, every atomic type in this crate, whether
+/// fallback or built-in, is [`Send`] and [`Sync`] (except for the
+/// single-threaded types in the module `cell`, which are not [`Sync`]), and
+/// none of them is [`Clone`] or [`Copy`]:
+///
+/// ```compile_fail
+/// fn assert_clone<T: Clone>() {}
+/// assert_clone::<atomic_int::AtomicI128>();
+/// ```
+///
+/// ```compile_fail
+/// fn assert_copy<T: Copy>() {}
+/// assert_copy::<atomic_int::AtomicCInt>();
+/// ```
 pub use fallback::AtomicFallback;
 
 #[rustfmt::skip]
@@ -239,4 +1062,80 @@ pub use fallback::AtomicFallback;
 /// This type is the pointer version of [`AtomicFallback`]; see its
 /// documentation for more details. Like [`AtomicFallback`], this type is
 /// exposed only in the documentation for illustrative purposes.
+///
+/// Like [`AtomicPtr`](atomic::AtomicPtr) in [`core::sync::atomic`], this type
+/// is [`Send`] and [`Sync`] regardless of `T`.
+///
+/// # Memory ordering
+///
+/// Every operation on a fallback atomic acquires its spinlock with at least
+/// [`Acquire`] ordering and releases it with at least [`Release`] ordering,
+/// so orderings behave at least as strongly as they would on a built-in
+/// atomic. In particular, [`swap`] with [`AcqRel`] is suitable for RCU-style
+/// updates, where a new object is published and the old one is reclaimed:
+///
+/// ```
+/// use atomic_int::AtomicPtr;
+/// use std::sync::atomic::Ordering;
+///
+/// let current = AtomicPtr::new(Box::into_raw(Box::new(1)));
+/// // `Release` publishes the initialization of the new object, and `Acquire`
+/// // makes prior writes to the old object visible before it is freed.
+/// let old = current.swap(Box::into_raw(Box::new(2)), Ordering::AcqRel);
+/// // SAFETY: `old` came from `Box::into_raw` and is no longer reachable.
+/// assert_eq!(*unsafe { Box::from_raw(old) }, 1);
+/// # drop(unsafe { Box::from_raw(current.into_inner()) });
+/// ```
+///
+/// [`Acquire`]: atomic::Ordering::Acquire
+/// [`Release`]: atomic::Ordering::Release
+/// [`AcqRel`]: atomic::Ordering::AcqRel
+/// [`swap`]: AtomicFallbackPtr::swap
 pub use fallback::AtomicFallbackPtr;
+
+#[cfg(feature = "wide")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "wide")))]
+/// A 256-bit unsigned atomic integer.
+///
+/// No platform provides 256-bit atomics, so this type always uses the
+/// fallback implementation; see [`AtomicFallback`] for details. Its
+/// values are [`U256`]s.
+///
+/// ```
+/// use atomic_int::{AtomicU256, U256};
+/// use std::sync::atomic::Ordering;
+///
+/// // A bitmap of 256 slots.
+/// let slots = AtomicU256::new(U256::ZERO);
+/// assert!(!slots.fetch_bit_set(200, Ordering::AcqRel));
+/// assert!(slots.fetch_bit_set(200, Ordering::AcqRel));
+/// slots.fetch_or(U256::ONE << 3, Ordering::AcqRel);
+/// assert_eq!(slots.load(Ordering::Acquire).count_ones(), 2);
+///
+/// let max = AtomicU256::new(U256::MAX);
+/// assert_eq!(max.fetch_add(U256::ONE, Ordering::Relaxed), U256::MAX);
+/// assert_eq!(max.load(Ordering::Relaxed), U256::ZERO);
+/// ```
+pub use fallback::AtomicU256;
+
+#[cfg(feature = "wide")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "wide")))]
+/// A 256-bit signed atomic integer.
+///
+/// Like [`AtomicU256`], this type always uses the fallback
+/// implementation. Its values are [`I256`]s.
+///
+/// ```
+/// use atomic_int::{AtomicI256, AtomicIntExt, I256};
+/// use std::sync::atomic::Ordering;
+///
+/// let a = AtomicI256::new(I256::MAX);
+/// assert_eq!(a.fetch_checked_add(I256::ONE, Ordering::Relaxed), None);
+/// a.fetch_sub(I256::from(-1_i64), Ordering::Relaxed);
+/// assert_eq!(a.load(Ordering::Relaxed), I256::MIN);
+/// a.fetch_saturating_sub(I256::ONE, Ordering::Relaxed);
+/// assert_eq!(a.load(Ordering::Relaxed), I256::MIN);
+/// a.store(I256::from(-42_i8), Ordering::Relaxed);
+/// assert_eq!(format!("{:?}", a), "-42");
+/// ```
+pub use fallback::AtomicI256;
@@ -0,0 +1,512 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::fallback::cas_order;
+#[cfg(all(not(all(spinlock, not(loom))), not(no_atomic_bool)))]
+use crate::fallback::{acquire_order, release_order};
+use crate::generic::{Plain, same_bits};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::ManuallyDrop;
+use core::ptr;
+#[cfg(not(no_atomic_bool))]
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering;
+
+/// A lock that protects the value of a [`LockedAtomic`].
+///
+/// The fallback types always use the lock selected by the crate features
+/// (e.g., a spinlock that blocks signals with the feature `signal`). A
+/// [`LockedAtomic`] instead uses the strategy given by its type parameter,
+/// so different atomics in the same program can be protected differently.
+/// This crate provides `SpinLock` and `SignalSafeSpinLock` (with the
+/// feature `signal-mask`), except on targets without atomic
+/// read-modify-write operations on `AtomicBool`, as well as
+/// `CriticalSection` (with the feature `critical-section`) and `OsMutex`
+/// (with the feature `std`).
+///
+/// # Safety
+///
+/// While a token returned by [`Self::lock`] for a state has not been passed
+/// to [`Self::unlock`], no call to [`Self::lock`] for the same state on
+/// another thread may return. Unlocking a state must synchronize with the
+/// next locking of it, as a [`Release`] store synchronizes with an
+/// [`Acquire`] load that reads it. If the operation’s ordering is
+/// [`SeqCst`], locking and unlocking must also take part in the single total
+/// order of [`SeqCst`] operations, e.g., by issuing [`SeqCst`] fences.
+///
+/// [`Release`]: Ordering::Release
+/// [`Acquire`]: Ordering::Acquire
+/// [`SeqCst`]: Ordering::SeqCst
+pub unsafe trait LockStrategy {
+    /// The lock state stored in each atomic.
+    type State;
+
+    /// The initial, unlocked state.
+    const INIT: Self::State;
+
+    /// A value held while the lock is held, such as the signal mask to
+    /// restore when it is released.
+    type Token;
+
+    /// Acquires the lock for an operation performed with ordering `order`.
+    ///
+    /// # Safety
+    ///
+    /// The returned token must be passed to [`Self::unlock`] for `state` on
+    /// the current thread, as described there, and must not be dropped or
+    /// leaked instead: a strategy like `CriticalSection` relies on every
+    /// lock being released in order.
+    unsafe fn lock(state: &Self::State, order: Ordering) -> Self::Token;
+
+    /// Releases the lock after an operation performed with ordering `order`.
+    ///
+    /// # Safety
+    ///
+    /// `token` must have been returned by [`Self::lock`] for `state` on the
+    /// current thread, and tokens must be passed to this function in the
+    /// reverse order in which they were obtained.
+    unsafe fn unlock(state: &Self::State, token: Self::Token, order: Ordering);
+}
+
+/// A spinlock, like the one that protects the fallback types by default.
+///
+/// Waiting threads spin as configured by the features `backoff`,
+/// `exponential-backoff`, and `adaptive`. Unlike the fallback types, this
+/// never blocks signals, even with the feature `signal`.
+///
+/// This type isn't available on targets without atomic read-modify-write
+/// operations on `AtomicBool`, like `thumbv6m` and `riscv32i`.
+#[cfg(not(no_atomic_bool))]
+#[derive(Clone, Copy, Debug)]
+pub struct SpinLock(());
+
+#[cfg(all(spinlock, not(loom), not(no_atomic_bool)))]
+use crate::fallback::{acquire, release};
+
+#[cfg(all(not(all(spinlock, not(loom))), not(no_atomic_bool)))]
+fn acquire(lock: &AtomicBool, order: Ordering) {
+    while lock
+        .compare_exchange_weak(
+            false,
+            true,
+            acquire_order(order),
+            Ordering::Relaxed,
+        )
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+}
+
+#[cfg(all(not(all(spinlock, not(loom))), not(no_atomic_bool)))]
+fn release(lock: &AtomicBool, order: Ordering) {
+    lock.store(false, release_order(order));
+}
+
+// SAFETY: `acquire` and `release` implement a spinlock with the required
+// orderings.
+#[cfg(not(no_atomic_bool))]
+unsafe impl LockStrategy for SpinLock {
+    type State = AtomicBool;
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: AtomicBool = AtomicBool::new(false);
+    type Token = ();
+
+    unsafe fn lock(state: &AtomicBool, order: Ordering) {
+        acquire(state, order);
+    }
+
+    unsafe fn unlock(state: &AtomicBool, _: (), order: Ordering) {
+        release(state, order);
+    }
+}
+
+/// A [`SpinLock`] that blocks incoming signals while it is held, like the
+/// fallback types with the feature `signal`.
+///
+/// This allows an atomic to be used from signal handlers without making
+/// every fallback type block signals. As with the feature `signal`, nothing
+/// is blocked on Windows, and [`signal::set_blocked_signals`] selects the
+/// signals that are blocked.
+///
+/// [`signal::set_blocked_signals`]: crate::signal::set_blocked_signals
+#[cfg(all(feature = "signal-mask", not(no_atomic_bool)))]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "signal-mask")))]
+#[derive(Clone, Copy, Debug)]
+pub struct SignalSafeSpinLock(());
+
+#[cfg(all(feature = "signal-mask", not(windows), not(no_atomic_bool)))]
+type SignalGuard = crate::fallback::sigmask::SignalGuard;
+
+#[cfg(all(feature = "signal-mask", windows, not(no_atomic_bool)))]
+type SignalGuard = ();
+
+// SAFETY: This is a `SpinLock` that also holds a signal guard, which is
+// created before the lock is acquired and dropped after it is released.
+#[cfg(all(feature = "signal-mask", not(no_atomic_bool)))]
+unsafe impl LockStrategy for SignalSafeSpinLock {
+    type State = AtomicBool;
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: AtomicBool = AtomicBool::new(false);
+    type Token = SignalGuard;
+
+    unsafe fn lock(state: &AtomicBool, order: Ordering) -> SignalGuard {
+        #[cfg(not(windows))]
+        let guard = SignalGuard::new();
+        #[cfg(windows)]
+        let guard = ();
+        acquire(state, order);
+        guard
+    }
+
+    unsafe fn unlock(
+        state: &AtomicBool,
+        _guard: SignalGuard,
+        order: Ordering,
+    ) {
+        // `_guard` is dropped, restoring the signal mask, after this.
+        release(state, order);
+    }
+}
+
+/// Runs every operation in a [`critical_section`], like the fallback types
+/// with the feature `critical-section`.
+///
+/// The atomic contains no lock of its own. Critical sections nest, so an
+/// atomic that uses this strategy may be used from the closure passed to
+/// another one’s `fetch_update`.
+///
+/// [`critical_section`]: https://docs.rs/critical-section/1
+#[cfg(feature = "critical-section")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "critical-section")))]
+#[derive(Clone, Copy, Debug)]
+pub struct CriticalSection(());
+
+// SAFETY: The `critical-section` implementation provides mutual exclusion
+// across threads and the required synchronization. The fences handle
+// `SeqCst`.
+#[cfg(feature = "critical-section")]
+unsafe impl LockStrategy for CriticalSection {
+    type State = ();
+    const INIT: () = ();
+    type Token = critical_section::RestoreState;
+
+    unsafe fn lock(_: &(), order: Ordering) -> Self::Token {
+        // SAFETY: The caller guarantees that the token is passed to
+        // `unlock`, which releases the critical section in the correct
+        // order.
+        let token = unsafe { critical_section::acquire() };
+        if order == Ordering::SeqCst {
+            core::sync::atomic::fence(Ordering::SeqCst);
+        }
+        token
+    }
+
+    unsafe fn unlock(_: &(), token: Self::Token, order: Ordering) {
+        if order == Ordering::SeqCst {
+            core::sync::atomic::fence(Ordering::SeqCst);
+        }
+        // SAFETY: Checked by the caller.
+        unsafe {
+            critical_section::release(token);
+        }
+    }
+}
+
+/// A lock that puts waiting threads to sleep using the standard library’s
+/// [`Mutex`](std::sync::Mutex) and [`Condvar`](std::sync::Condvar), for
+/// atomics that may be held for a long time (e.g., by a slow
+/// `fetch_update`).
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+#[derive(Clone, Copy, Debug)]
+pub struct OsMutex(());
+
+// SAFETY: The flag is protected by the mutex, whose locking and unlocking
+// provide the required synchronization. The fences handle `SeqCst`.
+#[cfg(feature = "std")]
+unsafe impl LockStrategy for OsMutex {
+    /// Whether the lock is held, and a condition variable that is notified
+    /// when it is released.
+    type State = (std::sync::Mutex<bool>, std::sync::Condvar);
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self::State =
+        (std::sync::Mutex::new(false), std::sync::Condvar::new());
+    type Token = ();
+
+    unsafe fn lock((mutex, released): &Self::State, order: Ordering) {
+        let relock = |e: std::sync::PoisonError<_>| e.into_inner();
+        let mut locked = mutex.lock().unwrap_or_else(relock);
+        while *locked {
+            locked = released.wait(locked).unwrap_or_else(relock);
+        }
+        *locked = true;
+        drop(locked);
+        if order == Ordering::SeqCst {
+            core::sync::atomic::fence(Ordering::SeqCst);
+        }
+    }
+
+    unsafe fn unlock((mutex, released): &Self::State, _: (), order: Ordering) {
+        if order == Ordering::SeqCst {
+            core::sync::atomic::fence(Ordering::SeqCst);
+        }
+        let relock = |e: std::sync::PoisonError<_>| e.into_inner();
+        *mutex.lock().unwrap_or_else(relock) = false;
+        released.notify_one();
+    }
+}
+
+/// An atomic version of any [`Plain`] type, protected by a lock chosen with
+/// the type parameter `S`.
+///
+/// Unlike the fallback types, whose lock is selected by the crate features,
+/// this type lets each atomic choose its own [`LockStrategy`], and unlike
+/// [`Atomic<T>`](crate::Atomic), it always uses its lock, even when a
+/// built-in atomic could hold the value. For example, in a program where
+/// only a few atomics are used by signal handlers, those atomics can block
+/// signals while the others don’t:
+///
+/// ```
+/// use atomic_int::{LockedAtomic, SpinLock};
+/// use std::sync::atomic::Ordering;
+///
+/// // Used only by ordinary code.
+/// static TOTAL: LockedAtomic<u128, SpinLock> = LockedAtomic::new(0);
+/// TOTAL.fetch_update(Ordering::AcqRel, Ordering::Acquire, |v| Some(v + 1))
+///     .unwrap();
+/// assert_eq!(TOTAL.load(Ordering::Acquire), 1);
+///
+/// # #[cfg(feature = "signal-mask")]
+/// # {
+/// use atomic_int::SignalSafeSpinLock;
+///
+/// // Also used by a signal handler.
+/// static LAST: LockedAtomic<[u64; 2], SignalSafeSpinLock> =
+///     LockedAtomic::new([0; 2]);
+/// LAST.store([1, 2], Ordering::Release);
+/// assert_eq!(LAST.load(Ordering::Acquire), [1, 2]);
+/// # }
+/// ```
+///
+/// The features that affect the fallback types’ locks in other ways (e.g.,
+/// `poisoning`, `hooks`, and `trace`) don’t apply to this type.
+#[cfg(not(no_atomic_bool))]
+pub struct LockedAtomic<T, S: LockStrategy = SpinLock> {
+    value: UnsafeCell<T>,
+    state: S::State,
+}
+
+/// An atomic version of any [`Plain`] type, protected by a lock chosen with
+/// the type parameter `S`.
+///
+/// This target lacks the atomic read-modify-write operations on `AtomicBool`
+/// that `SpinLock` needs, so unlike on other targets, `S` has no default.
+#[cfg(no_atomic_bool)]
+pub struct LockedAtomic<T, S: LockStrategy> {
+    value: UnsafeCell<T>,
+    state: S::State,
+}
+
+// SAFETY: Every access to the value happens while holding the lock, which
+// is shared between threads, so it must be `Sync` too.
+unsafe impl<T: Send, S: LockStrategy> Sync for LockedAtomic<T, S> where
+    S::State: Sync
+{
+}
+
+/// Releases the lock when dropped, including when the closure passed to
+/// `fetch_update` panics.
+struct Unlock<'a, S: LockStrategy> {
+    state: &'a S::State,
+    token: ManuallyDrop<S::Token>,
+    order: Ordering,
+}
+
+impl<S: LockStrategy> Drop for Unlock<'_, S> {
+    fn drop(&mut self) {
+        // SAFETY: `self.token` is not used again, and was returned by
+        // `S::lock` when `self` was created. `Unlock` objects are local to
+        // `LockedAtomic::locked`, so they are dropped in reverse order.
+        unsafe {
+            let token = ManuallyDrop::take(&mut self.token);
+            S::unlock(self.state, token, self.order);
+        }
+    }
+}
+
+impl<T, S: LockStrategy> LockedAtomic<T, S> {
+    /// Creates a new atomic.
+    pub const fn new(v: T) -> Self {
+        Self {
+            value: UnsafeCell::new(v),
+            state: S::INIT,
+        }
+    }
+
+    /// Whether this type is always lock-free, which is never the case.
+    pub const IS_ALWAYS_LOCK_FREE: bool = false;
+
+    /// Returns whether operations on this type are lock-free, which is never
+    /// the case.
+    pub const fn is_lock_free() -> bool {
+        Self::IS_ALWAYS_LOCK_FREE
+    }
+
+    /// Returns a mutable reference to the value.
+    ///
+    /// This does not acquire the lock: the mutable reference guarantees that
+    /// no other thread can access the atomic.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Consumes the atomic and returns the value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    /// Calls `f` with a pointer to the value while holding the lock.
+    ///
+    /// `f` must not keep a reference to the value while it calls code that
+    /// could access the atomic, which a reentrant strategy like
+    /// `CriticalSection` would allow.
+    fn locked<R>(&self, order: Ordering, f: impl FnOnce(*mut T) -> R) -> R {
+        let _unlock = Unlock::<S> {
+            state: &self.state,
+            // SAFETY: The token is stored in an `Unlock` local to this
+            // function, which passes it to `S::unlock` when dropped.
+            token: ManuallyDrop::new(unsafe { S::lock(&self.state, order) }),
+            order,
+        };
+        f(self.value.get())
+    }
+}
+
+impl<T: Plain, S: LockStrategy> LockedAtomic<T, S> {
+    /// Loads the value.
+    pub fn load(&self, order: Ordering) -> T {
+        // SAFETY: The lock is held.
+        self.locked(order, |p| unsafe { ptr::read(p) })
+    }
+
+    /// Stores a value.
+    pub fn store(&self, val: T, order: Ordering) {
+        // SAFETY: The lock is held.
+        self.locked(order, |p| unsafe { ptr::write(p, val) })
+    }
+
+    /// Stores a value, returning the previous value.
+    pub fn swap(&self, val: T, order: Ordering) -> T {
+        // SAFETY: The lock is held.
+        self.locked(order, |p| unsafe { ptr::replace(p, val) })
+    }
+
+    /// Stores `new` if the current value has the same bit pattern as
+    /// `current`, returning the previous value.
+    pub fn compare_exchange(
+        &self,
+        current: T,
+        new: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        self.locked(cas_order(success, failure), |p| {
+            // SAFETY: The lock is held.
+            let prev = unsafe { ptr::read(p) };
+            if same_bits(&prev, &current) {
+                // SAFETY: The lock is held.
+                unsafe { ptr::write(p, new) };
+                Ok(prev)
+            } else {
+                Err(prev)
+            }
+        })
+    }
+
+    /// Like [`Self::compare_exchange`], but may fail spuriously (though this
+    /// implementation never does).
+    pub fn compare_exchange_weak(
+        &self,
+        current: T,
+        new: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        self.compare_exchange(current, new, success, failure)
+    }
+
+    /// Fetches the value, and applies a function to it that returns an
+    /// optional new value.
+    ///
+    /// Like the fallback types, this calls `f` exactly once, while holding
+    /// the lock, so `f` must not use this atomic (unless the strategy is
+    /// reentrant, like `CriticalSection`).
+    pub fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: F,
+    ) -> Result<T, T>
+    where
+        F: FnMut(T) -> Option<T>,
+    {
+        self.locked(cas_order(set_order, fetch_order), |p| {
+            // SAFETY: The lock is held.
+            let prev = unsafe { ptr::read(p) };
+            match f(prev) {
+                Some(value) => {
+                    // SAFETY: The lock is held.
+                    unsafe { ptr::write(p, value) };
+                    Ok(prev)
+                }
+                None => Err(prev),
+            }
+        })
+    }
+}
+
+impl<T: Plain, S: LockStrategy> crate::ext::sealed::Sealed
+    for LockedAtomic<T, S>
+{
+}
+
+impl_any_atomic!([T: Plain, S: LockStrategy] LockedAtomic<T, S>, T, false);
+
+impl_serde!([T: Plain, L: LockStrategy] LockedAtomic<T, L>, T);
+
+impl<T: Default, S: LockStrategy> Default for LockedAtomic<T, S> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T, S: LockStrategy> From<T> for LockedAtomic<T, S> {
+    fn from(v: T) -> Self {
+        Self::new(v)
+    }
+}
+
+impl<T: Plain + fmt::Debug, S: LockStrategy> fmt::Debug
+    for LockedAtomic<T, S>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.load(Ordering::SeqCst), f)
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::{AtomicIntExt, HasAtomic};
+use core::fmt;
+use core::sync::atomic::Ordering;
+
+/// A running minimum and maximum of the integers recorded in it.
+///
+/// The minimum and maximum are stored in two atomics of the alias for `T` in
+/// this crate (e.g., [`AtomicU32`](crate::AtomicU32) for `u32`), and
+/// [`record`](Self::record) updates them with
+/// [`fetch_min`](AtomicIntExt::fetch_min) and
+/// [`fetch_max`](AtomicIntExt::fetch_max). Where these aliases are built-in
+/// atomics, every operation is thus lock-free and safe to perform from
+/// signal handlers and real-time threads.
+///
+/// The two atomics are updated separately, so a reader that runs
+/// concurrently with [`record`](Self::record) or [`reset`](Self::reset) may
+/// see the new minimum together with the old maximum (or vice versa).
+///
+/// ```
+/// use atomic_int::AtomicMinMax;
+/// use std::sync::atomic::Ordering;
+///
+/// // Latency watermarks, in microseconds.
+/// let latency = AtomicMinMax::<u32>::new();
+/// assert_eq!(latency.min(Ordering::Relaxed), None);
+/// for sample in [250, 120, 900, 310] {
+///     latency.record(sample, Ordering::Relaxed);
+/// }
+/// assert_eq!(latency.min(Ordering::Relaxed), Some(120));
+/// assert_eq!(latency.max(Ordering::Relaxed), Some(900));
+///
+/// latency.reset(Ordering::Relaxed);
+/// assert_eq!(latency.max(Ordering::Relaxed), None);
+/// ```
+pub struct AtomicMinMax<T: HasAtomic> {
+    min: T::Atomic,
+    max: T::Atomic,
+}
+
+impl<T: HasAtomic> AtomicMinMax<T> {
+    /// Creates a new accumulator with no values recorded.
+    pub fn new() -> Self {
+        Self {
+            min: T::Atomic::new(T::MAX),
+            max: T::Atomic::new(T::MIN),
+        }
+    }
+
+    /// Records `value`, updating the minimum and maximum.
+    pub fn record(&self, value: T, order: Ordering) {
+        self.min.fetch_min(value, order);
+        self.max.fetch_max(value, order);
+    }
+
+    /// Returns the smallest value recorded, or [`None`] if no values have
+    /// been recorded since the accumulator was created or last reset.
+    pub fn min(&self, order: Ordering) -> Option<T> {
+        self.min_max(order).map(|(min, _)| min)
+    }
+
+    /// Returns the largest value recorded, or [`None`] if no values have
+    /// been recorded since the accumulator was created or last reset.
+    pub fn max(&self, order: Ordering) -> Option<T> {
+        self.min_max(order).map(|(_, max)| max)
+    }
+
+    /// Returns the smallest and largest values recorded, or [`None`] if no
+    /// values have been recorded since the accumulator was created or last
+    /// reset.
+    pub fn min_max(&self, order: Ordering) -> Option<(T, T)> {
+        let min = self.min.load(order);
+        let max = self.max.load(order);
+        // Until a value is recorded, the minimum is `T::MAX` and the maximum
+        // is `T::MIN`.
+        (min <= max).then_some((min, max))
+    }
+
+    /// Forgets all recorded values.
+    pub fn reset(&self, order: Ordering) {
+        self.min.store(T::MAX, order);
+        self.max.store(T::MIN, order);
+    }
+
+    /// Consumes the accumulator and returns the smallest and largest values
+    /// recorded, as with [`Self::min_max`].
+    pub fn into_inner(self) -> Option<(T, T)> {
+        let min = self.min.into_inner();
+        let max = self.max.into_inner();
+        (min <= max).then_some((min, max))
+    }
+}
+
+impl<T: HasAtomic> Default for AtomicMinMax<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: HasAtomic> fmt::Debug for AtomicMinMax<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let min_max = self.min_max(Ordering::SeqCst);
+        f.debug_struct("AtomicMinMax")
+            .field("min", &min_max.map(|(min, _)| min))
+            .field("max", &min_max.map(|(_, max)| max))
+            .finish()
+    }
+}
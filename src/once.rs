@@ -0,0 +1,214 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hint::spin_loop;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+
+/// A cell that is initialized at most once, like
+/// [`OnceLock`](https://doc.rust-lang.org/std/sync/struct.OnceLock.html), but
+/// available in `no_std` code.
+///
+/// The state of the cell is a built-in `AtomicU8`, so reading an initialized
+/// cell never takes a lock. Like [`AtomicFlag`](crate::AtomicFlag), this type
+/// is therefore unavailable on targets without atomic read-modify-write
+/// operations on [`AtomicBool`](core::sync::atomic::AtomicBool) and
+/// `AtomicU8`. While one thread runs the initialization function, other
+/// threads that need the value spin until it finishes. If the function
+/// panics, the cell is left uninitialized, and the next caller runs its own
+/// function.
+///
+/// ```
+/// use atomic_int::AtomicOnce;
+///
+/// static CONFIG: AtomicOnce<u32> = AtomicOnce::new();
+///
+/// assert_eq!(CONFIG.get(), None);
+/// assert_eq!(*CONFIG.get_or_init(|| 42), 42);
+/// assert_eq!(*CONFIG.get_or_init(|| 0), 42);
+/// assert_eq!(CONFIG.set(1), Err(1));
+/// assert_eq!(CONFIG.get(), Some(&42));
+/// ```
+pub struct AtomicOnce<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: The value is written only by the thread that moves `state` from
+// `INCOMPLETE` to `RUNNING`, and shared only after `state` is `COMPLETE`,
+// so sharing the cell shares `&T` (which requires `T: Sync`) and may move
+// the value to another thread's initialization (which requires `T: Send`).
+unsafe impl<T: Send + Sync> Sync for AtomicOnce<T> {}
+
+// SAFETY: The cell owns its value.
+unsafe impl<T: Send> Send for AtomicOnce<T> {}
+
+/// Resets the state to `INCOMPLETE` if the initialization function panics.
+struct Reset<'a>(&'a AtomicU8);
+
+impl Drop for Reset<'_> {
+    fn drop(&mut self) {
+        self.0.store(INCOMPLETE, Ordering::Release);
+    }
+}
+
+impl<T> AtomicOnce<T> {
+    /// Creates a new, uninitialized cell.
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(INCOMPLETE),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns the value, or [`None`] if the cell is uninitialized or is
+    /// being initialized.
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == COMPLETE {
+            // SAFETY: The value was initialized before `state` was set to
+            // `COMPLETE`, and it is never modified afterward.
+            Some(unsafe { self.get_unchecked() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the value, or [`None`] if the cell is
+    /// uninitialized.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if *self.state.get_mut() == COMPLETE {
+            // SAFETY: The value is initialized.
+            Some(unsafe { (*self.value.get()).assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value, calling `f` to initialize the cell first if it is
+    /// uninitialized.
+    ///
+    /// If another thread is initializing the cell, this waits until it is
+    /// done. `f` must not call this method on the same cell, as that would
+    /// spin forever.
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        let mut f = Some(f);
+        loop {
+            match self.state.compare_exchange_weak(
+                INCOMPLETE,
+                RUNNING,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    let reset = Reset(&self.state);
+                    let f = f.take().unwrap();
+                    // SAFETY: Only this thread can access the value while
+                    // `state` is `RUNNING`.
+                    unsafe {
+                        (*self.value.get()).write(f());
+                    }
+                    core::mem::forget(reset);
+                    self.state.store(COMPLETE, Ordering::Release);
+                    // SAFETY: The value was just initialized.
+                    return unsafe { self.get_unchecked() };
+                }
+                // SAFETY: See `Self::get`.
+                Err(COMPLETE) => return unsafe { self.get_unchecked() },
+                Err(_) => spin_loop(),
+            }
+        }
+    }
+
+    /// Initializes the cell with `value`, or returns `Err(value)` if the
+    /// cell is already initialized.
+    ///
+    /// If another thread is initializing the cell, this waits until it is
+    /// done, and then fails.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        self.get_or_init(|| value.take().unwrap());
+        value.map_or(Ok(()), Err)
+    }
+
+    /// Consumes the cell, returning the value, or [`None`] if the cell is
+    /// uninitialized.
+    pub fn into_inner(mut self) -> Option<T> {
+        let complete = *self.state.get_mut() == COMPLETE;
+        *self.state.get_mut() = INCOMPLETE;
+        // SAFETY: The value is initialized if `state` was `COMPLETE`, and
+        // resetting `state` prevents `Drop` from dropping it again.
+        complete.then(|| unsafe { (*self.value.get()).assume_init_read() })
+    }
+
+    /// # Safety
+    ///
+    /// The value must be initialized.
+    unsafe fn get_unchecked(&self) -> &T {
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+}
+
+impl<T> Drop for AtomicOnce<T> {
+    fn drop(&mut self) {
+        if let Some(value) = self.get_mut() {
+            // SAFETY: The value is initialized and is not used again.
+            unsafe {
+                core::ptr::drop_in_place(value);
+            }
+        }
+    }
+}
+
+impl<T> Default for AtomicOnce<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<T> for AtomicOnce<T> {
+    fn from(value: T) -> Self {
+        Self {
+            state: AtomicU8::new(COMPLETE),
+            value: UnsafeCell::new(MaybeUninit::new(value)),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for AtomicOnce<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tuple = f.debug_tuple("AtomicOnce");
+        match self.get() {
+            Some(value) => tuple.field(value),
+            None => tuple.field(&format_args!("<uninit>")),
+        };
+        tuple.finish()
+    }
+}
+
+// SAFETY: Zero is the `INCOMPLETE` state, in which the value is
+// uninitialized.
+impl_zeroable!([T] AtomicOnce<T>);
@@ -0,0 +1,393 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::AtomicPtr;
+use crate::PrimInt;
+use core::fmt;
+use core::num::{NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128};
+use core::num::{NonZeroIsize, NonZeroUsize};
+use core::num::{NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128};
+use core::ptr::{self, NonNull};
+use core::sync::atomic::Ordering;
+
+const fn into_ptr<T>(v: Option<NonNull<T>>) -> *mut T {
+    match v {
+        Some(p) => p.as_ptr(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// An atomic optional pointer.
+///
+/// This type stores an <code>[Option]<[NonNull]\<T>></code> in an
+/// [`AtomicPtr`], using a null pointer to represent [`None`]. Like
+/// [`AtomicPtr`], it uses the built-in atomic where available and a
+/// spinlock-based fallback otherwise.
+#[repr(transparent)]
+pub struct AtomicOption<T> {
+    ptr: AtomicPtr<T>,
+}
+
+impl<T> AtomicOption<T> {
+    /// Creates a new atomic optional pointer.
+    pub const fn new(v: Option<NonNull<T>>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(into_ptr(v)),
+        }
+    }
+
+    /// Creates a new atomic optional pointer containing [`None`].
+    pub const fn none() -> Self {
+        Self::new(None)
+    }
+
+    /// Returns a mutable reference to the underlying value.
+    pub fn get_mut(&mut self) -> &mut Option<NonNull<T>> {
+        let ptr: *mut *mut T = self.ptr.get_mut();
+        // SAFETY: `Option<NonNull<T>>` has the same layout as `*mut T`, with
+        // `None` represented by a null pointer.
+        unsafe { &mut *ptr.cast() }
+    }
+
+    /// Consumes the atomic and returns the contained value.
+    pub fn into_inner(self) -> Option<NonNull<T>> {
+        NonNull::new(self.ptr.into_inner())
+    }
+
+    /// Loads the value.
+    pub fn load(&self, order: Ordering) -> Option<NonNull<T>> {
+        NonNull::new(self.ptr.load(order))
+    }
+
+    /// Stores a value.
+    pub fn store(&self, v: Option<NonNull<T>>, order: Ordering) {
+        self.ptr.store(into_ptr(v), order);
+    }
+
+    /// Stores a value, returning the previous value.
+    pub fn swap(
+        &self,
+        v: Option<NonNull<T>>,
+        order: Ordering,
+    ) -> Option<NonNull<T>> {
+        NonNull::new(self.ptr.swap(into_ptr(v), order))
+    }
+
+    /// Takes the value, leaving [`None`] in its place.
+    ///
+    /// If multiple threads call this method concurrently, at most one of
+    /// them will receive any given [`Some`] value:
+    ///
+    /// ```
+    /// use atomic_int::AtomicOption;
+    /// use std::ptr::NonNull;
+    /// use std::sync::atomic::Ordering;
+    /// use std::thread;
+    ///
+    /// let ptr = NonNull::from(Box::leak(Box::new(5)));
+    /// let option = AtomicOption::new(Some(ptr));
+    /// let taken: Vec<_> = thread::scope(|s| {
+    ///     let threads: Vec<_> = (0..8)
+    ///         .map(|_| s.spawn(|| option.take(Ordering::AcqRel).is_some()))
+    ///         .collect();
+    ///     threads.into_iter().map(|t| t.join().unwrap()).collect()
+    /// });
+    /// assert_eq!(taken.iter().filter(|&&t| t).count(), 1);
+    /// assert_eq!(option.into_inner(), None);
+    /// // SAFETY: `ptr` came from `Box::leak`.
+    /// drop(unsafe { Box::from_raw(ptr.as_ptr()) });
+    /// ```
+    pub fn take(&self, order: Ordering) -> Option<NonNull<T>> {
+        self.swap(None, order)
+    }
+
+    /// Stores [`Some(v)`](Some), returning the previous value.
+    pub fn replace(
+        &self,
+        v: NonNull<T>,
+        order: Ordering,
+    ) -> Option<NonNull<T>> {
+        self.swap(Some(v), order)
+    }
+
+    /// Stores a value if the current value is the same as `current`.
+    ///
+    /// See [`AtomicPtr::compare_exchange`][cmpxchg].
+    ///
+    /// [cmpxchg]: core::sync::atomic::AtomicPtr::compare_exchange
+    pub fn compare_exchange(
+        &self,
+        current: Option<NonNull<T>>,
+        new: Option<NonNull<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Option<NonNull<T>>, Option<NonNull<T>>> {
+        self.ptr
+            .compare_exchange(
+                into_ptr(current),
+                into_ptr(new),
+                success,
+                failure,
+            )
+            .map(NonNull::new)
+            .map_err(NonNull::new)
+    }
+
+    /// Stores a value if the current value is the same as `current`. This
+    /// method may fail spuriously.
+    ///
+    /// See [`AtomicPtr::compare_exchange_weak`][cmpxchg].
+    ///
+    /// [cmpxchg]: core::sync::atomic::AtomicPtr::compare_exchange_weak
+    pub fn compare_exchange_weak(
+        &self,
+        current: Option<NonNull<T>>,
+        new: Option<NonNull<T>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Option<NonNull<T>>, Option<NonNull<T>>> {
+        self.ptr
+            .compare_exchange_weak(
+                into_ptr(current),
+                into_ptr(new),
+                success,
+                failure,
+            )
+            .map(NonNull::new)
+            .map_err(NonNull::new)
+    }
+}
+
+impl<T> Default for AtomicOption<T> {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl<T> From<Option<NonNull<T>>> for AtomicOption<T> {
+    fn from(v: Option<NonNull<T>>) -> Self {
+        Self::new(v)
+    }
+}
+
+impl<T> fmt::Debug for AtomicOption<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.load(Ordering::SeqCst).fmt(f)
+    }
+}
+
+// SAFETY: Zero is a null pointer, which represents `None`.
+impl_zeroable!([T] AtomicOption<T>);
+
+macro_rules! define_option_nonzero {
+    ($($name:ident: $atomic:ident, $int:ident, $nonzero:ident;)*) => {$(
+        #[doc = concat!(
+            "An atomic <code>[Option]<[", stringify!($nonzero), "]></code>.",
+        )]
+        ///
+        #[doc = concat!(
+            "This type stores the value in an [`", stringify!($atomic),
+            "`], using 0 to represent [`None`], so it replaces the common ",
+            "pattern of reserving a sentinel value for “no value”. Like [`",
+            stringify!($atomic), "`], it uses the built-in atomic where ",
+            "available and a spinlock-based fallback otherwise.\n\n",
+            "[`", stringify!($atomic), "`]: crate::", stringify!($atomic),
+        )]
+        ///
+        /// ```
+        #[doc = concat!(
+            "use atomic_int::", stringify!($name), ";\n",
+            "use core::num::", stringify!($nonzero), ";\n",
+            "use std::sync::atomic::Ordering;\n",
+            "\n",
+            "let id = ", stringify!($name), "::none();\n",
+            "let new = ", stringify!($nonzero), "::new(7);\n",
+            "assert!(id.compare_exchange(\n",
+            "    None, new, Ordering::AcqRel, Ordering::Acquire,\n",
+            ").is_ok());\n",
+            "assert_eq!(id.take(Ordering::AcqRel), new);\n",
+            "assert_eq!(id.load(Ordering::Acquire), None);",
+        )]
+        /// ```
+        #[repr(transparent)]
+        pub struct $name {
+            int: crate::$atomic,
+        }
+
+        impl $name {
+            // With `--cfg loom`, the fallback types' `new` isn't `const`.
+            loom_const_fn! {
+                /// Creates a new atomic optional integer.
+                pub fn new(v: Option<$nonzero>) -> Self {
+                    Self {
+                        int: crate::$atomic::new(match v {
+                            Some(n) => n.get(),
+                            None => 0,
+                        }),
+                    }
+                }
+            }
+
+            loom_const_fn! {
+                /// Creates a new atomic optional integer containing
+                /// [`None`].
+                pub fn none() -> Self {
+                    Self::new(None)
+                }
+            }
+
+            /// Returns a mutable reference to the underlying value.
+            pub fn get_mut(&mut self) -> &mut Option<$nonzero> {
+                let int: *mut $int = self.int.get_mut();
+                // SAFETY: `Option<NonZero*>` has the same layout as the
+                // corresponding integer, with `None` represented by 0.
+                unsafe { &mut *int.cast() }
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            pub fn into_inner(self) -> Option<$nonzero> {
+                $nonzero::new(self.int.into_inner())
+            }
+
+            /// Loads the value.
+            pub fn load(&self, order: Ordering) -> Option<$nonzero> {
+                $nonzero::new(self.int.load(order))
+            }
+
+            /// Stores a value.
+            pub fn store(&self, v: Option<$nonzero>, order: Ordering) {
+                self.int.store(into_int(v), order);
+            }
+
+            /// Stores a value, returning the previous value.
+            pub fn swap(
+                &self,
+                v: Option<$nonzero>,
+                order: Ordering,
+            ) -> Option<$nonzero> {
+                $nonzero::new(self.int.swap(into_int(v), order))
+            }
+
+            /// Takes the value, leaving [`None`] in its place.
+            ///
+            /// If multiple threads call this method concurrently, at most
+            /// one of them will receive any given [`Some`] value.
+            pub fn take(&self, order: Ordering) -> Option<$nonzero> {
+                self.swap(None, order)
+            }
+
+            /// Stores [`Some(v)`](Some), returning the previous value.
+            pub fn replace(
+                &self,
+                v: $nonzero,
+                order: Ordering,
+            ) -> Option<$nonzero> {
+                self.swap(Some(v), order)
+            }
+
+            /// Stores a value if the current value is the same as
+            /// `current`.
+            ///
+            /// See, e.g., [`AtomicU64::compare_exchange`][cmpxchg].
+            ///
+            /// [cmpxchg]: core::sync::atomic::AtomicU64::compare_exchange
+            pub fn compare_exchange(
+                &self,
+                current: Option<$nonzero>,
+                new: Option<$nonzero>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<Option<$nonzero>, Option<$nonzero>> {
+                self.int
+                    .compare_exchange(
+                        into_int(current),
+                        into_int(new),
+                        success,
+                        failure,
+                    )
+                    .map($nonzero::new)
+                    .map_err($nonzero::new)
+            }
+
+            /// Stores a value if the current value is the same as
+            /// `current`. This method may fail spuriously.
+            ///
+            /// See, e.g., [`AtomicU64::compare_exchange_weak`][cmpxchg].
+            ///
+            /// [cmpxchg]: core::sync::atomic::AtomicU64::compare_exchange_weak
+            pub fn compare_exchange_weak(
+                &self,
+                current: Option<$nonzero>,
+                new: Option<$nonzero>,
+                success: Ordering,
+                failure: Ordering,
+            ) -> Result<Option<$nonzero>, Option<$nonzero>> {
+                self.int
+                    .compare_exchange_weak(
+                        into_int(current),
+                        into_int(new),
+                        success,
+                        failure,
+                    )
+                    .map($nonzero::new)
+                    .map_err($nonzero::new)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::none()
+            }
+        }
+
+        impl From<Option<$nonzero>> for $name {
+            fn from(v: Option<$nonzero>) -> Self {
+                Self::new(v)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.load(Ordering::SeqCst).fmt(f)
+            }
+        }
+
+        // SAFETY: Zero represents `None`.
+        impl_zeroable!([] $name);
+    )*};
+}
+
+/// Converts an optional nonzero integer to its integer representation.
+fn into_int<I: PrimInt>(v: Option<I::NonZero>) -> I {
+    v.map_or(I::ZERO, I::from_nonzero)
+}
+
+define_option_nonzero! {
+    AtomicOptionNonZeroI8: AtomicI8, i8, NonZeroI8;
+    AtomicOptionNonZeroU8: AtomicU8, u8, NonZeroU8;
+    AtomicOptionNonZeroI16: AtomicI16, i16, NonZeroI16;
+    AtomicOptionNonZeroU16: AtomicU16, u16, NonZeroU16;
+    AtomicOptionNonZeroI32: AtomicI32, i32, NonZeroI32;
+    AtomicOptionNonZeroU32: AtomicU32, u32, NonZeroU32;
+    AtomicOptionNonZeroI64: AtomicI64, i64, NonZeroI64;
+    AtomicOptionNonZeroU64: AtomicU64, u64, NonZeroU64;
+    AtomicOptionNonZeroI128: AtomicI128, i128, NonZeroI128;
+    AtomicOptionNonZeroU128: AtomicU128, u128, NonZeroU128;
+    AtomicOptionNonZeroIsize: AtomicIsize, isize, NonZeroIsize;
+    AtomicOptionNonZeroUsize: AtomicUsize, usize, NonZeroUsize;
+}
@@ -0,0 +1,221 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::AtomicIntExt;
+use core::fmt;
+use core::sync::atomic::Ordering;
+
+macro_rules! define_ordered {
+    (
+        $(#[$attr:meta])*
+        $name:ident,
+        load: $load:ident,
+        store: $store:ident,
+        rmw: $rmw:ident $(,)?
+    ) => {
+        $(#[$attr])*
+        ///
+        /// Every operation on this type uses a fixed ordering, so call sites
+        /// can't accidentally pass a weaker one:
+        ///
+        #[doc = concat!(
+            "* Loads (including the load performed by a failed compare-and-",
+            "swap) use [`", stringify!($load), "`](Ordering::",
+            stringify!($load), ").",
+        )]
+        #[doc = concat!(
+            "* Stores use [`", stringify!($store), "`](Ordering::",
+            stringify!($store), ").",
+        )]
+        #[doc = concat!(
+            "* Read-modify-write operations use [`", stringify!($rmw),
+            "`](Ordering::", stringify!($rmw), ").",
+        )]
+        #[repr(transparent)]
+        #[derive(Default)]
+        pub struct $name<A> {
+            atomic: A,
+        }
+
+        impl<A: AtomicIntExt> $name<A> {
+            /// Creates a new atomic.
+            pub fn new(v: A::Int) -> Self {
+                Self {
+                    atomic: A::new(v),
+                }
+            }
+
+            /// Wraps an existing atomic.
+            pub fn from_atomic(atomic: A) -> Self {
+                Self {
+                    atomic,
+                }
+            }
+
+            /// Consumes the wrapper and returns the underlying atomic.
+            pub fn into_atomic(self) -> A {
+                self.atomic
+            }
+
+            /// Returns a mutable reference to the underlying value.
+            pub fn get_mut(&mut self) -> &mut A::Int {
+                self.atomic.get_mut()
+            }
+
+            /// Consumes the atomic and returns the contained value.
+            pub fn into_inner(self) -> A::Int {
+                self.atomic.into_inner()
+            }
+
+            /// Loads a value from the atomic.
+            pub fn load(&self) -> A::Int {
+                self.atomic.load(Ordering::$load)
+            }
+
+            /// Stores a value into the atomic.
+            pub fn store(&self, val: A::Int) {
+                self.atomic.store(val, Ordering::$store);
+            }
+
+            /// Stores a value into the atomic, returning the previous value.
+            pub fn swap(&self, val: A::Int) -> A::Int {
+                self.atomic.swap(val, Ordering::$rmw)
+            }
+
+            /// Stores a value into the atomic if the current value is the
+            /// same as the `current` value.
+            pub fn compare_exchange(
+                &self,
+                current: A::Int,
+                new: A::Int,
+            ) -> Result<A::Int, A::Int> {
+                self.atomic.compare_exchange(
+                    current,
+                    new,
+                    Ordering::$rmw,
+                    Ordering::$load,
+                )
+            }
+
+            /// Fetches the value, and applies a function to it that returns
+            /// an optional new value.
+            pub fn fetch_update<F>(&self, f: F) -> Result<A::Int, A::Int>
+            where
+                F: FnMut(A::Int) -> Option<A::Int>,
+            {
+                self.atomic.fetch_update(Ordering::$rmw, Ordering::$load, f)
+            }
+
+            /// Adds to the current value, returning the previous value.
+            pub fn fetch_add(&self, val: A::Int) -> A::Int {
+                self.atomic.fetch_add(val, Ordering::$rmw)
+            }
+
+            /// Subtracts from the current value, returning the previous
+            /// value.
+            pub fn fetch_sub(&self, val: A::Int) -> A::Int {
+                self.atomic.fetch_sub(val, Ordering::$rmw)
+            }
+
+            /// Bitwise “and” with the current value.
+            pub fn fetch_and(&self, val: A::Int) -> A::Int {
+                self.atomic.fetch_and(val, Ordering::$rmw)
+            }
+
+            /// Bitwise “or” with the current value.
+            pub fn fetch_or(&self, val: A::Int) -> A::Int {
+                self.atomic.fetch_or(val, Ordering::$rmw)
+            }
+
+            /// Bitwise “xor” with the current value.
+            pub fn fetch_xor(&self, val: A::Int) -> A::Int {
+                self.atomic.fetch_xor(val, Ordering::$rmw)
+            }
+
+            /// Maximum with the current value.
+            pub fn fetch_max(&self, val: A::Int) -> A::Int {
+                self.atomic.fetch_max(val, Ordering::$rmw)
+            }
+
+            /// Minimum with the current value.
+            pub fn fetch_min(&self, val: A::Int) -> A::Int {
+                self.atomic.fetch_min(val, Ordering::$rmw)
+            }
+        }
+
+        impl<A: AtomicIntExt> fmt::Debug for $name<A> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.load().fmt(f)
+            }
+        }
+
+        // SAFETY: The type contains only the atomic.
+        impl_zeroable!([A: bytemuck::Zeroable] $name<A>);
+    };
+}
+
+define_ordered! {
+    /// An atomic whose operations are all sequentially consistent.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicU32, SeqCstAtomic};
+    ///
+    /// let a = SeqCstAtomic::<AtomicU32>::new(1);
+    /// assert_eq!(a.fetch_add(2), 1);
+    /// assert_eq!(a.compare_exchange(3, 4), Ok(3));
+    /// assert_eq!(a.load(), 4);
+    /// ```
+    SeqCstAtomic,
+    load: SeqCst,
+    store: SeqCst,
+    rmw: SeqCst,
+}
+
+define_ordered! {
+    /// An atomic for publishing data with acquire–release synchronization.
+    ///
+    /// ```
+    /// use atomic_int::{AcquireReleaseAtomic, AtomicI128};
+    ///
+    /// let a = AcquireReleaseAtomic::<AtomicI128>::new(0);
+    /// a.store(5);
+    /// assert_eq!(a.swap(6), 5);
+    /// assert_eq!(a.load(), 6);
+    /// ```
+    AcquireReleaseAtomic,
+    load: Acquire,
+    store: Release,
+    rmw: AcqRel,
+}
+
+define_ordered! {
+    /// An atomic whose operations impose no ordering on other memory
+    /// accesses, such as a statistics counter.
+    ///
+    /// ```
+    /// use atomic_int::{AtomicUsize, RelaxedAtomic};
+    ///
+    /// let hits = RelaxedAtomic::<AtomicUsize>::default();
+    /// hits.fetch_add(1);
+    /// assert_eq!(hits.into_inner(), 1);
+    /// ```
+    RelaxedAtomic,
+    load: Relaxed,
+    store: Relaxed,
+    rmw: Relaxed,
+}
@@ -0,0 +1,362 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::AtomicIntExt;
+use super::ext::load_order;
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering;
+
+/// The integer type that stores a packed `T`.
+type Bits<T> = <<T as Packed>::Atomic as AtomicIntExt>::Int;
+
+/// A struct that can be packed into a single atomic integer.
+///
+/// This trait is normally implemented with [`packed_atomic!`], which
+/// generates the bit-packing code.
+///
+/// [`packed_atomic!`]: crate::packed_atomic
+pub trait Packed: Copy {
+    /// The atomic integer type that stores the packed struct.
+    type Atomic: AtomicIntExt;
+
+    /// Packs the struct into an integer.
+    fn to_bits(self) -> <Self::Atomic as AtomicIntExt>::Int;
+
+    /// Unpacks the struct from an integer.
+    fn from_bits(bits: <Self::Atomic as AtomicIntExt>::Int) -> Self;
+}
+
+/// A single field of a [`Packed`] struct `T`, with type `F`.
+///
+/// [`packed_atomic!`] generates an associated constant of this type for each
+/// field, which can be passed to [`PackedAtomic::get`] and
+/// [`PackedAtomic::set`].
+///
+/// [`packed_atomic!`]: crate::packed_atomic
+pub struct PackedField<T: Packed, F> {
+    get: fn(Bits<T>) -> F,
+    set: fn(Bits<T>, F) -> Bits<T>,
+}
+
+impl<T: Packed, F> PackedField<T, F> {
+    #[doc(hidden)]
+    pub const fn __new(
+        get: fn(Bits<T>) -> F,
+        set: fn(Bits<T>, F) -> Bits<T>,
+    ) -> Self {
+        Self {
+            get,
+            set,
+        }
+    }
+}
+
+impl<T: Packed, F> Clone for PackedField<T, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Packed, F> Copy for PackedField<T, F> {}
+
+/// An atomic [`Packed`] struct, stored as a single atomic integer.
+///
+/// Operations on the whole struct and on individual fields are all single
+/// atomic operations on the underlying integer, so concurrent updates to
+/// different fields never overwrite each other.
+pub struct PackedAtomic<T: Packed> {
+    atomic: T::Atomic,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Packed> PackedAtomic<T> {
+    /// Creates a new atomic packed struct.
+    pub fn new(v: T) -> Self {
+        Self {
+            atomic: T::Atomic::new(v.to_bits()),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Consumes the atomic and returns the contained value.
+    pub fn into_inner(self) -> T {
+        T::from_bits(self.atomic.into_inner())
+    }
+
+    /// Loads the whole struct.
+    pub fn load(&self, order: Ordering) -> T {
+        T::from_bits(self.atomic.load(order))
+    }
+
+    /// Stores the whole struct.
+    pub fn store(&self, val: T, order: Ordering) {
+        self.atomic.store(val.to_bits(), order);
+    }
+
+    /// Stores the whole struct, returning the previous value.
+    pub fn swap(&self, val: T, order: Ordering) -> T {
+        T::from_bits(self.atomic.swap(val.to_bits(), order))
+    }
+
+    /// Stores `new` if the current value is the same as `current`, comparing
+    /// the packed representations.
+    ///
+    /// See [`AtomicIntExt::compare_exchange`].
+    pub fn compare_exchange(
+        &self,
+        current: T,
+        new: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        self.atomic
+            .compare_exchange(
+                current.to_bits(),
+                new.to_bits(),
+                success,
+                failure,
+            )
+            .map(T::from_bits)
+            .map_err(T::from_bits)
+    }
+
+    /// Like [`Self::compare_exchange`], but may fail spuriously.
+    ///
+    /// See [`AtomicIntExt::compare_exchange_weak`].
+    pub fn compare_exchange_weak(
+        &self,
+        current: T,
+        new: T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<T, T> {
+        self.atomic
+            .compare_exchange_weak(
+                current.to_bits(),
+                new.to_bits(),
+                success,
+                failure,
+            )
+            .map(T::from_bits)
+            .map_err(T::from_bits)
+    }
+
+    /// Fetches the struct, and applies a function to it that returns an
+    /// optional new value.
+    ///
+    /// See [`AtomicIntExt::fetch_update`].
+    pub fn fetch_update<F>(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: F,
+    ) -> Result<T, T>
+    where
+        F: FnMut(T) -> Option<T>,
+    {
+        self.atomic
+            .fetch_update(set_order, fetch_order, |bits| {
+                f(T::from_bits(bits)).map(T::to_bits)
+            })
+            .map(T::from_bits)
+            .map_err(T::from_bits)
+    }
+
+    /// Loads a single field.
+    pub fn get<F>(&self, field: PackedField<T, F>, order: Ordering) -> F {
+        (field.get)(self.atomic.load(order))
+    }
+
+    /// Stores a single field, leaving the other fields unchanged, and
+    /// returns the field’s previous value.
+    ///
+    /// `order` describes the ordering of the whole read-modify-write
+    /// operation, as with [`AtomicIntExt::swap`].
+    pub fn set<F: Copy>(
+        &self,
+        field: PackedField<T, F>,
+        val: F,
+        order: Ordering,
+    ) -> F {
+        let prev =
+            match self.atomic.fetch_update(order, load_order(order), |bits| {
+                Some((field.set)(bits, val))
+            }) {
+                Ok(prev) => prev,
+                Err(prev) => prev,
+            };
+        (field.get)(prev)
+    }
+}
+
+impl<T: Packed + Default> Default for PackedAtomic<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Packed + fmt::Debug> fmt::Debug for PackedAtomic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.load(Ordering::SeqCst).fmt(f)
+    }
+}
+
+/// Defines a struct of integer fields that can be packed into a single
+/// atomic integer.
+///
+/// The fields are packed in declaration order, starting from the least
+/// significant bits, and each occupies exactly the width of its type. The
+/// combined width of the fields must not exceed the width of the backing
+/// atomic; this is checked at compile time.
+///
+/// The macro implements [`Packed`] (along with [`Clone`] and [`Copy`]) for
+/// the struct, and defines an associated constant of type [`PackedField`]
+/// with the same name as each field. The struct is then used through
+/// [`PackedAtomic`]:
+///
+/// ```
+/// use atomic_int::{AtomicU64, PackedAtomic, packed_atomic};
+/// use std::sync::atomic::Ordering;
+///
+/// packed_atomic! {
+///     #[derive(Debug, PartialEq)]
+///     pub struct Header {
+///         pub version: u16,
+///         pub flags: u16,
+///         pub offset: u32,
+///     }
+///     backing AtomicU64
+/// }
+///
+/// let header = Header {
+///     version: 1,
+///     flags: 0xffff,
+///     offset: 0xdead_beef,
+/// };
+/// let atomic = PackedAtomic::new(header);
+/// assert_eq!(atomic.load(Ordering::Relaxed), header);
+///
+/// assert_eq!(atomic.set(Header::version, 0xffff, Ordering::Relaxed), 1);
+/// assert_eq!(atomic.set(Header::flags, 0, Ordering::Relaxed), 0xffff);
+/// assert_eq!(atomic.get(Header::offset, Ordering::Relaxed), 0xdead_beef);
+/// assert_eq!(
+///     atomic.into_inner(),
+///     Header {
+///         version: 0xffff,
+///         flags: 0,
+///         offset: 0xdead_beef,
+///     },
+/// );
+/// ```
+#[macro_export]
+macro_rules! packed_atomic {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_attr:meta])*
+                $field_vis:vis $field:ident: $field_type:ty
+            ),* $(,)?
+        }
+        backing $atomic:ty
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy)]
+        $vis struct $name {
+            $(
+                $(#[$field_attr])*
+                $field_vis $field: $field_type,
+            )*
+        }
+
+        const _: () = ::core::assert!(
+            0 $(+ ::core::mem::size_of::<$field_type>())*
+                <= ::core::mem::size_of::<
+                    <$atomic as $crate::AtomicIntExt>::Int,
+                >(),
+            ::core::concat!(
+                "the fields of `",
+                ::core::stringify!($name),
+                "` do not fit in `",
+                ::core::stringify!($atomic),
+                "`",
+            ),
+        );
+
+        impl $crate::Packed for $name {
+            type Atomic = $atomic;
+
+            #[allow(unused_assignments)]
+            fn to_bits(self) -> <$atomic as $crate::AtomicIntExt>::Int {
+                type Bits = <$atomic as $crate::AtomicIntExt>::Int;
+                const BITS: usize = ::core::mem::size_of::<Bits>() * 8;
+                let mut bits: Bits = 0;
+                let mut shift = 0;
+                $(
+                    let width = ::core::mem::size_of::<$field_type>() * 8;
+                    let mask: Bits = if width >= BITS {
+                        !0
+                    } else {
+                        (1 << width) - 1
+                    };
+                    bits |= (self.$field as Bits & mask) << shift;
+                    shift += width;
+                )*
+                bits
+            }
+
+            #[allow(unused_assignments)]
+            fn from_bits(
+                bits: <$atomic as $crate::AtomicIntExt>::Int,
+            ) -> Self {
+                let mut shift = 0;
+                $(
+                    let $field = (bits >> shift) as $field_type;
+                    shift += ::core::mem::size_of::<$field_type>() * 8;
+                )*
+                Self {
+                    $($field,)*
+                }
+            }
+        }
+
+        impl $name {
+            $(
+                #[allow(non_upper_case_globals)]
+                #[doc = ::core::concat!(
+                    "The field `",
+                    ::core::stringify!($field),
+                    "`.",
+                )]
+                $field_vis const $field: $crate::PackedField<
+                    Self,
+                    $field_type,
+                > = $crate::PackedField::__new(
+                    |bits| <Self as $crate::Packed>::from_bits(bits).$field,
+                    |bits, val| {
+                        let mut value =
+                            <Self as $crate::Packed>::from_bits(bits);
+                        value.$field = val;
+                        $crate::Packed::to_bits(value)
+                    },
+                );
+            )*
+        }
+    };
+}
@@ -0,0 +1,138 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use core::ops::{Deref, DerefMut};
+
+/// Pads and aligns a value to the length of a cache line.
+///
+/// Placing frequently modified atomics in separate `CachePadded` values
+/// prevents false sharing, where unrelated atomics on the same cache line
+/// contend with each other. The alignment is 128 bytes on x86-64, AArch64,
+/// and 64-bit PowerPC, whose prefetchers fetch pairs of 64-byte lines (or
+/// whose lines are 128 bytes), and 64 bytes elsewhere.
+///
+/// A fallback type that contains its own lock keeps the lock next to its
+/// value, so padding it also keeps its lock from being shared. With the
+/// feature `striped`, the locks are instead in a global table whose entries
+/// are padded the same way, but unrelated atomics may still be assigned the
+/// same lock.
+///
+/// Aliases like [`AtomicU64Padded`](crate::AtomicU64Padded) exist for the
+/// primitive atomics; for other atomics, such as the C integer types, use
+/// `CachePadded` directly (e.g., `CachePadded<AtomicCInt>`).
+///
+/// ```
+/// use atomic_int::AtomicU64Padded;
+/// use std::sync::atomic::Ordering;
+///
+/// // Counters updated by different threads, on separate cache lines.
+/// let counters: [AtomicU64Padded; 2] = Default::default();
+/// counters[0].fetch_add(1, Ordering::Relaxed);
+/// assert!(std::mem::align_of::<AtomicU64Padded>() >= 64);
+/// assert_eq!(counters[1].load(Ordering::Relaxed), 0);
+/// ```
+#[cfg_attr(
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "powerpc64",
+    ),
+    repr(align(128))
+)]
+#[cfg_attr(
+    not(any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "powerpc64",
+    )),
+    repr(align(64))
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CachePadded<T> {
+    value: T,
+}
+
+impl<T> CachePadded<T> {
+    /// Pads and aligns a value to the length of a cache line.
+    pub const fn new(value: T) -> Self {
+        Self {
+            value,
+        }
+    }
+
+    /// Returns the inner value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> From<T> for CachePadded<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+// SAFETY: The type contains only the value (and padding).
+impl_zeroable!([T: bytemuck::Zeroable] CachePadded<T>);
+
+macro_rules! define_padded_atomic {
+    ($padded:ident$(<$generic:ident>)?, $atomic:ident) => {
+        #[cfg(feature = "primitives")]
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "primitives")))]
+        #[doc = concat!("A cache-padded [`", stringify!($atomic), "`].")]
+        ///
+        /// See [`CachePadded`].
+        #[doc = concat!(
+            "\n\n[`",
+            stringify!($atomic),
+            "`]: crate::",
+            stringify!($atomic),
+        )]
+        pub type $padded$(<$generic>)? =
+            CachePadded<crate::$atomic$(<$generic>)?>;
+    };
+}
+
+define_padded_atomic!(AtomicI8Padded, AtomicI8);
+define_padded_atomic!(AtomicU8Padded, AtomicU8);
+define_padded_atomic!(AtomicI16Padded, AtomicI16);
+define_padded_atomic!(AtomicU16Padded, AtomicU16);
+define_padded_atomic!(AtomicI32Padded, AtomicI32);
+define_padded_atomic!(AtomicU32Padded, AtomicU32);
+define_padded_atomic!(AtomicI64Padded, AtomicI64);
+define_padded_atomic!(AtomicU64Padded, AtomicU64);
+define_padded_atomic!(AtomicI128Padded, AtomicI128);
+define_padded_atomic!(AtomicU128Padded, AtomicU128);
+define_padded_atomic!(AtomicIsizePadded, AtomicIsize);
+define_padded_atomic!(AtomicUsizePadded, AtomicUsize);
+define_padded_atomic!(AtomicBoolPadded, AtomicBool);
+define_padded_atomic!(AtomicPtrPadded<T>, AtomicPtr);
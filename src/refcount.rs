@@ -0,0 +1,292 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::AtomicUsize;
+use core::fmt;
+use core::marker::PhantomData;
+use core::sync::atomic::{Ordering, fence};
+
+/// A policy for handling the overflow of an [`AtomicRefCount`].
+///
+/// Overflow occurs when an increment would make the count exceed
+/// [`AtomicRefCount::MAX`], which in practice means that references are
+/// being leaked (e.g., with [`mem::forget`]), or when a decrement is
+/// performed on a count of zero, which means that the count was misused.
+/// Either way, the shared object can no longer be freed safely.
+///
+/// [`mem::forget`]: core::mem::forget
+pub trait OverflowPolicy {
+    /// Called when the count overflows.
+    ///
+    /// If this function returns, the count is saturated: it stays above
+    /// [`AtomicRefCount::MAX`] forever, so no decrement ever returns `true`,
+    /// and the shared object is leaked rather than freed while it is still
+    /// in use. This function may be called more than once for the same
+    /// count, e.g., when several threads overflow it concurrently.
+    fn on_overflow();
+}
+
+/// An [`OverflowPolicy`] that aborts the process, like
+/// [`Arc`](https://doc.rust-lang.org/std/sync/struct.Arc.html).
+///
+/// Without `std` (i.e., in `no_std` code), this panics while panicking,
+/// which aborts as well.
+#[derive(Clone, Copy, Debug)]
+pub struct AbortOnOverflow(());
+
+impl OverflowPolicy for AbortOnOverflow {
+    fn on_overflow() {
+        #[cfg(any(feature = "std", feature = "libc"))]
+        std::process::abort();
+
+        #[cfg(not(any(feature = "std", feature = "libc")))]
+        {
+            struct Abort;
+
+            impl Drop for Abort {
+                fn drop(&mut self) {
+                    panic!("reference count overflow");
+                }
+            }
+
+            let _abort = Abort;
+            panic!("reference count overflow");
+        }
+    }
+}
+
+/// An [`OverflowPolicy`] that saturates the count, leaking the shared
+/// object, like Linux’s `refcount_t`.
+#[derive(Clone, Copy, Debug)]
+pub struct SaturateOnOverflow(());
+
+impl OverflowPolicy for SaturateOnOverflow {
+    fn on_overflow() {}
+}
+
+/// A reference count, with the orderings used by
+/// [`Arc`](https://doc.rust-lang.org/std/sync/struct.Arc.html) built in.
+///
+/// [`Self::increment`] uses [`Relaxed`] ordering, as a new reference can be
+/// created only from an existing one, which already keeps the object alive.
+/// [`Self::decrement`] uses [`Release`] ordering, so that every use of the
+/// object through a reference happens before the reference is dropped, and
+/// when it drops the last reference, it issues an [`Acquire`] fence, so that
+/// all those uses happen before the object is freed.
+///
+/// What happens when the count overflows is determined by the
+/// [`OverflowPolicy`] `P`: by default, the process aborts.
+///
+/// ```
+/// use atomic_int::AtomicRefCount;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// let refs = AtomicRefCount::new(1);
+/// let freed = AtomicUsize::new(0);
+/// std::thread::scope(|s| {
+///     for _ in 0..8 {
+///         refs.increment();
+///         s.spawn(|| {
+///             if refs.decrement() {
+///                 freed.fetch_add(1, Ordering::Relaxed);
+///             }
+///         });
+///     }
+///     // Drop the initial reference.
+///     if refs.decrement() {
+///         freed.fetch_add(1, Ordering::Relaxed);
+///     }
+/// });
+/// assert_eq!(freed.into_inner(), 1);
+/// assert_eq!(refs.get(), 0);
+/// ```
+///
+/// With [`SaturateOnOverflow`], an overflowed count is never decremented to
+/// zero:
+///
+/// ```
+/// use atomic_int::{AtomicRefCount, SaturateOnOverflow};
+///
+/// type RefCount = AtomicRefCount<SaturateOnOverflow>;
+///
+/// let refs = RefCount::with_policy(RefCount::MAX);
+/// refs.increment();
+/// assert!(refs.is_saturated());
+/// assert!(!refs.decrement());
+/// assert!(refs.is_saturated());
+/// ```
+///
+/// [`Relaxed`]: Ordering::Relaxed
+/// [`Release`]: Ordering::Release
+/// [`Acquire`]: Ordering::Acquire
+#[repr(transparent)]
+pub struct AtomicRefCount<P: OverflowPolicy = AbortOnOverflow> {
+    count: AtomicUsize,
+    phantom: PhantomData<fn() -> P>,
+}
+
+impl AtomicRefCount {
+    /// Creates a new reference count that aborts on overflow, usually with a
+    /// `count` of 1 for the reference that is returned along with the
+    /// object.
+    ///
+    /// To choose a different [`OverflowPolicy`], use
+    /// [`Self::with_policy`].
+    pub const fn new(count: usize) -> Self {
+        Self::with_policy(count)
+    }
+}
+
+impl<P: OverflowPolicy> AtomicRefCount<P> {
+    /// The maximum count, [`isize::MAX`], like that of `Arc`.
+    pub const MAX: usize = isize::MAX as usize;
+
+    /// The value of a saturated count, halfway between [`Self::MAX`] and
+    /// [`usize::MAX`], so that concurrent increments and decrements can't
+    /// move it back into the valid range.
+    const SATURATED: usize = Self::MAX + (usize::MAX - Self::MAX) / 2;
+
+    /// Creates a new reference count with the overflow policy `P`.
+    ///
+    /// See [`AtomicRefCount::new`].
+    pub const fn with_policy(count: usize) -> Self {
+        Self {
+            count: AtomicUsize::new(count),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Consumes the reference count and returns the count.
+    pub fn into_inner(self) -> usize {
+        self.count.into_inner()
+    }
+
+    fn overflow(&self) {
+        P::on_overflow();
+        self.count.store(Self::SATURATED, Ordering::Relaxed);
+    }
+
+    /// Adds a reference.
+    ///
+    /// This must be called only through an existing reference (that is, when
+    /// the count is not zero). To add a reference through a weak reference,
+    /// which doesn't keep the object alive, use
+    /// [`Self::increment_unless_zero`] instead.
+    pub fn increment(&self) {
+        let prev = self.count.fetch_add(1, Ordering::Relaxed);
+        if prev == Self::MAX {
+            self.overflow();
+        } else if prev > Self::MAX {
+            // Keep the count saturated.
+            self.count.store(Self::SATURATED, Ordering::Relaxed);
+        }
+    }
+
+    /// Adds a reference unless the count is zero (i.e., the object was
+    /// already freed or is being freed), returning whether it was added.
+    ///
+    /// This is how a weak reference is upgraded, as in `Weak::upgrade`.
+    ///
+    /// ```
+    /// use atomic_int::AtomicRefCount;
+    ///
+    /// let refs = AtomicRefCount::new(1);
+    /// assert!(refs.increment_unless_zero());
+    /// assert!(!refs.decrement());
+    /// assert!(refs.decrement());
+    /// assert!(!refs.increment_unless_zero());
+    /// ```
+    pub fn increment_unless_zero(&self) -> bool {
+        let mut current = self.count.load(Ordering::Relaxed);
+        loop {
+            let new = match current {
+                0 => return false,
+                c if c > Self::MAX => return true,
+                c if c == Self::MAX => {
+                    P::on_overflow();
+                    Self::SATURATED
+                }
+                c => c + 1,
+            };
+            match self.count.compare_exchange_weak(
+                current,
+                new,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(c) => current = c,
+            }
+        }
+    }
+
+    /// Drops a reference, returning whether it was the last one, in which
+    /// case the object can be freed.
+    ///
+    /// When this returns `true`, every use of the object through other
+    /// references happens before this call returns. Decrementing a count of
+    /// zero is an overflow.
+    pub fn decrement(&self) -> bool {
+        let prev = self.count.fetch_sub(1, Ordering::Release);
+        if prev == 1 {
+            fence(Ordering::Acquire);
+            return true;
+        }
+        if prev == 0 {
+            self.overflow();
+        } else if prev > Self::MAX {
+            // Keep the count saturated.
+            self.count.store(Self::SATURATED, Ordering::Relaxed);
+        }
+        false
+    }
+
+    /// Returns the current count.
+    ///
+    /// Other threads may change the count at any time, so this is mainly
+    /// useful for debugging. A saturated count is reported as a value
+    /// greater than [`Self::MAX`].
+    pub fn get(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the count is 1, i.e., whether the caller holds the
+    /// only reference, in which case it can safely mutate the object.
+    ///
+    /// This uses [`Acquire`](Ordering::Acquire) ordering, so that the
+    /// uses of the object through other, already dropped references happen
+    /// before this returns `true`. If weak references exist, they must be
+    /// prevented from being upgraded separately.
+    pub fn is_unique(&self) -> bool {
+        self.count.load(Ordering::Acquire) == 1
+    }
+
+    /// Returns whether the count has overflowed and saturated.
+    pub fn is_saturated(&self) -> bool {
+        self.get() > Self::MAX
+    }
+}
+
+impl<P: OverflowPolicy> fmt::Debug for AtomicRefCount<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AtomicRefCount").field(&self.get()).finish()
+    }
+}
+
+// SAFETY: A count of zero is valid.
+impl_zeroable!([P: OverflowPolicy] AtomicRefCount<P>);
@@ -0,0 +1,120 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::{AtomicIntExt, CachePadded, PrimInt};
+use core::sync::atomic::Ordering;
+
+/// A counter split across `N` cache-padded atomic shards.
+///
+/// Under heavy contention, a single atomic counter becomes a bottleneck, as
+/// every increment must take exclusive ownership of the same cache line. This
+/// type spreads [`add`](Self::add) operations across `N` separate atomics of
+/// type `A`, chosen by a cheap hash of the calling thread, and combines them
+/// only when [`sum`](Self::sum) is called.
+///
+/// The choice of shard affects only performance, never correctness: any
+/// thread may add to any shard.
+pub struct ShardedCounter<A, const N: usize> {
+    shards: [CachePadded<A>; N],
+}
+
+impl<A: AtomicIntExt, const N: usize> ShardedCounter<A, N> {
+    /// Creates a new counter with a value of 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is 0.
+    pub fn new() -> Self {
+        assert!(N > 0, "`ShardedCounter` must have at least one shard");
+        Self {
+            shards: core::array::from_fn(|_| {
+                CachePadded::new(A::new(A::Int::ZERO))
+            }),
+        }
+    }
+
+    /// Adds `val` to the counter.
+    ///
+    /// As with [`AtomicIntExt::fetch_add`], this operation wraps around on
+    /// overflow.
+    pub fn add(&self, val: A::Int, order: Ordering) {
+        self.shards[shard_index(N)].fetch_add(val, order);
+    }
+
+    /// Returns the sum of all shards.
+    ///
+    /// The shards are loaded one at a time, so if the counter is modified
+    /// concurrently, the result may not correspond to a value the counter
+    /// held at any single point in time. Once the modifications have finished
+    /// (e.g., after joining the threads that made them), the result is exact:
+    ///
+    /// ```
+    /// use atomic_int::{AtomicU64, ShardedCounter};
+    /// use std::sync::atomic::Ordering;
+    /// use std::thread;
+    ///
+    /// let counter = ShardedCounter::<AtomicU64, 8>::new();
+    /// thread::scope(|s| {
+    ///     for id in 1..=16 {
+    ///         let counter = &counter;
+    ///         s.spawn(move || {
+    ///             for _ in 0..1000 {
+    ///                 counter.add(id, Ordering::Relaxed);
+    ///             }
+    ///         });
+    ///     }
+    /// });
+    /// let expected: u64 = (1..=16).sum::<u64>() * 1000;
+    /// assert_eq!(counter.sum(Ordering::Relaxed), expected);
+    /// ```
+    pub fn sum(&self, order: Ordering) -> A::Int {
+        self.shards.iter().fold(A::Int::ZERO, |sum, shard| {
+            sum.wrapping_add(shard.load(order))
+        })
+    }
+
+    /// Consumes the counter and returns the sum of all shards.
+    pub fn into_inner(self) -> A::Int {
+        IntoIterator::into_iter(self.shards)
+            .fold(A::Int::ZERO, |sum, shard| {
+                sum.wrapping_add(shard.into_inner().into_inner())
+            })
+    }
+}
+
+impl<A: AtomicIntExt, const N: usize> Default for ShardedCounter<A, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// SAFETY: The type contains only the shards.
+impl_zeroable!([A: bytemuck::Zeroable, const N: usize] ShardedCounter<A, N>);
+
+/// Picks a shard for the current thread.
+///
+/// Different threads run on different stacks, which are typically far apart
+/// in memory, so the address of a local variable, with its low bits (which
+/// vary within a single stack) discarded, serves as a cheap thread identifier
+/// that works without `std`.
+fn shard_index(shards: usize) -> usize {
+    let marker = 0_u8;
+    let addr = core::ptr::addr_of!(marker) as usize;
+    let hash = (addr >> 16).wrapping_mul(0x9e37_79b9_u32 as usize);
+    (hash ^ (hash >> 15)) % shards
+}
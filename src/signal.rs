@@ -0,0 +1,249 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Control over the signal blocking performed by the feature `signal` and by
+//! [`SignalSafeSpinLock`](crate::SignalSafeSpinLock), and access to that
+//! blocking for custom critical sections.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+/// Performs the one-time initialization needed to block signals while a
+/// lock is held.
+///
+/// Without this, that initialization (computing the set of all signals)
+/// happens the first time signals are blocked, which adds latency to that
+/// operation. Calling this function is optional, and calling it more than once
+/// has no further effect. On Windows, where no signals are blocked, it does
+/// nothing.
+///
+/// ```
+/// use atomic_int::AtomicU128;
+/// use atomic_int::signal::{__init_count, prewarm};
+/// use std::sync::atomic::Ordering;
+///
+/// prewarm();
+/// let count = __init_count();
+/// assert!(count <= 1);
+///
+/// // `AtomicU128` is a fallback type on most targets.
+/// let a = AtomicU128::new(0);
+/// a.fetch_add(1, Ordering::SeqCst);
+/// prewarm();
+/// assert_eq!(__init_count(), count);
+/// ```
+pub fn prewarm() {
+    #[cfg(not(windows))]
+    crate::fallback::sigmask::prewarm();
+}
+
+/// Sets the signals that are blocked while a lock is held.
+///
+/// By default, all signals are blocked. Signals left out of `set` (e.g.,
+/// `SIGPROF` for a sampling profiler) can then interrupt a thread that holds
+/// a lock, so their handlers must not use any atomic whose lock blocks
+/// signals.
+///
+/// The set can be configured only once, before it is first needed; this
+/// function returns [`AlreadyInitialized`] if signals have already been
+/// blocked, or if [`prewarm`] or this function was already called.
+///
+/// ```
+/// use atomic_int::signal::{AlreadyInitialized, set_blocked_signals};
+/// use std::mem::MaybeUninit;
+///
+/// let set = unsafe {
+///     let mut set = MaybeUninit::uninit();
+///     libc::sigfillset(set.as_mut_ptr());
+///     libc::sigdelset(set.as_mut_ptr(), libc::SIGPROF);
+///     set.assume_init()
+/// };
+/// assert_eq!(set_blocked_signals(&set), Ok(()));
+/// assert_eq!(set_blocked_signals(&set), Err(AlreadyInitialized));
+/// ```
+#[cfg(unix)]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(unix)))]
+pub fn set_blocked_signals(
+    set: &libc::sigset_t,
+) -> Result<(), AlreadyInitialized> {
+    if crate::fallback::sigmask::set_blocked(set) {
+        Ok(())
+    } else {
+        Err(AlreadyInitialized)
+    }
+}
+
+/// The error returned by [`set_blocked_signals`] when the set of blocked
+/// signals has already been determined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AlreadyInitialized;
+
+impl core::fmt::Display for AlreadyInitialized {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("the set of blocked signals was already initialized")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AlreadyInitialized {}
+
+/// A failure of a system call that blocks or unblocks signals.
+///
+/// This is passed to the hook installed with [`set_failure_hook`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SignalMaskFailure {
+    /// `sigfillset` failed while computing the set of all signals. Signals
+    /// are not blocked while the lock is held.
+    FillSet,
+    /// `pthread_sigmask` failed, returning the given error code, while
+    /// blocking signals. Signals are not blocked while the lock is held.
+    Block(i32),
+    /// `pthread_sigmask` failed, returning the given error code, while
+    /// restoring the previous signal mask after the lock was released.
+    /// Signals remain blocked.
+    Restore(i32),
+}
+
+/// Sets the function called when a system call that blocks or unblocks
+/// signals fails.
+///
+/// By default, such a failure writes a message to stderr and aborts the
+/// process, as the lock can then no longer guarantee that signal handlers
+/// don’t deadlock. If a hook is installed, it is called instead, and the
+/// operation continues once it returns, without blocking signals if they
+/// could not be blocked. To ignore failures entirely, install a hook that
+/// does nothing. Passing [`None`] restores the default behavior.
+///
+/// The hook may be called from a signal handler or while a lock is held, so
+/// it must be async-signal-safe, must not panic, and must not use any atomic
+/// whose lock blocks signals. On Windows, where no signals are blocked, it
+/// is never called.
+///
+/// ```
+/// use atomic_int::signal::{SignalMaskFailure, set_failure_hook};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static FAILURES: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn on_failure(_: SignalMaskFailure) {
+///     // Count the failure and continue rather than aborting.
+///     FAILURES.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// set_failure_hook(Some(on_failure));
+/// ```
+pub fn set_failure_hook(hook: Option<fn(SignalMaskFailure)>) {
+    #[cfg(not(windows))]
+    crate::fallback::sigmask::set_failure_hook(hook);
+    #[cfg(windows)]
+    let _ = hook;
+}
+
+/// Blocks signals on the current thread until dropped, like a lock whose
+/// lock blocks signals.
+///
+/// While the guard exists, the signals selected by [`set_blocked_signals`]
+/// (by default, all signals) are blocked, so code that runs with the guard
+/// held can't be interrupted by their handlers. This allows implementing
+/// custom critical sections that are safe to enter from signal handlers:
+/// the state they protect can't be accessed by a handler that interrupts the
+/// thread that holds it. When the guard is dropped, the previous signal mask
+/// is restored, so guards can be nested. A nested guard, like an operation
+/// on a fallback type performed while a guard is held, doesn't block the
+/// signals again, and thus makes no system calls; code that runs with a
+/// guard held must therefore not unblock them itself.
+///
+/// Signal masks are per-thread, so the guard is neither [`Send`] nor
+/// [`Sync`], and guards should be dropped in the reverse order of their
+/// creation. If a system call that blocks or unblocks signals fails, the
+/// hook installed with [`set_failure_hook`] is called (by default, the
+/// process aborts). On Windows, where no signals are blocked, the guard does
+/// nothing.
+///
+/// See also [`with_signals_blocked`].
+///
+/// ```
+/// use atomic_int::signal::SignalGuard;
+///
+/// let guard = SignalGuard::new();
+/// // Signal handlers can't run on this thread here.
+/// drop(guard);
+/// ```
+pub struct SignalGuard {
+    #[cfg(not(windows))]
+    _guard: crate::fallback::sigmask::SignalGuard,
+    phantom: PhantomData<*const ()>,
+}
+
+impl SignalGuard {
+    /// Blocks signals on the current thread, returning a guard that
+    /// restores the previous signal mask when dropped.
+    pub fn new() -> Self {
+        Self {
+            #[cfg(not(windows))]
+            _guard: crate::fallback::sigmask::SignalGuard::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl Default for SignalGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for SignalGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignalGuard").finish_non_exhaustive()
+    }
+}
+
+/// Calls `f` with signals blocked on the current thread, as with a
+/// [`SignalGuard`].
+///
+/// ```
+/// use atomic_int::signal::with_signals_blocked;
+/// use std::cell::Cell;
+///
+/// thread_local! {
+///     static EVENTS: Cell<u32> = Cell::new(0);
+/// }
+///
+/// // A handler that also updates `EVENTS` can't observe a partial update.
+/// let events = with_signals_blocked(|| {
+///     EVENTS.with(|e| {
+///         e.set(e.get() + 1);
+///         e.get()
+///     })
+/// });
+/// assert_eq!(events, 1);
+/// ```
+pub fn with_signals_blocked<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = SignalGuard::new();
+    f()
+}
+
+#[doc(hidden)]
+pub fn __init_count() -> usize {
+    #[cfg(not(windows))]
+    return crate::fallback::sigmask::init_count();
+    #[cfg(windows)]
+    0
+}
@@ -0,0 +1,240 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::AtomicPtr;
+use super::ext::{load_order, map_addr};
+use core::fmt;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::Ordering;
+
+/// An atomic pointer that stores a `BITS`-bit tag in its low bits.
+///
+/// A pointer to a `T` is aligned to <code>[align_of]::\<T>()</code> bytes,
+/// so its low bits are always zero, and this type uses `BITS` of them to
+/// store a small integer (the tag) alongside the pointer. The pair is stored
+/// in a single [`AtomicPtr`], so it can be loaded, stored, and compared and
+/// exchanged as a unit; like [`AtomicPtr`], this uses the built-in atomic
+/// where available and a spinlock-based fallback otherwise. The tag is
+/// added to the pointer's address without changing its provenance.
+///
+/// `2^BITS` must not exceed the alignment of `T`, which is checked at
+/// compile time. Methods that take a pointer panic if the pointer isn't
+/// aligned, and methods that take a tag panic if the tag exceeds
+/// [`Self::MAX_TAG`].
+///
+/// ```
+/// use atomic_int::AtomicTaggedPtr;
+/// use std::sync::atomic::Ordering;
+///
+/// // A version counter in the low bits guards against ABA problems.
+/// let mut node = 5_u64;
+/// let head = AtomicTaggedPtr::<u64, 3>::new(&mut node, 0);
+/// let (ptr, tag) = head.load_ptr_and_tag(Ordering::Acquire);
+/// let next = (tag + 1) & AtomicTaggedPtr::<u64, 3>::MAX_TAG;
+/// assert!(
+///     head.compare_exchange(
+///         (ptr, tag),
+///         (ptr, next),
+///         Ordering::AcqRel,
+///         Ordering::Acquire,
+///     )
+///     .is_ok()
+/// );
+/// assert_eq!(head.fetch_set_tag(7, Ordering::AcqRel), (ptr, 1));
+/// assert_eq!(head.load_ptr_and_tag(Ordering::Acquire), (ptr, 7));
+/// ```
+///
+/// [align_of]: core::mem::align_of
+#[repr(transparent)]
+pub struct AtomicTaggedPtr<T, const BITS: usize> {
+    ptr: AtomicPtr<T>,
+}
+
+impl<T, const BITS: usize> AtomicTaggedPtr<T, BITS> {
+    const ALIGN_CHECK: () = assert!(
+        BITS < usize::BITS as usize && 1 << BITS <= mem::align_of::<T>(),
+        "`AtomicTaggedPtr` needs `2^BITS` to be at most the alignment of `T`",
+    );
+
+    /// The largest tag that can be stored.
+    pub const MAX_TAG: usize = (1 << BITS) - 1;
+
+    /// Combines `ptr` and `tag` into a single pointer.
+    fn pack(ptr: *mut T, tag: usize) -> *mut T {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::ALIGN_CHECK;
+        assert!(
+            ptr as usize & Self::MAX_TAG == 0,
+            "pointer is not sufficiently aligned",
+        );
+        assert!(tag <= Self::MAX_TAG, "tag out of range");
+        map_addr(ptr, |addr| addr | tag)
+    }
+
+    /// Splits a pointer created by [`Self::pack`] into its pointer and tag.
+    fn unpack(packed: *mut T) -> (*mut T, usize) {
+        let tag = packed as usize & Self::MAX_TAG;
+        (map_addr(packed, |addr| addr & !Self::MAX_TAG), tag)
+    }
+
+    /// Creates a new atomic tagged pointer.
+    pub fn new(ptr: *mut T, tag: usize) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Self::pack(ptr, tag)),
+        }
+    }
+
+    /// Consumes the atomic and returns the contained pointer and tag.
+    pub fn into_inner(self) -> (*mut T, usize) {
+        Self::unpack(self.ptr.into_inner())
+    }
+
+    /// Loads the pointer and tag.
+    ///
+    /// See [`AtomicPtr::load`][load].
+    ///
+    /// [load]: core::sync::atomic::AtomicPtr::load
+    pub fn load_ptr_and_tag(&self, order: Ordering) -> (*mut T, usize) {
+        Self::unpack(self.ptr.load(order))
+    }
+
+    /// Stores a pointer and tag.
+    ///
+    /// See [`AtomicPtr::store`][store].
+    ///
+    /// [store]: core::sync::atomic::AtomicPtr::store
+    pub fn store(&self, ptr: *mut T, tag: usize, order: Ordering) {
+        self.ptr.store(Self::pack(ptr, tag), order);
+    }
+
+    /// Stores a pointer and tag, returning the previous pair.
+    ///
+    /// See [`AtomicPtr::swap`][swap].
+    ///
+    /// [swap]: core::sync::atomic::AtomicPtr::swap
+    pub fn swap(
+        &self,
+        ptr: *mut T,
+        tag: usize,
+        order: Ordering,
+    ) -> (*mut T, usize) {
+        Self::unpack(self.ptr.swap(Self::pack(ptr, tag), order))
+    }
+
+    /// Stores the pair `new` if the current pointer and tag are the same as
+    /// `current`.
+    ///
+    /// See [`AtomicPtr::compare_exchange`][cmpxchg].
+    ///
+    /// [cmpxchg]: core::sync::atomic::AtomicPtr::compare_exchange
+    pub fn compare_exchange(
+        &self,
+        current: (*mut T, usize),
+        new: (*mut T, usize),
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(*mut T, usize), (*mut T, usize)> {
+        self.ptr
+            .compare_exchange(
+                Self::pack(current.0, current.1),
+                Self::pack(new.0, new.1),
+                success,
+                failure,
+            )
+            .map(Self::unpack)
+            .map_err(Self::unpack)
+    }
+
+    /// Stores the pair `new` if the current pointer and tag are the same as
+    /// `current`. This method may fail spuriously.
+    ///
+    /// See [`AtomicPtr::compare_exchange_weak`][cmpxchg].
+    ///
+    /// [cmpxchg]: core::sync::atomic::AtomicPtr::compare_exchange_weak
+    pub fn compare_exchange_weak(
+        &self,
+        current: (*mut T, usize),
+        new: (*mut T, usize),
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(*mut T, usize), (*mut T, usize)> {
+        self.ptr
+            .compare_exchange_weak(
+                Self::pack(current.0, current.1),
+                Self::pack(new.0, new.1),
+                success,
+                failure,
+            )
+            .map(Self::unpack)
+            .map_err(Self::unpack)
+    }
+
+    /// Replaces the tag, keeping the pointer, and returns the previous
+    /// pointer and tag.
+    pub fn fetch_set_tag(
+        &self,
+        tag: usize,
+        order: Ordering,
+    ) -> (*mut T, usize) {
+        assert!(tag <= Self::MAX_TAG, "tag out of range");
+        let prev = self.ptr.fetch_update(order, load_order(order), |p| {
+            Some(map_addr(p, |addr| addr & !Self::MAX_TAG | tag))
+        });
+        match prev {
+            Ok(p) => Self::unpack(p),
+            Err(p) => Self::unpack(p),
+        }
+    }
+
+    /// Replaces the pointer, keeping the tag, and returns the previous
+    /// pointer and tag.
+    pub fn fetch_set_ptr(
+        &self,
+        ptr: *mut T,
+        order: Ordering,
+    ) -> (*mut T, usize) {
+        let new = Self::pack(ptr, 0);
+        let prev = self.ptr.fetch_update(order, load_order(order), |p| {
+            Some(map_addr(new, |addr| addr | p as usize & Self::MAX_TAG))
+        });
+        match prev {
+            Ok(p) => Self::unpack(p),
+            Err(p) => Self::unpack(p),
+        }
+    }
+}
+
+impl<T, const BITS: usize> Default for AtomicTaggedPtr<T, BITS> {
+    fn default() -> Self {
+        Self::new(ptr::null_mut(), 0)
+    }
+}
+
+impl<T, const BITS: usize> fmt::Debug for AtomicTaggedPtr<T, BITS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (ptr, tag) = self.load_ptr_and_tag(Ordering::SeqCst);
+        f.debug_struct("AtomicTaggedPtr")
+            .field("ptr", &ptr)
+            .field("tag", &tag)
+            .finish()
+    }
+}
+
+// SAFETY: Zero is a null pointer with a tag of 0.
+impl_zeroable!([T, const BITS: usize] AtomicTaggedPtr<T, BITS>);
@@ -0,0 +1,324 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+// Unused when `AtomicU64` is a fallback type, which has inherent methods.
+#[allow(unused_imports)]
+use super::AtomicIntExt;
+use super::AtomicU64;
+use core::fmt;
+use core::sync::atomic::Ordering;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Converts a duration to nanoseconds, saturating at [`u64::MAX`].
+const fn to_nanos(d: Duration) -> u64 {
+    let nanos = d.as_nanos();
+    if nanos > u64::MAX as u128 {
+        u64::MAX
+    } else {
+        nanos as u64
+    }
+}
+
+const fn from_nanos(nanos: u64) -> Duration {
+    Duration::new(nanos / NANOS_PER_SEC, (nanos % NANOS_PER_SEC) as u32)
+}
+
+/// Converts a time to nanoseconds since the Unix epoch, saturating at 0 and
+/// [`u64::MAX`].
+fn time_to_nanos(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).map_or(0, to_nanos)
+}
+
+fn time_from_nanos(nanos: u64) -> SystemTime {
+    UNIX_EPOCH + from_nanos(nanos)
+}
+
+/// An atomic [`Duration`], stored as a number of nanoseconds in an
+/// [`AtomicU64`].
+///
+/// This can represent durations up to [`Self::MAX`] (about 584 years).
+/// Longer durations are stored as [`Self::MAX`], and arithmetic saturates at
+/// 0 and [`Self::MAX`] instead of overflowing.
+///
+/// ```
+/// use atomic_int::AtomicDuration;
+/// use std::sync::atomic::Ordering;
+/// use std::time::Duration;
+///
+/// let busy = AtomicDuration::new(Duration::ZERO);
+/// busy.fetch_add(Duration::from_millis(1500), Ordering::Relaxed);
+/// busy.fetch_add(Duration::from_millis(700), Ordering::Relaxed);
+/// assert_eq!(busy.load(Ordering::Relaxed), Duration::from_millis(2200));
+///
+/// busy.store(Duration::MAX, Ordering::Relaxed);
+/// assert_eq!(busy.load(Ordering::Relaxed), AtomicDuration::MAX);
+/// ```
+#[repr(transparent)]
+pub struct AtomicDuration {
+    nanos: AtomicU64,
+}
+
+impl AtomicDuration {
+    /// The longest duration that can be stored: [`u64::MAX`] nanoseconds.
+    pub const MAX: Duration = from_nanos(u64::MAX);
+
+    /// Creates a new atomic duration.
+    pub const fn new(d: Duration) -> Self {
+        Self {
+            nanos: AtomicU64::new(to_nanos(d)),
+        }
+    }
+
+    /// Consumes the atomic and returns the contained value.
+    pub fn into_inner(self) -> Duration {
+        from_nanos(self.nanos.into_inner())
+    }
+
+    /// Returns the underlying atomic, which holds the duration in
+    /// nanoseconds.
+    pub fn as_nanos(&self) -> &AtomicU64 {
+        &self.nanos
+    }
+
+    /// Loads the value.
+    pub fn load(&self, order: Ordering) -> Duration {
+        from_nanos(self.nanos.load(order))
+    }
+
+    /// Stores a value.
+    pub fn store(&self, d: Duration, order: Ordering) {
+        self.nanos.store(to_nanos(d), order);
+    }
+
+    /// Stores a value, returning the previous value.
+    pub fn swap(&self, d: Duration, order: Ordering) -> Duration {
+        from_nanos(self.nanos.swap(to_nanos(d), order))
+    }
+
+    /// Stores a value if the current value is the same as `current`.
+    ///
+    /// See [`AtomicU64::compare_exchange`][cmpxchg]. Values are compared
+    /// after being rounded down to [`Self::MAX`].
+    ///
+    /// [cmpxchg]: core::sync::atomic::AtomicU64::compare_exchange
+    pub fn compare_exchange(
+        &self,
+        current: Duration,
+        new: Duration,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Duration, Duration> {
+        self.nanos
+            .compare_exchange(
+                to_nanos(current),
+                to_nanos(new),
+                success,
+                failure,
+            )
+            .map(from_nanos)
+            .map_err(from_nanos)
+    }
+
+    /// Adds to the current value, saturating at [`Self::MAX`], and returns
+    /// the previous value.
+    pub fn fetch_add(&self, d: Duration, order: Ordering) -> Duration {
+        from_nanos(self.nanos.fetch_saturating_add(to_nanos(d), order))
+    }
+
+    /// Subtracts from the current value, saturating at zero, and returns the
+    /// previous value.
+    pub fn fetch_sub(&self, d: Duration, order: Ordering) -> Duration {
+        from_nanos(self.nanos.fetch_saturating_sub(to_nanos(d), order))
+    }
+
+    /// Maximum with the current value, returning the previous value.
+    pub fn fetch_max(&self, d: Duration, order: Ordering) -> Duration {
+        from_nanos(self.nanos.fetch_max(to_nanos(d), order))
+    }
+
+    /// Minimum with the current value, returning the previous value.
+    pub fn fetch_min(&self, d: Duration, order: Ordering) -> Duration {
+        from_nanos(self.nanos.fetch_min(to_nanos(d), order))
+    }
+}
+
+impl Default for AtomicDuration {
+    fn default() -> Self {
+        Self::new(Duration::ZERO)
+    }
+}
+
+impl From<Duration> for AtomicDuration {
+    fn from(d: Duration) -> Self {
+        Self::new(d)
+    }
+}
+
+impl fmt::Debug for AtomicDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.load(Ordering::SeqCst).fmt(f)
+    }
+}
+
+// SAFETY: Zero represents `Duration::ZERO`.
+impl_zeroable!([] AtomicDuration);
+
+/// An atomic [`SystemTime`], stored as a number of nanoseconds since the
+/// Unix epoch in an [`AtomicU64`].
+///
+/// This can represent times from the Unix epoch (1970) until [`Self::MAX`]
+/// (in the year 2554). Times outside that range are stored as the nearest
+/// representable time, and [`fetch_add`](Self::fetch_add) saturates at
+/// [`Self::MAX`].
+///
+/// This is useful for timestamps published across threads, such as the time
+/// of the last heartbeat:
+///
+/// ```
+/// use atomic_int::AtomicSystemTime;
+/// use std::sync::atomic::Ordering;
+/// use std::time::{Duration, SystemTime};
+///
+/// static LAST_HEARTBEAT: AtomicSystemTime = AtomicSystemTime::UNIX_EPOCH;
+///
+/// // Heartbeats may be recorded out of order; keep the latest one.
+/// LAST_HEARTBEAT.fetch_max(SystemTime::now(), Ordering::Release);
+/// let elapsed = LAST_HEARTBEAT.load(Ordering::Acquire).elapsed();
+/// assert!(elapsed.unwrap_or_default() < Duration::from_secs(60));
+/// ```
+#[repr(transparent)]
+pub struct AtomicSystemTime {
+    nanos: AtomicU64,
+}
+
+impl AtomicSystemTime {
+    /// An atomic initialized to the Unix epoch, for use in `const` and
+    /// `static` items, as [`Self::new`] isn't `const`.
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub const UNIX_EPOCH: Self = Self {
+        nanos: AtomicU64::new(0),
+    };
+
+    /// The latest time that can be stored: [`u64::MAX`] nanoseconds after
+    /// the Unix epoch.
+    pub const MAX: Duration = from_nanos(u64::MAX);
+
+    /// Creates a new atomic time.
+    pub fn new(t: SystemTime) -> Self {
+        Self {
+            nanos: AtomicU64::new(time_to_nanos(t)),
+        }
+    }
+
+    /// Creates a new atomic containing the current time.
+    pub fn now() -> Self {
+        Self::new(SystemTime::now())
+    }
+
+    /// Consumes the atomic and returns the contained value.
+    pub fn into_inner(self) -> SystemTime {
+        time_from_nanos(self.nanos.into_inner())
+    }
+
+    /// Returns the underlying atomic, which holds the number of nanoseconds
+    /// since the Unix epoch.
+    pub fn as_nanos(&self) -> &AtomicU64 {
+        &self.nanos
+    }
+
+    /// Loads the value.
+    pub fn load(&self, order: Ordering) -> SystemTime {
+        time_from_nanos(self.nanos.load(order))
+    }
+
+    /// Stores a value.
+    pub fn store(&self, t: SystemTime, order: Ordering) {
+        self.nanos.store(time_to_nanos(t), order);
+    }
+
+    /// Stores a value, returning the previous value.
+    pub fn swap(&self, t: SystemTime, order: Ordering) -> SystemTime {
+        time_from_nanos(self.nanos.swap(time_to_nanos(t), order))
+    }
+
+    /// Stores a value if the current value is the same as `current`.
+    ///
+    /// See [`AtomicU64::compare_exchange`][cmpxchg]. Values are compared
+    /// after being rounded to the representable range.
+    ///
+    /// [cmpxchg]: core::sync::atomic::AtomicU64::compare_exchange
+    pub fn compare_exchange(
+        &self,
+        current: SystemTime,
+        new: SystemTime,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<SystemTime, SystemTime> {
+        self.nanos
+            .compare_exchange(
+                time_to_nanos(current),
+                time_to_nanos(new),
+                success,
+                failure,
+            )
+            .map(time_from_nanos)
+            .map_err(time_from_nanos)
+    }
+
+    /// Advances the current value by `d`, saturating at [`Self::MAX`], and
+    /// returns the previous value.
+    pub fn fetch_add(&self, d: Duration, order: Ordering) -> SystemTime {
+        time_from_nanos(self.nanos.fetch_saturating_add(to_nanos(d), order))
+    }
+
+    /// Maximum (the later time) with the current value, returning the
+    /// previous value.
+    pub fn fetch_max(&self, t: SystemTime, order: Ordering) -> SystemTime {
+        time_from_nanos(self.nanos.fetch_max(time_to_nanos(t), order))
+    }
+
+    /// Minimum (the earlier time) with the current value, returning the
+    /// previous value.
+    pub fn fetch_min(&self, t: SystemTime, order: Ordering) -> SystemTime {
+        time_from_nanos(self.nanos.fetch_min(time_to_nanos(t), order))
+    }
+}
+
+impl Default for AtomicSystemTime {
+    /// Returns an atomic containing the Unix epoch.
+    fn default() -> Self {
+        Self::UNIX_EPOCH
+    }
+}
+
+impl From<SystemTime> for AtomicSystemTime {
+    fn from(t: SystemTime) -> Self {
+        Self::new(t)
+    }
+}
+
+impl fmt::Debug for AtomicSystemTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.load(Ordering::SeqCst).fmt(f)
+    }
+}
+
+// SAFETY: Zero represents the Unix epoch.
+impl_zeroable!([] AtomicSystemTime);
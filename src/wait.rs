@@ -0,0 +1,221 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Blocking on the value of an atomic, with the feature `wait`.
+//!
+//! Atomics of every size (including fallback types) share a global table of
+//! 32-bit “epoch” counters, chosen by hashing the atomic’s address. A waiter
+//! blocks on the epoch with the operating system’s address-based wait
+//! primitive, and a notifier increments the epoch before waking the waiters.
+//! As unrelated atomics can share an epoch, every notification wakes all of
+//! the waiters on that epoch; the others simply wait again.
+
+use super::CachePadded;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+struct Bucket {
+    epoch: AtomicU32,
+    waiters: AtomicUsize,
+}
+
+const NUM_BUCKETS: usize = 64;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: CachePadded<Bucket> = CachePadded::new(Bucket {
+    epoch: AtomicU32::new(0),
+    waiters: AtomicUsize::new(0),
+});
+
+static BUCKETS: [CachePadded<Bucket>; NUM_BUCKETS] = [EMPTY; NUM_BUCKETS];
+
+/// Returns the bucket for the atomic at `addr`, using the same mix as the
+/// stripes of the feature `striped`.
+fn bucket(addr: usize) -> &'static Bucket {
+    let mut x = addr as u64;
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^= x >> 33;
+    &BUCKETS[(x % NUM_BUCKETS as u64) as usize]
+}
+
+/// Blocks while `should_wait` returns true, rechecking it whenever the
+/// atomic at `addr` is notified.
+pub fn wait<F>(addr: usize, mut should_wait: F)
+where
+    F: FnMut() -> bool,
+{
+    let bucket = bucket(addr);
+    bucket.waiters.fetch_add(1, Ordering::SeqCst);
+    loop {
+        // A notifier updates the value before incrementing the epoch, so if
+        // the epoch is unchanged when the OS compares it below, any
+        // notification comes after this point and will wake this thread.
+        let epoch = bucket.epoch.load(Ordering::SeqCst);
+        if !should_wait() {
+            break;
+        }
+        sys::wait(&bucket.epoch, epoch);
+    }
+    bucket.waiters.fetch_sub(1, Ordering::SeqCst);
+}
+
+/// Wakes the threads waiting on the atomic at `addr`.
+pub fn notify(addr: usize) {
+    let bucket = bucket(addr);
+    bucket.epoch.fetch_add(1, Ordering::SeqCst);
+    // Both this load and the increment of `waiters` in `wait` are `SeqCst`,
+    // so either this sees the waiter, or the waiter sees the new epoch.
+    if bucket.waiters.load(Ordering::SeqCst) != 0 {
+        sys::wake_all(&bucket.epoch);
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use core::ptr;
+    use core::sync::atomic::AtomicU32;
+
+    pub fn wait(futex: &AtomicU32, expected: u32) {
+        // Errors (like `EAGAIN` when the value has already changed, or
+        // `EINTR`) are spurious wakeups, which the caller handles.
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                futex as *const AtomicU32,
+                libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                expected,
+                ptr::null::<libc::timespec>(),
+            );
+        }
+    }
+
+    pub fn wake_all(futex: &AtomicU32) {
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                futex as *const AtomicU32,
+                libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+                i32::MAX,
+            );
+        }
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+mod sys {
+    use core::ffi::{c_int, c_void};
+    use core::sync::atomic::AtomicU32;
+
+    const UL_COMPARE_AND_WAIT: u32 = 1;
+    const ULF_WAKE_ALL: u32 = 0x100;
+
+    extern "C" {
+        fn __ulock_wait(
+            operation: u32,
+            addr: *mut c_void,
+            value: u64,
+            timeout: u32,
+        ) -> c_int;
+        fn __ulock_wake(
+            operation: u32,
+            addr: *mut c_void,
+            value: u64,
+        ) -> c_int;
+    }
+
+    pub fn wait(futex: &AtomicU32, expected: u32) {
+        unsafe {
+            __ulock_wait(
+                UL_COMPARE_AND_WAIT,
+                (futex as *const AtomicU32 as *mut AtomicU32).cast(),
+                expected.into(),
+                0,
+            );
+        }
+    }
+
+    pub fn wake_all(futex: &AtomicU32) {
+        unsafe {
+            __ulock_wake(
+                UL_COMPARE_AND_WAIT | ULF_WAKE_ALL,
+                (futex as *const AtomicU32 as *mut AtomicU32).cast(),
+                0,
+            );
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use core::ffi::c_void;
+    use core::sync::atomic::AtomicU32;
+
+    const INFINITE: u32 = u32::MAX;
+
+    #[link(name = "synchronization")]
+    extern "system" {
+        fn WaitOnAddress(
+            address: *const c_void,
+            compare_address: *const c_void,
+            address_size: usize,
+            milliseconds: u32,
+        ) -> i32;
+        fn WakeByAddressAll(address: *const c_void);
+    }
+
+    pub fn wait(futex: &AtomicU32, expected: u32) {
+        unsafe {
+            WaitOnAddress(
+                (futex as *const AtomicU32).cast(),
+                (&expected as *const u32).cast(),
+                4,
+                INFINITE,
+            );
+        }
+    }
+
+    pub fn wake_all(futex: &AtomicU32) {
+        unsafe {
+            WakeByAddressAll((futex as *const AtomicU32).cast());
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    windows,
+)))]
+mod sys {
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    /// Without an OS primitive, waiting is a busy loop.
+    pub fn wait(futex: &AtomicU32, expected: u32) {
+        while futex.load(Ordering::Relaxed) == expected {
+            #[cfg(feature = "std")]
+            std::thread::yield_now();
+            #[cfg(not(feature = "std"))]
+            core::hint::spin_loop();
+        }
+    }
+
+    pub fn wake_all(_: &AtomicU32) {}
+}
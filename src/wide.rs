@@ -0,0 +1,685 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::ext::{PrimInt, sealed};
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign};
+use core::ops::{BitXor, BitXorAssign, Not, Shl, Shr};
+
+/// Multiplies two 256-bit integers, given as 64-bit limbs (least significant
+/// first). Returns the low 256 bits of the product, and whether any of the
+/// high 256 bits are set.
+fn mul_limbs(a: [u64; 4], b: [u64; 4]) -> ([u64; 4], bool) {
+    let mut out = [0_u64; 8];
+    for i in 0..4 {
+        let mut carry = 0_u128;
+        for j in 0..4 {
+            // Cannot overflow: (2^64 - 1)^2 + 2 * (2^64 - 1) = 2^128 - 1.
+            let t = a[i] as u128 * b[j] as u128 + out[i + j] as u128 + carry;
+            out[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        out[i + 4] = carry as u64;
+    }
+    let high = out[4..].iter().any(|&w| w != 0);
+    ([out[0], out[1], out[2], out[3]], high)
+}
+
+/// Divides the 256-bit integer `limbs` by `d` in place, returning the
+/// remainder.
+fn div_rem_limbs(limbs: &mut [u64; 4], d: u64) -> u64 {
+    let mut rem = 0_u128;
+    for limb in limbs.iter_mut().rev() {
+        let cur = (rem << 64) | *limb as u128;
+        *limb = (cur / d as u128) as u64;
+        rem = cur % d as u128;
+    }
+    rem as u64
+}
+
+/// Formats the magnitude `limbs` in base 10, with a minus sign if
+/// `nonneg` is false.
+fn fmt_decimal(
+    mut limbs: [u64; 4],
+    nonneg: bool,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    // 2^256 has 78 decimal digits.
+    let mut buf = [0_u8; 78];
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + div_rem_limbs(&mut limbs, 10) as u8;
+        if limbs == [0; 4] {
+            break;
+        }
+    }
+    f.pad_integral(nonneg, "", core::str::from_utf8(&buf[i..]).unwrap())
+}
+
+/// Formats the bits `limbs` in base 16.
+fn fmt_hex(
+    limbs: [u64; 4],
+    digits: &[u8; 16],
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    let mut buf = [0_u8; 64];
+    let mut i = buf.len();
+    for limb in limbs {
+        for shift in (0..64).step_by(4) {
+            i -= 1;
+            buf[i] = digits[(limb >> shift) as usize & 0xf];
+        }
+    }
+    let first = buf.iter().position(|&d| d != b'0').unwrap_or(63);
+    f.pad_integral(true, "0x", core::str::from_utf8(&buf[first..]).unwrap())
+}
+
+macro_rules! impl_bit_op {
+    (
+        $name:ident,
+        $trait:ident::$fn:ident,
+        $assign:ident::$afn:ident,
+        $op:tt
+    ) => {
+        impl $trait for $name {
+            type Output = Self;
+
+            fn $fn(self, rhs: Self) -> Self {
+                Self {
+                    lo: self.lo $op rhs.lo,
+                    hi: self.hi $op rhs.hi,
+                }
+            }
+        }
+
+        impl $assign for $name {
+            fn $afn(&mut self, rhs: Self) {
+                *self = *self $op rhs;
+            }
+        }
+    };
+}
+
+macro_rules! define_wide {
+    (
+        $name:ident,
+        $nonzero:ident,
+        $hi:ident,
+        $signed:ident,
+        $doc:literal
+    ) => {
+        #[doc = $doc]
+        ///
+        /// This type supports only the operations needed by its atomic
+        /// type, conversions, and formatting.
+        #[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+        pub struct $name {
+            lo: u128,
+            hi: u128,
+        }
+
+        impl $name {
+            /// The size of this type in bits.
+            pub const BITS: u32 = 256;
+
+            /// The value 0.
+            pub const ZERO: Self = Self::from_words(0, 0);
+
+            /// The value 1.
+            pub const ONE: Self = Self::from_words(0, 1);
+
+            /// Creates an integer from its high and low 128 bits.
+            pub const fn from_words(hi: $hi, lo: u128) -> Self {
+                Self {
+                    lo,
+                    hi: hi as u128,
+                }
+            }
+
+            /// Returns the high and low 128 bits of the integer.
+            pub const fn to_words(self) -> ($hi, u128) {
+                (self.hi as $hi, self.lo)
+            }
+
+            fn limbs(self) -> [u64; 4] {
+                [
+                    self.lo as u64,
+                    (self.lo >> 64) as u64,
+                    self.hi as u64,
+                    (self.hi >> 64) as u64,
+                ]
+            }
+
+            fn from_limbs(l: [u64; 4]) -> Self {
+                Self {
+                    lo: l[0] as u128 | (l[1] as u128) << 64,
+                    hi: l[2] as u128 | (l[3] as u128) << 64,
+                }
+            }
+
+            /// Returns the number of ones in the binary representation.
+            pub const fn count_ones(self) -> u32 {
+                self.lo.count_ones() + self.hi.count_ones()
+            }
+
+            /// Wrapping (modular) addition.
+            pub const fn wrapping_add(self, rhs: Self) -> Self {
+                let (lo, carry) = self.lo.overflowing_add(rhs.lo);
+                let hi = self.hi.wrapping_add(rhs.hi);
+                Self {
+                    lo,
+                    hi: hi.wrapping_add(carry as u128),
+                }
+            }
+
+            /// Wrapping (modular) subtraction.
+            pub const fn wrapping_sub(self, rhs: Self) -> Self {
+                let (lo, borrow) = self.lo.overflowing_sub(rhs.lo);
+                let hi = self.hi.wrapping_sub(rhs.hi);
+                Self {
+                    lo,
+                    hi: hi.wrapping_sub(borrow as u128),
+                }
+            }
+
+            /// Wrapping (modular) multiplication.
+            pub fn wrapping_mul(self, rhs: Self) -> Self {
+                Self::from_limbs(mul_limbs(self.limbs(), rhs.limbs()).0)
+            }
+
+            /// Checked addition, returning [`None`] on overflow.
+            pub fn checked_add(self, rhs: Self) -> Option<Self> {
+                let (value, overflow) = self.overflowing_add(rhs);
+                (!overflow).then_some(value)
+            }
+
+            /// Checked subtraction, returning [`None`] on overflow.
+            pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+                let (value, overflow) = self.overflowing_sub(rhs);
+                (!overflow).then_some(value)
+            }
+        }
+
+        impl Not for $name {
+            type Output = Self;
+
+            fn not(self) -> Self {
+                Self {
+                    lo: !self.lo,
+                    hi: !self.hi,
+                }
+            }
+        }
+
+        impl_bit_op!($name, BitAnd::bitand, BitAndAssign::bitand_assign, &);
+        impl_bit_op!($name, BitOr::bitor, BitOrAssign::bitor_assign, |);
+        impl_bit_op!($name, BitXor::bitxor, BitXorAssign::bitxor_assign, ^);
+
+        impl Shl<u32> for $name {
+            type Output = Self;
+
+            /// Shifts left by `rhs` bits, which must be less than 256. Like
+            /// the primitive integers, this panics otherwise with debug
+            /// assertions enabled, and masks `rhs` to its low 8 bits without
+            /// them.
+            fn shl(self, rhs: u32) -> Self {
+                debug_assert!(rhs < 256, "shift left with overflow");
+                match rhs & 255 {
+                    0 => self,
+                    n @ 1..=127 => Self {
+                        lo: self.lo << n,
+                        hi: self.hi << n | self.lo >> (128 - n),
+                    },
+                    n => Self {
+                        lo: 0,
+                        hi: self.lo << (n - 128),
+                    },
+                }
+            }
+        }
+
+        impl Shr<u32> for $name {
+            type Output = Self;
+
+            /// Shifts right by `rhs` bits, which must be less than 256 (see
+            /// [`Shl`]). Like the primitive integers, this is a logical shift
+            /// for unsigned types and an arithmetic shift for signed types.
+            fn shr(self, rhs: u32) -> Self {
+                debug_assert!(rhs < 256, "shift right with overflow");
+                let hi = self.hi as $hi;
+                match rhs & 255 {
+                    0 => self,
+                    n @ 1..=127 => Self {
+                        lo: self.lo >> n | self.hi << (128 - n),
+                        hi: (hi >> n) as u128,
+                    },
+                    n => Self {
+                        lo: (hi >> (n - 128)) as u128,
+                        // Zero, or the sign bit repeated for signed types.
+                        hi: (hi >> 64 >> 64) as u128,
+                    },
+                }
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.to_words().cmp(&other.to_words())
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(self, f)
+            }
+        }
+
+        impl fmt::LowerHex for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_hex(self.limbs(), b"0123456789abcdef", f)
+            }
+        }
+
+        impl fmt::UpperHex for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_hex(self.limbs(), b"0123456789ABCDEF", f)
+            }
+        }
+
+        #[doc = concat!(
+            "A [`", stringify!($name), "`] that is known not to equal zero.",
+        )]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $nonzero($name);
+
+        impl $nonzero {
+            /// Creates a nonzero integer, returning [`None`] if `v` is 0.
+            pub fn new(v: $name) -> Option<Self> {
+                (v != $name::ZERO).then(|| Self(v))
+            }
+
+            /// Returns the value as a primitive type.
+            pub const fn get(self) -> $name {
+                self.0
+            }
+        }
+
+        impl fmt::Debug for $nonzero {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.0, f)
+            }
+        }
+
+        impl sealed::Sealed for $name {}
+
+        impl PrimInt for $name {
+            const BITS: u32 = 256;
+            const ZERO: Self = Self::ZERO;
+            const ONE: Self = Self::ONE;
+            const MIN: Self = Self::MIN;
+            const MAX: Self = Self::MAX;
+
+            fn wrapping_add(self, rhs: Self) -> Self {
+                Self::wrapping_add(self, rhs)
+            }
+
+            fn wrapping_sub(self, rhs: Self) -> Self {
+                Self::wrapping_sub(self, rhs)
+            }
+
+            fn wrapping_mul(self, rhs: Self) -> Self {
+                Self::wrapping_mul(self, rhs)
+            }
+
+            fn saturating_add(self, rhs: Self) -> Self {
+                Self::saturating_add(self, rhs)
+            }
+
+            fn saturating_sub(self, rhs: Self) -> Self {
+                Self::saturating_sub(self, rhs)
+            }
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                Self::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                Self::checked_sub(self, rhs)
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                Self::checked_mul(self, rhs)
+            }
+
+            fn wrapping_div(self, rhs: Self) -> Self {
+                Self::wrapping_div(self, rhs)
+            }
+
+            fn wrapping_rem(self, rhs: Self) -> Self {
+                Self::wrapping_rem(self, rhs)
+            }
+
+            fn count_ones(self) -> u32 {
+                Self::count_ones(self)
+            }
+
+            type NonZero = $nonzero;
+
+            fn to_nonzero(self) -> Option<$nonzero> {
+                $nonzero::new(self)
+            }
+
+            fn from_nonzero(n: $nonzero) -> Self {
+                n.get()
+            }
+
+            type Signed = $signed;
+
+            fn from_signed_bits(v: $signed) -> Self {
+                Self { lo: v.lo, hi: v.hi }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "serde")))]
+        impl serde::Serialize for $name {
+            /// Serializes the integer as the tuple returned by
+            #[doc = concat!("[`", stringify!($name), "::to_words`].")]
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serde::Serialize::serialize(&self.to_words(), serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "serde")))]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                <($hi, u128) as serde::Deserialize<'de>>::deserialize(
+                    deserializer,
+                )
+                .map(|(hi, lo)| Self::from_words(hi, lo))
+            }
+        }
+
+        // SAFETY: Zero is a valid integer.
+        impl_zeroable!([] $name);
+    };
+}
+
+define_wide!(
+    U256,
+    NonZeroU256,
+    u128,
+    I256,
+    "A 256-bit unsigned integer, the value type of [`AtomicU256`].\n\n\
+     [`AtomicU256`]: crate::AtomicU256"
+);
+
+define_wide!(
+    I256,
+    NonZeroI256,
+    i128,
+    I256,
+    "A 256-bit signed integer, the value type of [`AtomicI256`].\n\n\
+     [`AtomicI256`]: crate::AtomicI256"
+);
+
+impl U256 {
+    /// The smallest value, 0.
+    pub const MIN: Self = Self::ZERO;
+
+    /// The largest value, 2<sup>256</sup> − 1.
+    pub const MAX: Self = Self::from_words(u128::MAX, u128::MAX);
+
+    fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let value = self.wrapping_add(rhs);
+        (value, value < self)
+    }
+
+    fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        (self.wrapping_sub(rhs), rhs > self)
+    }
+
+    /// Checked multiplication, returning [`None`] on overflow.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let (limbs, overflow) = mul_limbs(self.limbs(), rhs.limbs());
+        (!overflow).then(|| Self::from_limbs(limbs))
+    }
+
+    /// Saturating addition.
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).unwrap_or(Self::MAX)
+    }
+
+    /// Saturating subtraction.
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs).unwrap_or(Self::MIN)
+    }
+
+    /// Returns the quotient and remainder of `self / rhs`, by binary long
+    /// division.
+    fn div_rem(self, rhs: Self) -> (Self, Self) {
+        assert!(rhs != Self::ZERO, "attempt to divide by zero");
+        if self.hi == 0 && rhs.hi == 0 {
+            let (lo, d) = (self.lo, rhs.lo);
+            return (Self::from_words(0, lo / d), Self::from_words(0, lo % d));
+        }
+        let mut quot = Self::ZERO;
+        let mut rem = Self::ZERO;
+        for i in (0..256).rev() {
+            rem = (rem << 1) | ((self >> i) & Self::ONE);
+            quot = quot << 1;
+            if rem >= rhs {
+                rem = rem.wrapping_sub(rhs);
+                quot.lo |= 1;
+            }
+        }
+        (quot, rem)
+    }
+
+    /// Wrapping division, which is the same as normal division for
+    /// unsigned integers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rhs` is 0.
+    pub fn wrapping_div(self, rhs: Self) -> Self {
+        self.div_rem(rhs).0
+    }
+
+    /// Wrapping remainder, which is the same as the normal remainder for
+    /// unsigned integers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rhs` is 0.
+    pub fn wrapping_rem(self, rhs: Self) -> Self {
+        self.div_rem(rhs).1
+    }
+}
+
+impl I256 {
+    /// The smallest value, −2<sup>255</sup>.
+    pub const MIN: Self = Self::from_words(i128::MIN, 0);
+
+    /// The largest value, 2<sup>255</sup> − 1.
+    pub const MAX: Self = Self::from_words(i128::MAX, u128::MAX);
+
+    /// Returns whether the value is negative.
+    pub const fn is_negative(self) -> bool {
+        (self.hi as i128) < 0
+    }
+
+    /// Wrapping (modular) negation.
+    pub const fn wrapping_neg(self) -> Self {
+        Self::ZERO.wrapping_sub(self)
+    }
+
+    fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let value = self.wrapping_add(rhs);
+        let overflow = self.is_negative() == rhs.is_negative()
+            && value.is_negative() != self.is_negative();
+        (value, overflow)
+    }
+
+    fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let value = self.wrapping_sub(rhs);
+        let overflow = self.is_negative() != rhs.is_negative()
+            && value.is_negative() != self.is_negative();
+        (value, overflow)
+    }
+
+    /// Returns the magnitude of the value as unsigned bits (so that
+    /// [`Self::MIN`] yields 2<sup>255</sup>).
+    fn unsigned_abs_limbs(self) -> [u64; 4] {
+        if self.is_negative() {
+            self.wrapping_neg().limbs()
+        } else {
+            self.limbs()
+        }
+    }
+
+    /// Checked multiplication, returning [`None`] on overflow.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let (limbs, overflow) =
+            mul_limbs(self.unsigned_abs_limbs(), rhs.unsigned_abs_limbs());
+        if overflow {
+            return None;
+        }
+        let magnitude = Self::from_limbs(limbs);
+        if self.is_negative() != rhs.is_negative() {
+            // Valid if the magnitude is at most 2^255.
+            let value = magnitude.wrapping_neg();
+            (magnitude == Self::ZERO || value.is_negative()).then_some(value)
+        } else {
+            (!magnitude.is_negative()).then_some(magnitude)
+        }
+    }
+
+    /// Saturating addition.
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).unwrap_or(if rhs.is_negative() {
+            Self::MIN
+        } else {
+            Self::MAX
+        })
+    }
+
+    /// Saturating subtraction.
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs).unwrap_or(if rhs.is_negative() {
+            Self::MAX
+        } else {
+            Self::MIN
+        })
+    }
+
+    /// Returns the quotient and remainder of `self / rhs`, rounding toward
+    /// zero like the primitive signed integers.
+    fn div_rem(self, rhs: Self) -> (Self, Self) {
+        let n = U256::from_limbs(self.unsigned_abs_limbs());
+        let d = U256::from_limbs(rhs.unsigned_abs_limbs());
+        let (quot, rem) = n.div_rem(d);
+        let quot = Self::from_limbs(quot.limbs());
+        let rem = Self::from_limbs(rem.limbs());
+        // The remainder has the sign of the dividend.
+        let rem = if self.is_negative() {
+            rem.wrapping_neg()
+        } else {
+            rem
+        };
+        if self.is_negative() != rhs.is_negative() {
+            (quot.wrapping_neg(), rem)
+        } else {
+            (quot, rem)
+        }
+    }
+
+    /// Wrapping division, which wraps only for [`Self::MIN`] divided by −1,
+    /// returning [`Self::MIN`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rhs` is 0.
+    pub fn wrapping_div(self, rhs: Self) -> Self {
+        self.div_rem(rhs).0
+    }
+
+    /// Wrapping remainder, which is 0 for [`Self::MIN`] divided by −1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rhs` is 0.
+    pub fn wrapping_rem(self, rhs: Self) -> Self {
+        self.div_rem(rhs).1
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_decimal(self.limbs(), true, f)
+    }
+}
+
+impl fmt::Display for I256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_decimal(self.unsigned_abs_limbs(), !self.is_negative(), f)
+    }
+}
+
+macro_rules! impl_from {
+    ($wide:ident, $($int:ident),*) => {$(
+        impl From<$int> for $wide {
+            fn from(v: $int) -> Self {
+                // Sign-extends signed integers.
+                let lo = v as i128 as u128;
+                let hi = if (v as i128) < 0 { u128::MAX } else { 0 };
+                Self { lo, hi }
+            }
+        }
+    )*};
+}
+
+impl_from!(U256, u8, u16, u32, u64, bool);
+impl_from!(I256, i8, i16, i32, i64, i128, u8, u16, u32, u64, bool);
+
+impl From<u128> for U256 {
+    fn from(v: u128) -> Self {
+        Self::from_words(0, v)
+    }
+}
+
+impl From<u128> for I256 {
+    fn from(v: u128) -> Self {
+        Self::from_words(0, v)
+    }
+}
@@ -0,0 +1,106 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Checks which ARM targets known to `rustc` get the `ldrexd`/`strexd`
+//! implementation of the 64-bit atomics with the feature `asm-atomics` (see
+//! build/asm_atomic64.rs).
+
+#![cfg(not(loom))]
+
+use std::process::Command;
+
+#[path = "../build/asm_atomic64.rs"]
+mod asm_atomic64;
+
+/// Built-in targets without 64-bit atomics that have `ldrexd` and `strexd`.
+const EXPECTED: &[&str] = &["armv6k-nintendo-3ds"];
+
+fn rustc(args: &[&str]) -> String {
+    let output = Command::new(option_env!("RUSTC").unwrap_or("rustc"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn cfg_values(cfg: &str, name: &str) -> Vec<String> {
+    let prefix = format!("{name}=\"");
+    cfg.lines()
+        .filter_map(|line| line.strip_prefix(&prefix)?.strip_suffix('"'))
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn builtin_targets() {
+    let list = rustc(&["--print", "target-list"]);
+    let targets = list.lines().filter(|t| {
+        (t.starts_with("arm") && !t.starts_with("arm64"))
+            || t.starts_with("thumb")
+    });
+    let mut found = Vec::new();
+    let mut known = Vec::new();
+    for target in targets {
+        let cfg = rustc(&["--print", "cfg", "--target", target]);
+        let arch = cfg_values(&cfg, "target_arch");
+        if arch.first().map(String::as_str) != Some("arm") {
+            continue;
+        }
+        if EXPECTED.contains(&target) {
+            known.push(target);
+        }
+        if asm_atomic64::applies(
+            target,
+            "arm",
+            &cfg_values(&cfg, "target_has_atomic"),
+            &cfg_values(&cfg, "target_feature"),
+        ) {
+            found.push(target);
+        }
+    }
+    assert_eq!(found, known);
+}
+
+#[test]
+fn custom_targets() {
+    let applies = |target: &str, features: &[&str]| {
+        let features: Vec<_> =
+            features.iter().map(|&s| s.to_owned()).collect();
+        asm_atomic64::applies(target, "arm", &["32".to_owned()], &features)
+    };
+    assert!(applies("armv7-custom-none-eabi", &[]));
+    assert!(applies("/path/to/armv7a-custom.json", &[]));
+    assert!(applies("thumbv7a-custom-none-eabi", &[]));
+    assert!(applies("armebv7r-custom-none-eabi", &[]));
+    assert!(applies("armv6k-custom-none-eabi", &[]));
+    assert!(!applies("thumbv6k-custom-none-eabi", &[]));
+    assert!(!applies("armv6-custom-none-eabi", &[]));
+    assert!(!applies("armv5te-custom-none-eabi", &[]));
+    assert!(!applies("thumbv7m-custom-none-eabi", &[]));
+    assert!(!applies("thumbv7em-custom-none-eabi", &[]));
+    assert!(!applies("thumbv8m.main-custom-none-eabi", &[]));
+    assert!(applies("custom", &["v7"]));
+    assert!(!applies("armv7-custom-none-eabi", &["v7", "mclass"]));
+    assert!(!asm_atomic64::applies(
+        "armv7-custom-none-eabi",
+        "arm",
+        &["32".to_owned(), "64".to_owned()],
+        &[],
+    ));
+}
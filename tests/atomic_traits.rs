@@ -0,0 +1,131 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Checks that code generic over the traits of `atomic-traits` works with
+//! the fallback types. `AtomicU128` is one of this crate's own types on every
+//! target (lock-free with `asm-atomics` on some), unless it comes from
+//! `portable-atomic`.
+
+#![cfg(all(feature = "atomic-traits", not(feature = "portable-atomic")))]
+
+use atomic_int::AtomicU128;
+use atomic_traits::{Atomic, Bitwise, NumOps};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+
+/// Counts events and records which of them have occurred, as a bit set.
+struct Events<A>(A, A);
+
+impl<A> Events<A>
+where
+    A: Atomic + NumOps + Bitwise,
+    <A as Atomic>::Type: From<u8>,
+{
+    fn new() -> Self {
+        Self(A::new(0.into()), A::new(0.into()))
+    }
+
+    fn record(&self, bit: <A as Atomic>::Type) {
+        self.0.fetch_add(1.into(), Ordering::Relaxed);
+        self.1.fetch_or(bit, Ordering::Relaxed);
+    }
+
+    fn into_inner(self) -> (<A as Atomic>::Type, <A as Atomic>::Type) {
+        (self.0.into_inner(), self.1.into_inner())
+    }
+}
+
+fn record_from_threads<A>() -> (<A as Atomic>::Type, <A as Atomic>::Type)
+where
+    A: Atomic + NumOps + Bitwise + Sync,
+    <A as Atomic>::Type: From<u8>,
+{
+    let events = Events::<A>::new();
+    thread::scope(|s| {
+        for i in 0..8_u8 {
+            let events = &events;
+            s.spawn(move || {
+                for _ in 0..100 {
+                    events.record((1 << i).into());
+                }
+            });
+        }
+    });
+    events.into_inner()
+}
+
+#[test]
+fn fallback_matches_builtin() {
+    assert_eq!(record_from_threads::<AtomicU128>(), (800, 0xff));
+    assert_eq!(record_from_threads::<AtomicU32>(), (800, 0xff));
+}
+
+#[test]
+fn atomic_methods() {
+    fn check<A>(a: A)
+    where
+        A: Atomic<Type = u128> + NumOps + Bitwise,
+    {
+        assert_eq!(a.swap(5, Ordering::AcqRel), 1);
+        assert_eq!(
+            a.compare_exchange(5, 6, Ordering::AcqRel, Ordering::Acquire),
+            Ok(5),
+        );
+        assert_eq!(
+            a.compare_exchange(5, 7, Ordering::AcqRel, Ordering::Acquire),
+            Err(6),
+        );
+        #[allow(deprecated)]
+        let prev = a.compare_and_swap(6, 8, Ordering::AcqRel);
+        assert_eq!(prev, 6);
+        assert_eq!(a.fetch_max(3, Ordering::Relaxed), 8);
+        assert_eq!(
+            a.fetch_update(Ordering::AcqRel, Ordering::Acquire, |v| {
+                v.checked_sub(10)
+            }),
+            Err(8),
+        );
+        assert_eq!(a.fetch_nand(u128::MAX, Ordering::Relaxed), 8);
+        assert_eq!(a.load(Ordering::Relaxed), !8);
+    }
+    check(AtomicU128::new(1));
+}
+
+#[cfg(feature = "cell")]
+#[test]
+fn cell_ptr() {
+    use atomic_traits::fetch::Update;
+
+    fn swap_out<A>(a: &A, new: <A as Atomic>::Type) -> <A as Atomic>::Type
+    where
+        A: Atomic + Update<Type = <A as Atomic>::Type>,
+        <A as Atomic>::Type: Copy,
+    {
+        match a
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |_| Some(new))
+        {
+            Ok(v) | Err(v) => v,
+        }
+    }
+    let mut values = [1_u8, 2];
+    let first = values.as_mut_ptr();
+    let second = first.wrapping_add(1);
+    let ptr = atomic_int::cell::AtomicPtr::new(first);
+    assert_eq!(swap_out(&ptr, second), first);
+    assert_eq!(Atomic::into_inner(ptr), second);
+}
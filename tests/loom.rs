@@ -0,0 +1,126 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Model checks of the fallback types with [loom].
+//!
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --release --test loom`.
+//! `AtomicU128` is used because it is a fallback type on most targets; the
+//! tests are skipped where it isn't.
+//!
+//! [loom]: https://docs.rs/loom/0.7
+
+#![cfg(all(loom, fallback_128))]
+
+use atomic_int::AtomicU128;
+use loom::cell::UnsafeCell;
+use loom::sync::Arc;
+use loom::thread;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+#[test]
+fn compare_exchange() {
+    loom::model(|| {
+        let a = Arc::new(AtomicU128::new(0));
+        let threads: Vec<_> = (1..=2)
+            .map(|id| {
+                let a = a.clone();
+                thread::spawn(move || {
+                    a.compare_exchange(0, id, Relaxed, Relaxed).is_ok()
+                })
+            })
+            .collect();
+        let won: Vec<_> =
+            threads.into_iter().map(|t| t.join().unwrap()).collect();
+        assert_eq!(won.iter().filter(|&&w| w).count(), 1);
+        let winner = if won[0] {
+            1
+        } else {
+            2
+        };
+        assert_eq!(a.load(Relaxed), winner);
+    });
+}
+
+#[test]
+fn fetch_update() {
+    loom::model(|| {
+        let a = Arc::new(AtomicU128::new(0));
+        let t = {
+            let a = a.clone();
+            thread::spawn(move || {
+                a.fetch_update(Relaxed, Relaxed, |v| Some(v + 1)).unwrap()
+            })
+        };
+        let prev = a.fetch_update(Relaxed, Relaxed, |v| Some(v + 1)).unwrap();
+        let other = t.join().unwrap();
+        assert_eq!(prev + other, 1);
+        assert_eq!(a.load(Relaxed), 2);
+    });
+}
+
+#[test]
+fn fetch_update_publishes() {
+    loom::model(|| {
+        let data = Arc::new(UnsafeCell::new(0));
+        let flag = Arc::new(AtomicU128::new(0));
+        let t = {
+            let (data, flag) = (data.clone(), flag.clone());
+            thread::spawn(move || {
+                // SAFETY: `data` isn't read until `flag` is set.
+                data.with_mut(|p| unsafe { *p = 42 });
+                flag.fetch_update(Release, Relaxed, |_| Some(1)).unwrap();
+            })
+        };
+        let result = flag.fetch_update(Acquire, Acquire, |v| {
+            if v == 1 {
+                Some(2)
+            } else {
+                None
+            }
+        });
+        if result.is_ok() {
+            // SAFETY: The writer has finished writing `data`, and the
+            // acquire operation above synchronizes with its release.
+            assert_eq!(data.with(|p| unsafe { *p }), 42);
+        }
+        t.join().unwrap();
+    });
+}
+
+/// Store buffering: with `conservative-ordering`, even relaxed operations on
+/// different atomics take part in a single total order, so at least one
+/// thread must see the other's store.
+#[cfg(feature = "conservative-ordering")]
+#[test]
+fn store_buffering() {
+    loom::model(|| {
+        let x = Arc::new(AtomicU128::new(0));
+        let y = Arc::new(AtomicU128::new(0));
+        let t = {
+            let (x, y) = (x.clone(), y.clone());
+            thread::spawn(move || {
+                x.store(1, Relaxed);
+                y.load(Relaxed)
+            })
+        };
+        y.store(1, Relaxed);
+        let r2 = x.load(Relaxed);
+        let r1 = t.join().unwrap();
+        assert!(r1 == 1 || r2 == 1);
+    });
+}
@@ -0,0 +1,62 @@
+/*
+ * Copyright 2023 taylor.fish <contact@taylor.fish>
+ *
+ * This file is part of atomic-int.
+ *
+ * atomic-int is licensed under the Apache License, Version 2.0
+ * (the "License"); you may not use atomic-int except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Checks that the crate refuses to build on targets without `AtomicBool`
+//! support (like `thumbv6m` and `riscv32i`) unless the feature
+//! `critical-section` or `portable-atomic` is enabled. Such targets are
+//! simulated by building with `--cfg no_atomic_bool` (see build.rs).
+
+#![cfg(not(loom))]
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn check(features: &str) -> Output {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["check", "--lib", "--quiet", "--features", features])
+        .arg("--target-dir")
+        .arg(target_dir.join("no-atomic-bool"))
+        .env("RUSTFLAGS", "--cfg no_atomic_bool")
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn spinlock_fails() {
+    let output = check("");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("which this target lacks"), "{}", stderr);
+}
+
+#[test]
+fn critical_section_builds() {
+    let output = check("critical-section");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+}
+
+#[test]
+fn portable_atomic_builds() {
+    let output = check("portable-atomic");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+}